@@ -0,0 +1,952 @@
+use crate::cwslice::UnsafeSlice;
+use crate::elementwise_ops::{rust_binary, rust_unary, BinOp, UnaryOp};
+use crate::index_ops::{parse_key, rust_getitem, rust_setitem};
+use crate::matrix_mult::rust_array_matmul;
+use crate::matrix_ops::{rust_flat_transpose, rust_transpose_inplace};
+use crate::npy_io::{extract_path, rust_tofile, Dtype};
+use crate::sparse_ops::as_sparse;
+use pyo3::exceptions::{PyIOError, PyIndexError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyList, PySequence, PyString, PyTuple};
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
+use std::sync::Arc;
+
+/// Flat storage shared by an array and all of the views taken from it:
+/// either an owned vector or a region of a memory-mapped file.
+pub struct Buffer {
+    ptr: *mut f64,
+    len: usize,
+    writable: bool,
+    // Whatever `ptr` points into; never touched except to keep it alive.
+    _vec: Vec<f64>,
+    map: Option<Mapping>,
+}
+unsafe impl Send for Buffer {}
+unsafe impl Sync for Buffer {}
+
+/// How a file is mapped: `ReadOnly` and `CopyOnWrite` mappings are private,
+/// so writes (which `ReadOnly` arrays refuse anyway) never reach the file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MapMode {
+    ReadOnly,
+    ReadWrite,
+    CopyOnWrite,
+}
+
+struct Mapping {
+    addr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.addr, self.len) };
+    }
+}
+
+impl Buffer {
+    fn new(mut data: Vec<f64>) -> Self {
+        Self {
+            ptr: data.as_mut_ptr(),
+            len: data.len(),
+            writable: true,
+            _vec: data,
+            map: None,
+        }
+    }
+
+    /// Maps `len` native-endian `f64`s of `file` starting at byte `offset`,
+    /// which must be a multiple of 8. The file must be long enough.
+    fn map(file: &std::fs::File, offset: usize, len: usize, mode: MapMode) -> Result<Self, String> {
+        use std::os::unix::io::AsRawFd;
+        if !offset.is_multiple_of(std::mem::align_of::<f64>()) {
+            return Err("memmap offset must be a multiple of 8 bytes".to_string());
+        }
+        if len == 0 {
+            let mut buf = Buffer::new(vec![]);
+            buf.writable = mode != MapMode::ReadOnly;
+            return Ok(buf);
+        }
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let start = offset / page * page;
+        let map_len = offset - start + len * std::mem::size_of::<f64>();
+        let flags = match mode {
+            MapMode::ReadWrite => libc::MAP_SHARED,
+            MapMode::ReadOnly | MapMode::CopyOnWrite => libc::MAP_PRIVATE,
+        };
+        let addr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                map_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                file.as_raw_fd(),
+                start as libc::off_t,
+            )
+        };
+        if addr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(Self {
+            ptr: unsafe { (addr as *mut u8).add(offset - start) as *mut f64 },
+            len,
+            writable: mode != MapMode::ReadOnly,
+            _vec: vec![],
+            map: Some(Mapping { addr, len: map_len }),
+        })
+    }
+
+    fn as_slice(&self) -> &[f64] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// SAFETY: Callers must not write to the same index from two threads and
+    /// must not hold a shared slice of the buffer while writing.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn as_mut_slice(&self) -> &mut [f64] {
+        std::slice::from_raw_parts_mut(self.ptr, self.len)
+    }
+
+    /// Writes a shared mapping back to its file; a no-op otherwise.
+    fn flush(&self) -> Result<(), String> {
+        match &self.map {
+            Some(m) if unsafe { libc::msync(m.addr, m.len, libc::MS_SYNC) } != 0 => {
+                Err(std::io::Error::last_os_error().to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// N-dimensional array of `f64` described by a shape and element strides over
+/// a shared buffer. Transposes and other axis shuffles only rewrite the
+/// strides, so they never copy; `to_vec`/`to_contiguous` materialize the
+/// elements in row-major order when a kernel needs a flat buffer.
+#[pyclass(name = "Array")]
+#[derive(Clone)]
+pub struct Array {
+    buf: Arc<Buffer>,
+    shape: Vec<usize>,
+    strides: Vec<isize>,
+    offset: usize,
+}
+
+pub fn row_major_strides(shape: &[usize]) -> Vec<isize> {
+    let mut strides = vec![1isize; shape.len()];
+    for i in (0..shape.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * shape[i + 1].max(1) as isize;
+    }
+    strides
+}
+
+pub fn normalize_axis(axis: isize, ndim: usize) -> Result<usize, String> {
+    let n = ndim as isize;
+    if axis < -n || axis >= n {
+        Err(format!(
+            "axis {} is out of bounds for array of dimension {}",
+            axis, ndim
+        ))
+    } else if axis < 0 {
+        Ok((axis + n) as usize)
+    } else {
+        Ok(axis as usize)
+    }
+}
+
+impl Array {
+    pub fn from_vec(data: Vec<f64>, shape: Vec<usize>) -> Self {
+        assert_eq!(data.len(), shape.iter().product::<usize>());
+        let strides = row_major_strides(&shape);
+        Self {
+            buf: Arc::new(Buffer::new(data)),
+            shape,
+            strides,
+            offset: 0,
+        }
+    }
+
+    /// Array over `shape.product()` doubles of `file` starting at byte
+    /// `offset`, without reading them.
+    pub fn mapped(
+        file: &std::fs::File,
+        offset: usize,
+        shape: Vec<usize>,
+        mode: MapMode,
+    ) -> Result<Self, String> {
+        let buf = Buffer::map(file, offset, shape.iter().product(), mode)?;
+        let strides = row_major_strides(&shape);
+        Ok(Self {
+            buf: Arc::new(buf),
+            shape,
+            strides,
+            offset: 0,
+        })
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.buf.writable
+    }
+
+    pub fn check_writable(&self) -> Result<(), String> {
+        if self.is_writable() {
+            Ok(())
+        } else {
+            Err("assignment destination is read-only".to_string())
+        }
+    }
+
+    pub fn flush(&self) -> Result<(), String> {
+        self.buf.flush()
+    }
+
+    pub fn scalar(x: f64) -> Self {
+        Self::from_vec(vec![x], vec![])
+    }
+
+    pub fn from_matrix(matrix: &[Vec<f64>]) -> Self {
+        let cols = matrix.first().map_or(0, |row| row.len());
+        let data = matrix.iter().flatten().copied().collect();
+        Self::from_vec(data, vec![matrix.len(), cols])
+    }
+
+    pub fn to_matrix(&self) -> Vec<Vec<f64>> {
+        assert_eq!(self.ndim(), 2);
+        let cols = self.shape[1];
+        if cols == 0 {
+            return vec![vec![]; self.shape[0]];
+        }
+        self.to_vec().chunks(cols).map(|row| row.to_vec()).collect()
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn strides(&self) -> &[isize] {
+        &self.strides
+    }
+
+    pub fn ndim(&self) -> usize {
+        self.shape.len()
+    }
+
+    pub fn size(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Row-major contiguity; strides of length-1 axes don't matter.
+    pub fn is_contiguous(&self) -> bool {
+        let mut expected = 1isize;
+        for (&n, &s) in self.shape.iter().zip(self.strides.iter()).rev() {
+            if n == 0 {
+                return true;
+            } else if n != 1 && s != expected {
+                return false;
+            }
+            expected *= n as isize;
+        }
+        true
+    }
+
+    /// Whether `self` and `other` read from the same underlying buffer.
+    pub fn shares_buffer(&self, other: &Array) -> bool {
+        Arc::ptr_eq(&self.buf, &other.buf)
+    }
+
+    /// Contiguous row-major elements, or `None` if `self` is a strided view.
+    pub fn as_slice(&self) -> Option<&[f64]> {
+        if self.is_contiguous() {
+            Some(&self.buf.as_slice()[self.offset..self.offset + self.size()])
+        } else {
+            None
+        }
+    }
+
+    /// For a 2-D view that is the transpose of a contiguous matrix, the
+    /// elements of that matrix in row-major order.
+    pub fn as_transposed_slice(&self) -> Option<&[f64]> {
+        if self.ndim() == 2 && self.t().is_contiguous() {
+            Some(&self.buf.as_slice()[self.offset..self.offset + self.size()])
+        } else {
+            None
+        }
+    }
+
+    fn offset_of(&self, idx: &[usize]) -> usize {
+        let off = idx
+            .iter()
+            .zip(self.strides.iter())
+            .map(|(&i, &s)| i as isize * s)
+            .sum::<isize>();
+        (self.offset as isize + off) as usize
+    }
+
+    pub fn get(&self, idx: &[usize]) -> f64 {
+        self.buf.as_slice()[self.offset_of(idx)]
+    }
+
+    pub fn set(&self, idx: &[usize], value: f64) {
+        let off = self.offset_of(idx);
+        unsafe { self.buf.as_mut_slice()[off] = value };
+    }
+
+    /// Buffer offset of the first element of the `lane`-th row of the last
+    /// axis, counting rows in row-major order.
+    pub fn lane_base(&self, mut lane: usize) -> isize {
+        let mut off = self.offset as isize;
+        for ax in (0..self.ndim().saturating_sub(1)).rev() {
+            let n = self.shape[ax];
+            off += (lane % n) as isize * self.strides[ax];
+            lane /= n;
+        }
+        off
+    }
+
+    pub fn raw(&self) -> &[f64] {
+        self.buf.as_slice()
+    }
+
+    /// Writable handle on the whole underlying buffer, for kernels that
+    /// scatter into disjoint offsets from several threads.
+    pub fn raw_unsafe(&self) -> UnsafeSlice<'_, f64> {
+        UnsafeSlice::new(unsafe { self.buf.as_mut_slice() })
+    }
+
+    /// Copies the elements out in row-major order.
+    pub fn to_vec(&self) -> Vec<f64> {
+        if let Some(slice) = self.as_slice() {
+            return slice.to_vec();
+        }
+        let size = self.size();
+        let mut out = vec![0.; size];
+        if size == 0 {
+            return out;
+        }
+        let raw = self.raw();
+        if self.ndim() == 2 && self.strides == [1, self.shape[0] as isize] {
+            let (rows, cols) = (self.shape[1], self.shape[0]);
+            return rust_flat_transpose(&raw[self.offset..self.offset + size], rows, cols);
+        }
+        let last = *self.shape.last().unwrap();
+        let last_stride = *self.strides.last().unwrap();
+        out.par_chunks_mut(last)
+            .enumerate()
+            .for_each(|(lane, chunk)| {
+                let base = self.lane_base(lane);
+                chunk.iter_mut().enumerate().for_each(|(i, e)| {
+                    *e = raw[(base + i as isize * last_stride) as usize];
+                })
+            });
+        out
+    }
+
+    /// Returns `self` if it is already contiguous, otherwise a fresh
+    /// row-major copy.
+    pub fn to_contiguous(&self) -> Array {
+        if self.is_contiguous() {
+            self.clone()
+        } else {
+            Array::from_vec(self.to_vec(), self.shape.clone())
+        }
+    }
+
+    /// Same data viewed with a different shape; the caller guarantees `self`
+    /// is contiguous and the sizes agree.
+    pub fn reshaped(&self, shape: Vec<usize>) -> Array {
+        assert!(self.is_contiguous());
+        assert_eq!(self.size(), shape.iter().product::<usize>());
+        Array {
+            buf: self.buf.clone(),
+            strides: row_major_strides(&shape),
+            shape,
+            offset: self.offset,
+        }
+    }
+
+    /// Transposes a contiguous matrix by permuting its buffer in place
+    /// instead of allocating a second one. Other views of the buffer see
+    /// the permuted elements.
+    pub fn transpose_inplace(&mut self) {
+        assert!(self.ndim() == 2 && self.is_contiguous());
+        let (rows, cols) = (self.shape[0], self.shape[1]);
+        let data = unsafe { &mut self.buf.as_mut_slice()[self.offset..self.offset + rows * cols] };
+        rust_transpose_inplace(data, rows, cols);
+        self.shape = vec![cols, rows];
+        self.strides = row_major_strides(&self.shape);
+    }
+
+    pub fn permute_axes(&self, axes: &[usize]) -> Result<Array, String> {
+        let n = self.ndim();
+        let mut seen = vec![false; n];
+        if axes.len() != n {
+            return Err("axes don't match array".to_string());
+        }
+        for &ax in axes {
+            if ax >= n || seen[ax] {
+                return Err("axes must be a permutation of the array's dimensions".to_string());
+            }
+            seen[ax] = true;
+        }
+        Ok(Array {
+            buf: self.buf.clone(),
+            shape: axes.iter().map(|&ax| self.shape[ax]).collect(),
+            strides: axes.iter().map(|&ax| self.strides[ax]).collect(),
+            offset: self.offset,
+        })
+    }
+
+    /// Reverses the axes, like `np.transpose` without `axes`.
+    pub fn t(&self) -> Array {
+        let axes: Vec<usize> = (0..self.ndim()).rev().collect();
+        self.permute_axes(&axes).unwrap()
+    }
+
+    pub fn transpose(&self, axes: Option<&[isize]>) -> Result<Array, String> {
+        match axes {
+            None => Ok(self.t()),
+            Some(axes) => {
+                let axes = axes
+                    .iter()
+                    .map(|&ax| normalize_axis(ax, self.ndim()))
+                    .collect::<Result<Vec<usize>, String>>()?;
+                self.permute_axes(&axes)
+            }
+        }
+    }
+
+    pub fn swapaxes(&self, axis1: isize, axis2: isize) -> Result<Array, String> {
+        let a1 = normalize_axis(axis1, self.ndim())?;
+        let a2 = normalize_axis(axis2, self.ndim())?;
+        let mut axes: Vec<usize> = (0..self.ndim()).collect();
+        axes.swap(a1, a2);
+        self.permute_axes(&axes)
+    }
+
+    pub fn moveaxis(&self, source: &[isize], destination: &[isize]) -> Result<Array, String> {
+        if source.len() != destination.len() {
+            return Err(
+                "`source` and `destination` arguments must have the same number of elements"
+                    .to_string(),
+            );
+        }
+        let n = self.ndim();
+        let src = source
+            .iter()
+            .map(|&ax| normalize_axis(ax, n))
+            .collect::<Result<Vec<usize>, String>>()?;
+        let dst = destination
+            .iter()
+            .map(|&ax| normalize_axis(ax, n))
+            .collect::<Result<Vec<usize>, String>>()?;
+        for (axes, name) in [(&src, "source"), (&dst, "destination")] {
+            if (1..axes.len()).any(|i| axes[..i].contains(&axes[i])) {
+                return Err(format!("repeated axis in `{}` argument", name));
+            }
+        }
+
+        let mut order: Vec<usize> = (0..n).filter(|ax| !src.contains(ax)).collect();
+        let mut moved: Vec<(usize, usize)> = dst.into_iter().zip(src).collect();
+        moved.sort_unstable();
+        for (d, s) in moved {
+            order.insert(d, s);
+        }
+        self.permute_axes(&order)
+    }
+
+    /// Sub-array at `i` along the first axis, as a view.
+    pub fn index_first(&self, i: usize) -> Array {
+        Array {
+            buf: self.buf.clone(),
+            shape: self.shape[1..].to_vec(),
+            strides: self.strides[1..].to_vec(),
+            offset: (self.offset as isize + i as isize * self.strides[0]) as usize,
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// View of the same buffer with an arbitrary layout; the caller
+    /// guarantees every reachable offset is in bounds.
+    pub fn with_layout(&self, shape: Vec<usize>, strides: Vec<isize>, offset: usize) -> Array {
+        Array {
+            buf: self.buf.clone(),
+            shape,
+            strides,
+            offset,
+        }
+    }
+
+    /// View of `len` elements along `axis` starting at `start` and stepping
+    /// by `step`; the caller guarantees the range stays in bounds.
+    pub fn slice_axis(&self, axis: usize, start: usize, len: usize, step: isize) -> Array {
+        let mut shape = self.shape.clone();
+        let mut strides = self.strides.clone();
+        let offset = if len == 0 {
+            self.offset
+        } else {
+            (self.offset as isize + start as isize * strides[axis]) as usize
+        };
+        shape[axis] = len;
+        strides[axis] *= step;
+        Array {
+            buf: self.buf.clone(),
+            shape,
+            strides,
+            offset,
+        }
+    }
+
+    /// View of the diagonal running along axes `ax1 < ax2`; the diagonal
+    /// takes the place of `ax1` and `ax2` is dropped.
+    pub fn diagonal_axes(&self, ax1: usize, ax2: usize) -> Array {
+        let mut shape = self.shape.clone();
+        let mut strides = self.strides.clone();
+        shape[ax1] = shape[ax1].min(shape[ax2]);
+        strides[ax1] += strides[ax2];
+        shape.remove(ax2);
+        strides.remove(ax2);
+        Array {
+            buf: self.buf.clone(),
+            shape,
+            strides,
+            offset: self.offset,
+        }
+    }
+
+    /// View with a new length-1 axis inserted before `axis`.
+    pub fn expand_dims(&self, axis: usize) -> Array {
+        let mut shape = self.shape.clone();
+        let mut strides = self.strides.clone();
+        shape.insert(axis, 1);
+        strides.insert(axis, 0);
+        Array {
+            buf: self.buf.clone(),
+            shape,
+            strides,
+            offset: self.offset,
+        }
+    }
+
+    /// Views `self` with the given shape, repeating length-1 (or missing
+    /// leading) axes through zero strides.
+    pub fn broadcast_to(&self, shape: &[usize]) -> Result<Array, String> {
+        if shape.len() < self.ndim() {
+            return Err("cannot broadcast to fewer dimensions".to_string());
+        }
+        let pad = shape.len() - self.ndim();
+        let mut strides = vec![0isize; shape.len()];
+        for (ax, &n) in self.shape.iter().enumerate() {
+            if n == shape[pad + ax] {
+                strides[pad + ax] = self.strides[ax];
+            } else if n != 1 {
+                return Err(format!(
+                    "operands could not be broadcast together with shapes {:?} {:?}",
+                    self.shape, shape
+                ));
+            }
+        }
+        Ok(Array {
+            buf: self.buf.clone(),
+            shape: shape.to_vec(),
+            strides,
+            offset: self.offset,
+        })
+    }
+
+    /// Copies the row-major elements `start..start + out.len()` of `self`
+    /// into `out`, stepping through the strides like an odometer so a
+    /// chunk can start anywhere in a broadcast or strided view.
+    pub fn gather_into(&self, start: usize, out: &mut [f64]) {
+        if let Some(xs) = self.as_slice() {
+            out.copy_from_slice(&xs[start..start + out.len()]);
+            return;
+        }
+        let ndim = self.ndim();
+        let raw = self.raw();
+        let mut idx = vec![0; ndim];
+        let mut rem = start;
+        for ax in (0..ndim).rev() {
+            idx[ax] = rem % self.shape[ax];
+            rem /= self.shape[ax];
+        }
+        let mut off = self.offset_of(&idx) as isize;
+        for o in out.iter_mut() {
+            *o = raw[off as usize];
+            for ax in (0..ndim).rev() {
+                idx[ax] += 1;
+                off += self.strides[ax];
+                if idx[ax] < self.shape[ax] {
+                    break;
+                }
+                off -= self.strides[ax] * self.shape[ax] as isize;
+                idx[ax] = 0;
+            }
+        }
+    }
+
+    /// Writes row-major `data` into the elements of `self`, which may be a
+    /// strided view.
+    pub fn assign(&self, data: &[f64]) {
+        assert_eq!(data.len(), self.size());
+        if data.is_empty() {
+            return;
+        }
+        let out = self.raw_unsafe();
+        let last = self.shape.last().copied().unwrap_or(1);
+        let last_stride = self.strides.last().copied().unwrap_or(0);
+        data.par_chunks(last).enumerate().for_each(|(lane, chunk)| {
+            let base = self.lane_base(lane);
+            chunk.iter().enumerate().for_each(|(i, &e)| unsafe {
+                out.write((base + i as isize * last_stride) as usize, e);
+            })
+        });
+    }
+
+    fn fmt_nested(&self, data: &[f64], depth: usize, out: &mut String) {
+        if depth == self.ndim() {
+            out.push_str(&format!("{:?}", data[0]));
+            return;
+        }
+        let n = self.shape[depth];
+        let chunk = self.shape[depth + 1..].iter().product::<usize>();
+        out.push('[');
+        for i in 0..n {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            self.fmt_nested(&data[i * chunk..], depth + 1, out);
+        }
+        out.push(']');
+    }
+}
+
+fn extract_nested(
+    obj: &PyAny,
+    depth: usize,
+    shape: &mut Vec<usize>,
+    data: &mut Vec<f64>,
+) -> PyResult<()> {
+    if let Ok(x) = obj.extract::<f64>() {
+        if depth != shape.len() {
+            return Err(PyTypeError::new_err(
+                "Parameter is a ragged nested sequence.",
+            ));
+        }
+        data.push(x);
+        return Ok(());
+    }
+    // Strings are sequences of strings, so they would never bottom out.
+    let text = obj.is_instance_of::<PyString>()? || obj.is_instance_of::<PyBytes>()?;
+    let seq = match obj.downcast::<PySequence>() {
+        Ok(seq) if !text => seq,
+        _ => {
+            return Err(PyTypeError::new_err(
+                "Parameter cannot be converted to array of floats.",
+            ))
+        }
+    };
+    let len = seq.len()?;
+    if depth == shape.len() && data.is_empty() {
+        shape.push(len);
+    } else if depth >= shape.len() || shape[depth] != len {
+        return Err(PyTypeError::new_err(
+            "Parameter is a ragged nested sequence.",
+        ));
+    }
+    for i in 0..len {
+        extract_nested(seq.get_item(i)?, depth + 1, shape, data)?;
+    }
+    Ok(())
+}
+
+/// Accepts an `Array` or any nested sequence of floats.
+pub fn to_array(obj: &PyAny) -> PyResult<Array> {
+    if let Ok(a) = obj.extract::<PyRef<Array>>() {
+        return Ok(a.clone());
+    }
+    let mut shape = vec![];
+    let mut data = vec![];
+    extract_nested(obj, 0, &mut shape, &mut data)?;
+    if data.len() != shape.iter().product::<usize>() {
+        return Err(PyTypeError::new_err(
+            "Parameter is a ragged nested sequence.",
+        ));
+    }
+    Ok(Array::from_vec(data, shape))
+}
+
+/// Accepts a single axis or a sequence of axes.
+pub fn extract_axes(obj: &PyAny) -> PyResult<Vec<isize>> {
+    match (obj.extract::<isize>(), obj.extract::<Vec<isize>>()) {
+        (Ok(ax), _) => Ok(vec![ax]),
+        (_, Ok(axes)) => Ok(axes),
+        _ => Err(PyTypeError::new_err(
+            "Axes must be an int or a sequence of ints.",
+        )),
+    }
+}
+
+fn to_nested_list(py: Python, a: &Array, data: &[f64], depth: usize) -> PyObject {
+    if depth == a.ndim() {
+        return data[0].into_py(py);
+    }
+    let chunk = a.shape[depth + 1..].iter().product::<usize>();
+    let items: Vec<PyObject> = (0..a.shape[depth])
+        .map(|i| to_nested_list(py, a, &data[i * chunk..], depth + 1))
+        .collect();
+    PyList::new(py, items).into_py(py)
+}
+
+/// The lanes of an array along one axis, laid out contiguously one after
+/// the other; `axis: None` means the flattened array.
+pub struct Lanes {
+    pub data: Vec<f64>,
+    pub shape: Vec<usize>,
+    pub axis: isize,
+}
+
+impl Lanes {
+    pub fn new(a: &Array, axis: Option<isize>) -> Result<Lanes, String> {
+        match axis {
+            None => Ok(Lanes {
+                data: a.to_vec(),
+                shape: vec![a.size()],
+                axis: -1,
+            }),
+            Some(axis) => {
+                normalize_axis(axis, a.ndim())?;
+                let moved = a.moveaxis(&[axis], &[-1])?;
+                Ok(Lanes {
+                    data: moved.to_vec(),
+                    shape: moved.shape().to_vec(),
+                    axis,
+                })
+            }
+        }
+    }
+
+    pub fn lane_len(&self) -> usize {
+        self.shape[self.shape.len() - 1]
+    }
+
+    /// Whether there is a single lane, in which case kernels parallelize
+    /// within the lane instead of across lanes.
+    pub fn single(&self) -> bool {
+        self.data.len() == self.lane_len()
+    }
+
+    /// Puts lanes of length `n` back where the original axis was.
+    pub fn finish(&self, data: Vec<f64>, n: usize) -> Result<Array, String> {
+        let mut shape = self.shape.clone();
+        let last = shape.len() - 1;
+        shape[last] = n;
+        Ok(Array::from_vec(data, shape)
+            .moveaxis(&[-1], &[self.axis])?
+            .to_contiguous())
+    }
+}
+
+#[pymethods]
+impl Array {
+    #[new]
+    fn py_new(obj: &PyAny) -> PyResult<Self> {
+        to_array(obj)
+    }
+
+    #[getter(shape)]
+    fn py_shape(&self, py: Python) -> PyObject {
+        PyTuple::new(py, &self.shape).into_py(py)
+    }
+
+    #[getter(ndim)]
+    fn py_ndim(&self) -> usize {
+        self.ndim()
+    }
+
+    #[getter(size)]
+    fn py_size(&self) -> usize {
+        self.size()
+    }
+
+    #[getter(T)]
+    fn py_t(&self) -> Array {
+        self.t()
+    }
+
+    fn tolist(&self, py: Python) -> PyObject {
+        to_nested_list(py, self, &self.to_vec(), 0)
+    }
+
+    /// Writes the elements in C order as raw `dtype` items.
+    #[args(dtype = "\"float64\"")]
+    fn tofile(&self, file: &PyAny, dtype: &str) -> PyResult<()> {
+        let dtype = Dtype::from_name(dtype).map_err(PyTypeError::new_err)?;
+        rust_tofile(&extract_path(file)?, self, dtype).map_err(PyIOError::new_err)
+    }
+
+    #[pyo3(name = "flush")]
+    fn py_flush(&self) -> PyResult<()> {
+        self.flush().map_err(PyTypeError::new_err)
+    }
+
+    fn copy(&self) -> Array {
+        Array::from_vec(self.to_vec(), self.shape.clone())
+    }
+
+    fn __len__(&self) -> PyResult<usize> {
+        match self.shape.first() {
+            Some(&n) => Ok(n),
+            None => Err(PyTypeError::new_err("len() of unsized object")),
+        }
+    }
+
+    fn __getitem__(&self, py: Python, key: &PyAny) -> PyResult<PyObject> {
+        let sub = rust_getitem(self, &parse_key(key)?).map_err(PyIndexError::new_err)?;
+        if sub.ndim() == 0 {
+            Ok(sub.get(&[]).into_py(py))
+        } else {
+            Ok(sub.into_py(py))
+        }
+    }
+
+    fn __setitem__(&self, key: &PyAny, value: &PyAny) -> PyResult<()> {
+        self.check_writable().map_err(PyTypeError::new_err)?;
+        rust_setitem(self, &parse_key(key)?, &to_array(value)?).map_err(PyIndexError::new_err)
+    }
+
+    fn __add__(&self, other: &PyAny) -> PyResult<Array> {
+        self.py_binary(BinOp::Add, other, false)
+    }
+
+    fn __radd__(&self, other: &PyAny) -> PyResult<Array> {
+        self.py_binary(BinOp::Add, other, true)
+    }
+
+    fn __sub__(&self, other: &PyAny) -> PyResult<Array> {
+        self.py_binary(BinOp::Sub, other, false)
+    }
+
+    fn __rsub__(&self, other: &PyAny) -> PyResult<Array> {
+        self.py_binary(BinOp::Sub, other, true)
+    }
+
+    fn __mul__(&self, other: &PyAny) -> PyResult<Array> {
+        self.py_binary(BinOp::Mul, other, false)
+    }
+
+    fn __rmul__(&self, other: &PyAny) -> PyResult<Array> {
+        self.py_binary(BinOp::Mul, other, true)
+    }
+
+    fn __truediv__(&self, other: &PyAny) -> PyResult<Array> {
+        self.py_binary(BinOp::Div, other, false)
+    }
+
+    fn __rtruediv__(&self, other: &PyAny) -> PyResult<Array> {
+        self.py_binary(BinOp::Div, other, true)
+    }
+
+    fn __pow__(&self, other: &PyAny, _modulo: Option<&PyAny>) -> PyResult<Array> {
+        self.py_binary(BinOp::Pow, other, false)
+    }
+
+    fn __rpow__(&self, other: &PyAny, _modulo: Option<&PyAny>) -> PyResult<Array> {
+        self.py_binary(BinOp::Pow, other, true)
+    }
+
+    fn __neg__(&self) -> PyResult<Array> {
+        rust_binary(BinOp::Mul, self, &Array::scalar(-1.)).map_err(PyTypeError::new_err)
+    }
+
+    fn __abs__(&self) -> Array {
+        rust_unary(UnaryOp::Abs, self)
+    }
+
+    fn __matmul__(&self, other: &PyAny) -> PyResult<Array> {
+        if let Some(s) = as_sparse(other) {
+            return s.inner.rdot_dense(self).map_err(PyTypeError::new_err);
+        }
+        rust_array_matmul(self, &to_array(other)?).map_err(PyTypeError::new_err)
+    }
+
+    fn __rmatmul__(&self, other: &PyAny) -> PyResult<Array> {
+        rust_array_matmul(&to_array(other)?, self).map_err(PyTypeError::new_err)
+    }
+
+    fn __repr__(&self) -> String {
+        let mut out = String::from("Array(");
+        self.fmt_nested(&self.to_vec(), 0, &mut out);
+        out.push(')');
+        out
+    }
+}
+
+impl Array {
+    fn py_binary(&self, op: BinOp, other: &PyAny, reflected: bool) -> PyResult<Array> {
+        let other = to_array(other)?;
+        let res = if reflected {
+            rust_binary(op, &other, self)
+        } else {
+            rust_binary(op, self, &other)
+        };
+        res.map_err(PyTypeError::new_err)
+    }
+}
+
+#[pyfunction]
+pub fn ascontiguousarray(a: &PyAny) -> PyResult<Array> {
+    Ok(to_array(a)?.to_contiguous())
+}
+
+pub(crate) mod test {
+    /// `0, 1, 2, ...` laid out in `shape`; shared by the other modules' tests.
+    #[allow(dead_code)]
+    pub(crate) fn arange(shape: Vec<usize>) -> super::Array {
+        let n = shape.iter().product::<usize>();
+        super::Array::from_vec((0..n).map(|e| e as f64).collect(), shape)
+    }
+
+    #[test]
+    fn permute_test() {
+        let a = arange(vec![2, 3, 4]);
+        let b = a.transpose(Some(&[1, -1, 0])).unwrap();
+        assert_eq!(b.shape(), &[3, 4, 2]);
+        assert!(!b.is_contiguous());
+        assert!(b.shares_buffer(&a));
+        assert_eq!(b.get(&[2, 1, 1]), a.get(&[1, 2, 1]));
+
+        let v = b.to_vec();
+        assert_eq!(&v[..4], &[0., 12., 1., 13.]);
+        assert!(b.to_contiguous().is_contiguous());
+        assert!(a.transpose(Some(&[0, 0, 1])).is_err());
+    }
+
+    #[test]
+    fn swap_move_test() {
+        let a = arange(vec![2, 3, 4, 5]);
+        assert_eq!(a.swapaxes(0, -1).unwrap().shape(), &[5, 3, 4, 2]);
+        assert_eq!(a.moveaxis(&[0], &[-1]).unwrap().shape(), &[3, 4, 5, 2]);
+        assert_eq!(a.moveaxis(&[-1], &[0]).unwrap().shape(), &[5, 2, 3, 4]);
+        assert_eq!(
+            a.moveaxis(&[0, 1], &[-1, -2]).unwrap().shape(),
+            &[4, 5, 3, 2]
+        );
+        assert!(a.swapaxes(0, 4).is_err());
+        assert!(a.moveaxis(&[0, 1], &[0, 0]).is_err());
+        assert!(a.moveaxis(&[0, -4], &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn matrix_roundtrip_test() {
+        let m = vec![vec![1., 2., 3.], vec![4., 5., 6.]];
+        let a = super::Array::from_matrix(&m);
+        assert_eq!(&a.to_matrix(), &m);
+        assert_eq!(
+            &a.t().to_matrix(),
+            &vec![vec![1., 4.], vec![2., 5.], vec![3., 6.]]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use crate::array::{to_array, Array};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::{pyfunction, PyAny, PyResult};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// Accepts either an int or a sequence of ints, like numpy's `shape`
+/// arguments.
+pub fn extract_shape(obj: &PyAny) -> PyResult<Vec<usize>> {
+    match (obj.extract::<usize>(), obj.extract::<Vec<usize>>()) {
+        (Ok(n), _) => Ok(vec![n]),
+        (_, Ok(shape)) => Ok(shape),
+        _ => Err(PyTypeError::new_err(
+            "Shape must be a non-negative int or a sequence of them.",
+        )),
+    }
+}
+
+/// Builds an array whose `i`-th element (in row-major order) is `f(i)`,
+/// filling the buffer in parallel.
+pub fn rust_from_fn<F>(shape: Vec<usize>, f: F) -> Array
+where
+    F: Fn(usize) -> f64 + Sync + Send,
+{
+    let n = shape.iter().product::<usize>();
+    Array::from_vec((0..n).into_par_iter().map(f).collect(), shape)
+}
+
+pub fn rust_full(shape: Vec<usize>, value: f64) -> Array {
+    rust_from_fn(shape, |_| value)
+}
+
+/// `n x m` matrix with ones on the `k`-th diagonal.
+pub fn rust_eye(n: usize, m: usize, k: isize) -> Array {
+    rust_from_fn(vec![n, m], |i| {
+        if (i % m) as isize - (i / m) as isize == k {
+            1.
+        } else {
+            0.
+        }
+    })
+}
+
+pub fn rust_arange(start: f64, stop: f64, step: f64) -> Result<Array, String> {
+    if step == 0. || !step.is_finite() {
+        return Err("arange step must be finite and non-zero".to_string());
+    }
+    let len = ((stop - start) / step).ceil().max(0.) as usize;
+    Ok(rust_from_fn(vec![len], |i| start + i as f64 * step))
+}
+
+/// Evenly spaced samples over `[start, stop]`; with `endpoint` the last
+/// sample is exactly `stop`.
+pub fn rust_linspace(start: f64, stop: f64, num: usize, endpoint: bool) -> Array {
+    let div = if endpoint { num.saturating_sub(1) } else { num };
+    let step = if div > 0 {
+        (stop - start) / div as f64
+    } else {
+        0.
+    };
+    rust_from_fn(vec![num], |i| {
+        if endpoint && i > 0 && i == num - 1 {
+            stop
+        } else {
+            start + i as f64 * step
+        }
+    })
+}
+
+pub fn rust_logspace(start: f64, stop: f64, num: usize, endpoint: bool, base: f64) -> Array {
+    let exps = rust_linspace(start, stop, num, endpoint).to_vec();
+    rust_from_fn(vec![num], |i| base.powf(exps[i]))
+}
+
+/// Coordinate matrices from coordinate vectors. `xy` indexing swaps the
+/// first two output axes, as in numpy.
+pub fn rust_meshgrid(xs: &[Array], indexing: &str) -> Result<Vec<Array>, String> {
+    let cartesian = match indexing {
+        "xy" => true,
+        "ij" => false,
+        _ => return Err("Valid values for `indexing` are 'xy' and 'ij'.".to_string()),
+    };
+    let vecs = xs.iter().map(|x| x.to_vec()).collect::<Vec<_>>();
+    let mut shape = vecs.iter().map(|v| v.len()).collect::<Vec<_>>();
+    if cartesian && shape.len() > 1 {
+        shape.swap(0, 1);
+    }
+    Ok(vecs
+        .iter()
+        .enumerate()
+        .map(|(d, v)| {
+            // Axis of the output along which input `d` varies.
+            let ax = match d {
+                0 | 1 if cartesian && shape.len() > 1 => 1 - d,
+                _ => d,
+            };
+            let inner = shape[ax + 1..].iter().product::<usize>();
+            let n = shape[ax];
+            rust_from_fn(shape.clone(), |i| v[(i / inner) % n])
+        })
+        .collect())
+}
+
+#[pyfunction]
+pub fn zeros(shape: &PyAny) -> PyResult<Array> {
+    Ok(rust_full(extract_shape(shape)?, 0.))
+}
+
+#[pyfunction]
+pub fn ones(shape: &PyAny) -> PyResult<Array> {
+    Ok(rust_full(extract_shape(shape)?, 1.))
+}
+
+#[pyfunction]
+pub fn full(shape: &PyAny, fill_value: f64) -> PyResult<Array> {
+    Ok(rust_full(extract_shape(shape)?, fill_value))
+}
+
+#[pyfunction(k = "0")]
+#[allow(non_snake_case)]
+pub fn eye(N: usize, M: Option<usize>, k: isize) -> PyResult<Array> {
+    Ok(rust_eye(N, M.unwrap_or(N), k))
+}
+
+#[pyfunction]
+pub fn identity(n: usize) -> PyResult<Array> {
+    Ok(rust_eye(n, n, 0))
+}
+
+#[pyfunction(stop = "None", step = "1.")]
+pub fn arange(start: f64, stop: Option<f64>, step: f64) -> PyResult<Array> {
+    let (start, stop) = match stop {
+        Some(stop) => (start, stop),
+        None => (0., start),
+    };
+    rust_arange(start, stop, step).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(num = "50", endpoint = "true")]
+pub fn linspace(start: f64, stop: f64, num: usize, endpoint: bool) -> PyResult<Array> {
+    Ok(rust_linspace(start, stop, num, endpoint))
+}
+
+#[pyfunction(num = "50", endpoint = "true", base = "10.")]
+pub fn logspace(start: f64, stop: f64, num: usize, endpoint: bool, base: f64) -> PyResult<Array> {
+    Ok(rust_logspace(start, stop, num, endpoint, base))
+}
+
+#[pyfunction(xi = "*", indexing = "\"xy\"")]
+pub fn meshgrid(xi: Vec<&PyAny>, indexing: &str) -> PyResult<Vec<Array>> {
+    let xs = xi.into_iter().map(to_array).collect::<PyResult<Vec<_>>>()?;
+    rust_meshgrid(&xs, indexing).map_err(PyTypeError::new_err)
+}
+
+mod test {
+    #[test]
+    fn eye_test() {
+        let e = super::rust_eye(3, 4, 1);
+        assert_eq!(e.shape(), &[3, 4]);
+        assert_eq!(
+            e.to_vec(),
+            vec![0., 1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1.]
+        );
+        assert_eq!(super::rust_eye(2, 2, -1).to_vec(), vec![0., 0., 1., 0.]);
+    }
+
+    #[test]
+    fn range_test() {
+        assert_eq!(
+            super::rust_arange(1., 2., 0.25).unwrap().to_vec(),
+            vec![1., 1.25, 1.5, 1.75]
+        );
+        assert_eq!(
+            super::rust_arange(3., 0., -1.).unwrap().to_vec(),
+            vec![3., 2., 1.]
+        );
+        assert_eq!(super::rust_arange(0., 1., -1.).unwrap().size(), 0);
+        assert!(super::rust_arange(0., 1., 0.).is_err());
+
+        let xs = super::rust_linspace(0., 1., 5, true).to_vec();
+        assert_eq!(xs, vec![0., 0.25, 0.5, 0.75, 1.]);
+        assert_eq!(super::rust_linspace(0., 1., 4, false).to_vec()[3], 0.75);
+        assert_eq!(
+            super::rust_logspace(0., 2., 3, true, 10.).to_vec(),
+            vec![1., 10., 100.]
+        );
+    }
+
+    #[test]
+    fn meshgrid_test() {
+        use crate::array::Array;
+        let x = Array::from_vec(vec![1., 2., 3.], vec![3]);
+        let y = Array::from_vec(vec![4., 5.], vec![2]);
+        let xy = super::rust_meshgrid(&[x.clone(), y.clone()], "xy").unwrap();
+        assert_eq!(xy[0].shape(), &[2, 3]);
+        assert_eq!(xy[0].to_vec(), vec![1., 2., 3., 1., 2., 3.]);
+        assert_eq!(xy[1].to_vec(), vec![4., 4., 4., 5., 5., 5.]);
+        let ij = super::rust_meshgrid(&[x, y], "ij").unwrap();
+        assert_eq!(ij[0].shape(), &[3, 2]);
+        assert_eq!(ij[0].to_vec(), vec![1., 1., 2., 2., 3., 3.]);
+        assert_eq!(ij[1].to_vec(), vec![4., 5., 4., 5., 4., 5.]);
+    }
+}
@@ -23,6 +23,15 @@ impl<'a, T> UnsafeSlice<'a, T> {
         *ptr = value;
     }
 
+    /// SAFETY: It is UB if another thread writes to `i` without
+    /// synchronization.
+    pub unsafe fn read(&self, i: usize) -> T
+    where
+        T: Copy,
+    {
+        *self.slice[i].get()
+    }
+
     #[allow(dead_code)]
     pub fn get_slice(&self) -> &'a [UnsafeCell<T>] {
         self.slice
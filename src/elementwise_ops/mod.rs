@@ -0,0 +1,427 @@
+use crate::array::{to_array, Array};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Max,
+    Min,
+}
+
+impl BinOp {
+    pub fn apply(self, x: f64, y: f64) -> f64 {
+        match self {
+            BinOp::Add => x + y,
+            BinOp::Sub => x - y,
+            BinOp::Mul => x * y,
+            BinOp::Div => x / y,
+            BinOp::Pow => x.powf(y),
+            // unlike f64::max, numpy propagates NaN
+            BinOp::Max if x.is_nan() || y.is_nan() => f64::NAN,
+            BinOp::Min if x.is_nan() || y.is_nan() => f64::NAN,
+            BinOp::Max => x.max(y),
+            BinOp::Min => x.min(y),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UnaryOp {
+    Exp,
+    Log,
+    Log1p,
+    Expm1,
+    Sin,
+    Cos,
+    Tan,
+    Tanh,
+    Sqrt,
+    Abs,
+    Sign,
+    Floor,
+    Ceil,
+    Clip(f64, f64),
+}
+
+impl UnaryOp {
+    pub fn apply(self, x: f64) -> f64 {
+        match self {
+            UnaryOp::Exp => x.exp(),
+            UnaryOp::Log => x.ln(),
+            UnaryOp::Log1p => x.ln_1p(),
+            UnaryOp::Expm1 => x.exp_m1(),
+            UnaryOp::Sin => x.sin(),
+            UnaryOp::Cos => x.cos(),
+            UnaryOp::Tan => x.tan(),
+            UnaryOp::Tanh => x.tanh(),
+            UnaryOp::Sqrt => x.sqrt(),
+            UnaryOp::Abs => x.abs(),
+            // f64::signum maps 0 to 1
+            UnaryOp::Sign if x == 0. => 0.,
+            UnaryOp::Sign => x.signum(),
+            UnaryOp::Floor => x.floor(),
+            UnaryOp::Ceil => x.ceil(),
+            UnaryOp::Clip(_, _) if x.is_nan() => x,
+            UnaryOp::Clip(lo, hi) => x.max(lo).min(hi),
+        }
+    }
+}
+
+const UFUNC_CHUNK: usize = 1 << 14;
+
+/// Applies `f` to every element of `a` into a new contiguous array. Contiguous
+/// inputs are split into fixed-size chunks; strided ones are walked one
+/// last-axis lane per task.
+pub fn map_array<F>(a: &Array, f: F) -> Array
+where
+    F: Fn(f64) -> f64 + Sync,
+{
+    let mut data = vec![0.; a.size()];
+    if let Some(xs) = a.as_slice() {
+        data.par_chunks_mut(UFUNC_CHUNK)
+            .zip(xs.par_chunks(UFUNC_CHUNK))
+            .for_each(|(out, xs)| {
+                out.iter_mut().zip(xs).for_each(|(o, &x)| *o = f(x));
+            });
+    } else if !data.is_empty() {
+        let last = *a.shape().last().unwrap();
+        let sa = last_stride(a);
+        let raw = a.raw();
+        data.par_chunks_mut(last)
+            .enumerate()
+            .for_each(|(lane, out)| {
+                let base = a.lane_base(lane);
+                out.iter_mut().enumerate().for_each(|(i, o)| {
+                    *o = f(raw[(base + i as isize * sa) as usize]);
+                })
+            });
+    }
+    Array::from_vec(data, a.shape().to_vec())
+}
+
+pub fn rust_unary(op: UnaryOp, a: &Array) -> Array {
+    map_array(a, |x| op.apply(x))
+}
+
+/// Shape the operands broadcast to, aligning trailing axes like numpy.
+pub fn broadcast_shape(shapes: &[&[usize]]) -> Result<Vec<usize>, String> {
+    let ndim = shapes.iter().map(|s| s.len()).max().unwrap_or(0);
+    let mut out = vec![1; ndim];
+    for shape in shapes {
+        let pad = ndim - shape.len();
+        for (ax, &n) in shape.iter().enumerate() {
+            let o = &mut out[pad + ax];
+            if *o == 1 {
+                *o = n;
+            } else if n != 1 && n != *o {
+                return Err(format!(
+                    "operands could not be broadcast together with shapes {:?}",
+                    shapes
+                ));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Applies `op` over the broadcast of `a` and `b` into a new contiguous array.
+pub fn rust_binary(op: BinOp, a: &Array, b: &Array) -> Result<Array, String> {
+    let shape = broadcast_shape(&[a.shape(), b.shape()])?;
+    if let (Some(xs), Some(ys)) = (a.as_slice(), b.as_slice()) {
+        if a.shape() == b.shape() {
+            let data = xs
+                .par_iter()
+                .zip(ys.par_iter())
+                .map(|(&x, &y)| op.apply(x, y))
+                .collect();
+            return Ok(Array::from_vec(data, shape));
+        }
+    }
+
+    let (a, b) = (a.broadcast_to(&shape)?, b.broadcast_to(&shape)?);
+    let size = shape.iter().product::<usize>();
+    let mut data = vec![0.; size];
+    let last = shape.last().copied().unwrap_or(1);
+    if size > 0 {
+        let (sa, sb) = (last_stride(&a), last_stride(&b));
+        let (ra, rb) = (a.raw(), b.raw());
+        data.par_chunks_mut(last)
+            .enumerate()
+            .for_each(|(lane, chunk)| {
+                let (ba, bb) = (a.lane_base(lane), b.lane_base(lane));
+                chunk.iter_mut().enumerate().for_each(|(i, e)| {
+                    let i = i as isize;
+                    *e = op.apply(ra[(ba + i * sa) as usize], rb[(bb + i * sb) as usize]);
+                })
+            });
+    }
+    Ok(Array::from_vec(data, shape))
+}
+
+pub fn last_stride(a: &Array) -> isize {
+    a.strides().last().copied().unwrap_or(0)
+}
+
+/// Like `rust_binary`, but stores the result into `out`, which must already
+/// have the broadcast shape. `out` may alias either operand.
+pub fn rust_binary_into(op: BinOp, a: &Array, b: &Array, out: &Array) -> Result<(), String> {
+    out.check_writable()?;
+    let res = rust_binary(op, a, b)?;
+    if res.shape() != out.shape() {
+        return Err(format!(
+            "non-broadcastable output operand with shape {:?} doesn't match the broadcast shape {:?}",
+            out.shape(),
+            res.shape()
+        ));
+    }
+    out.assign(res.as_slice().unwrap());
+    Ok(())
+}
+
+pub fn binary_pyfunction(
+    py: Python,
+    op: BinOp,
+    a: &PyAny,
+    b: &PyAny,
+    out: Option<&PyCell<Array>>,
+) -> PyResult<PyObject> {
+    let (a, b) = (to_array(a)?, to_array(b)?);
+    match out {
+        Some(cell) => {
+            rust_binary_into(op, &a, &b, &cell.borrow()).map_err(PyTypeError::new_err)?;
+            Ok(cell.into_py(py))
+        }
+        None => Ok(rust_binary(op, &a, &b)
+            .map_err(PyTypeError::new_err)?
+            .into_py(py)),
+    }
+}
+
+pub fn unary_pyfunction(
+    py: Python,
+    op: UnaryOp,
+    a: &PyAny,
+    out: Option<&PyCell<Array>>,
+) -> PyResult<PyObject> {
+    let res = rust_unary(op, &to_array(a)?);
+    match out {
+        Some(cell) => {
+            let out = cell.borrow();
+            out.check_writable().map_err(PyTypeError::new_err)?;
+            if out.shape() != res.shape() {
+                return Err(PyTypeError::new_err(
+                    "Output parameter has the wrong shape.",
+                ));
+            }
+            out.assign(res.as_slice().unwrap());
+            drop(out);
+            Ok(cell.into_py(py))
+        }
+        None => Ok(res.into_py(py)),
+    }
+}
+
+#[pyfunction]
+pub fn exp(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Exp, a, out)
+}
+
+#[pyfunction]
+pub fn log(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Log, a, out)
+}
+
+#[pyfunction]
+pub fn log1p(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Log1p, a, out)
+}
+
+#[pyfunction]
+pub fn expm1(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Expm1, a, out)
+}
+
+#[pyfunction]
+pub fn sin(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Sin, a, out)
+}
+
+#[pyfunction]
+pub fn cos(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Cos, a, out)
+}
+
+#[pyfunction]
+pub fn tan(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Tan, a, out)
+}
+
+#[pyfunction]
+pub fn tanh(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Tanh, a, out)
+}
+
+#[pyfunction]
+pub fn sqrt(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Sqrt, a, out)
+}
+
+#[pyfunction]
+pub fn abs(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Abs, a, out)
+}
+
+#[pyfunction]
+pub fn sign(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Sign, a, out)
+}
+
+#[pyfunction]
+pub fn floor(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Floor, a, out)
+}
+
+#[pyfunction]
+pub fn ceil(py: Python, a: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    unary_pyfunction(py, UnaryOp::Ceil, a, out)
+}
+
+#[pyfunction]
+pub fn clip(
+    py: Python,
+    a: &PyAny,
+    a_min: Option<f64>,
+    a_max: Option<f64>,
+    out: Option<&PyCell<Array>>,
+) -> PyResult<PyObject> {
+    match (a_min, a_max) {
+        (None, None) => Err(PyTypeError::new_err("One of max or min must be given.")),
+        (lo, hi) => {
+            let op = UnaryOp::Clip(lo.unwrap_or(f64::NEG_INFINITY), hi.unwrap_or(f64::INFINITY));
+            unary_pyfunction(py, op, a, out)
+        }
+    }
+}
+
+#[pyfunction]
+pub fn add(py: Python, a: &PyAny, b: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    binary_pyfunction(py, BinOp::Add, a, b, out)
+}
+
+#[pyfunction]
+pub fn subtract(
+    py: Python,
+    a: &PyAny,
+    b: &PyAny,
+    out: Option<&PyCell<Array>>,
+) -> PyResult<PyObject> {
+    binary_pyfunction(py, BinOp::Sub, a, b, out)
+}
+
+#[pyfunction]
+pub fn multiply(
+    py: Python,
+    a: &PyAny,
+    b: &PyAny,
+    out: Option<&PyCell<Array>>,
+) -> PyResult<PyObject> {
+    binary_pyfunction(py, BinOp::Mul, a, b, out)
+}
+
+#[pyfunction]
+pub fn divide(py: Python, a: &PyAny, b: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    binary_pyfunction(py, BinOp::Div, a, b, out)
+}
+
+#[pyfunction]
+pub fn power(py: Python, a: &PyAny, b: &PyAny, out: Option<&PyCell<Array>>) -> PyResult<PyObject> {
+    binary_pyfunction(py, BinOp::Pow, a, b, out)
+}
+
+#[pyfunction]
+pub fn maximum(
+    py: Python,
+    a: &PyAny,
+    b: &PyAny,
+    out: Option<&PyCell<Array>>,
+) -> PyResult<PyObject> {
+    binary_pyfunction(py, BinOp::Max, a, b, out)
+}
+
+#[pyfunction]
+pub fn minimum(
+    py: Python,
+    a: &PyAny,
+    b: &PyAny,
+    out: Option<&PyCell<Array>>,
+) -> PyResult<PyObject> {
+    binary_pyfunction(py, BinOp::Min, a, b, out)
+}
+
+mod test {
+    #[test]
+    fn broadcast_shape_test() {
+        use super::broadcast_shape;
+        assert_eq!(
+            broadcast_shape(&[&[3, 1, 5], &[4, 1], &[]]).unwrap(),
+            vec![3, 4, 5]
+        );
+        assert!(broadcast_shape(&[&[3, 2], &[3]]).is_err());
+    }
+
+    #[test]
+    fn binary_test() {
+        use super::{rust_binary, BinOp};
+        use crate::array::Array;
+        let a = Array::from_vec(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+        let row = Array::from_vec(vec![10., 20., 30.], vec![3]);
+        let col = Array::from_vec(vec![1., -1.], vec![2, 1]);
+
+        let sum = rust_binary(BinOp::Add, &a, &row).unwrap();
+        assert_eq!(sum.to_vec(), vec![11., 22., 33., 14., 25., 36.]);
+        let prod = rust_binary(BinOp::Mul, &a.t(), &col.t()).unwrap();
+        assert_eq!(prod.shape(), &[3, 2]);
+        assert_eq!(prod.to_vec(), vec![1., -4., 2., -5., 3., -6.]);
+        let sq = rust_binary(BinOp::Pow, &a, &Array::scalar(2.)).unwrap();
+        assert_eq!(sq.to_vec(), vec![1., 4., 9., 16., 25., 36.]);
+        let mx = rust_binary(BinOp::Max, &row, &Array::scalar(f64::NAN)).unwrap();
+        assert!(mx.to_vec().iter().all(|e| e.is_nan()));
+    }
+
+    #[test]
+    fn binary_into_test() {
+        use super::{rust_binary_into, BinOp};
+        use crate::array::Array;
+        let a = Array::from_vec(vec![1., 2., 3., 4.], vec![2, 2]);
+        let b = Array::from_vec(vec![1., 1.], vec![2]);
+        rust_binary_into(BinOp::Sub, &a, &b, &a).unwrap();
+        assert_eq!(a.to_vec(), vec![0., 1., 2., 3.]);
+        assert!(rust_binary_into(BinOp::Sub, &a, &b, &b).is_err());
+    }
+
+    #[test]
+    fn unary_test() {
+        use super::{rust_unary, UnaryOp};
+        use crate::array::Array;
+        let a = Array::from_vec(vec![-1.5, 0., 2.25, f64::NAN], vec![2, 2]);
+        assert_eq!(rust_unary(UnaryOp::Sign, &a).to_vec()[..3], [-1., 0., 1.]);
+        assert_eq!(
+            rust_unary(UnaryOp::Floor, &a.t()).to_vec()[..3],
+            [-2., 2., 0.]
+        );
+        let clipped = rust_unary(UnaryOp::Clip(-1., 1.), &a).to_vec();
+        assert_eq!(clipped[..3], [-1., 0., 1.]);
+        assert!(clipped[3].is_nan());
+
+        let big = Array::from_vec((0..100_000).map(|e| e as f64).collect(), vec![100_000]);
+        let roots = rust_unary(UnaryOp::Sqrt, &big).to_vec();
+        assert_eq!(roots[99_856], 316.);
+    }
+}
@@ -0,0 +1,400 @@
+use crate::array::{to_array, Array};
+use crate::elementwise_ops::{broadcast_shape, BinOp, UnaryOp};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                i += 1;
+                if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let lit: String = chars[start..i].iter().collect();
+            let x = lit
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number `{}`", lit))?;
+            tokens.push(Token::Num(x));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let tok = match c {
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                '+' => Token::Op("+"),
+                '-' => Token::Op("-"),
+                '/' => Token::Op("/"),
+                '*' if chars.get(i + 1) == Some(&'*') => {
+                    i += 1;
+                    Token::Op("**")
+                }
+                '*' => Token::Op("*"),
+                _ => return Err(format!("unexpected character `{}`", c)),
+            };
+            tokens.push(tok);
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Instr {
+    Const(f64),
+    Load(usize),
+    Unary(UnaryOp),
+    Binary(BinOp),
+}
+
+/// Stack bytecode for an expression, plus the variable each `Load` refers to.
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub code: Vec<Instr>,
+    pub vars: Vec<String>,
+    depth: usize,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    code: Vec<Instr>,
+    vars: Vec<String>,
+}
+
+// expr  := term (('+' | '-') term)*
+// term  := unary (('*' | '/') unary)*
+// unary := '-' unary | '+' unary | power
+// power := atom ('**' unary)?
+// atom  := number | name | name '(' expr (',' expr)* ')' | '(' expr ')'
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1).cloned()
+    }
+
+    fn expect(&mut self, tok: Token) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == tok => Ok(()),
+            t => Err(format!("expected {:?}, found {:?}", tok, t)),
+        }
+    }
+
+    fn expr(&mut self) -> Result<(), String> {
+        self.term()?;
+        while let Some(Token::Op(op @ ("+" | "-"))) = self.peek().cloned() {
+            self.pos += 1;
+            self.term()?;
+            self.code.push(Instr::Binary(if op == "+" {
+                BinOp::Add
+            } else {
+                BinOp::Sub
+            }));
+        }
+        Ok(())
+    }
+
+    fn term(&mut self) -> Result<(), String> {
+        self.unary()?;
+        while let Some(Token::Op(op @ ("*" | "/"))) = self.peek().cloned() {
+            self.pos += 1;
+            self.unary()?;
+            self.code.push(Instr::Binary(if op == "*" {
+                BinOp::Mul
+            } else {
+                BinOp::Div
+            }));
+        }
+        Ok(())
+    }
+
+    fn unary(&mut self) -> Result<(), String> {
+        match self.peek() {
+            Some(Token::Op("-")) => {
+                self.pos += 1;
+                self.unary()?;
+                self.code.push(Instr::Const(-1.));
+                self.code.push(Instr::Binary(BinOp::Mul));
+                Ok(())
+            }
+            Some(Token::Op("+")) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<(), String> {
+        self.atom()?;
+        if let Some(Token::Op("**")) = self.peek() {
+            self.pos += 1;
+            self.unary()?;
+            self.code.push(Instr::Binary(BinOp::Pow));
+        }
+        Ok(())
+    }
+
+    fn atom(&mut self) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Num(x)) => self.code.push(Instr::Const(x)),
+            Some(Token::LParen) => {
+                self.expr()?;
+                self.expect(Token::RParen)?;
+            }
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                let mut argc = 0;
+                if self.peek() != Some(&Token::RParen) {
+                    self.expr()?;
+                    argc += 1;
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        self.expr()?;
+                        argc += 1;
+                    }
+                }
+                self.expect(Token::RParen)?;
+                self.code.push(function_instr(&name, argc)?);
+            }
+            Some(Token::Ident(name)) => {
+                let i = match self.vars.iter().position(|v| *v == name) {
+                    Some(i) => i,
+                    None => {
+                        self.vars.push(name);
+                        self.vars.len() - 1
+                    }
+                };
+                self.code.push(Instr::Load(i));
+            }
+            t => return Err(format!("unexpected token {:?}", t)),
+        }
+        Ok(())
+    }
+}
+
+fn function_instr(name: &str, argc: usize) -> Result<Instr, String> {
+    let unary = match name {
+        "exp" => Some(UnaryOp::Exp),
+        "log" => Some(UnaryOp::Log),
+        "log1p" => Some(UnaryOp::Log1p),
+        "expm1" => Some(UnaryOp::Expm1),
+        "sin" => Some(UnaryOp::Sin),
+        "cos" => Some(UnaryOp::Cos),
+        "tan" => Some(UnaryOp::Tan),
+        "tanh" => Some(UnaryOp::Tanh),
+        "sqrt" => Some(UnaryOp::Sqrt),
+        "abs" => Some(UnaryOp::Abs),
+        "sign" => Some(UnaryOp::Sign),
+        "floor" => Some(UnaryOp::Floor),
+        "ceil" => Some(UnaryOp::Ceil),
+        _ => None,
+    };
+    let binary = match name {
+        "power" => Some(BinOp::Pow),
+        "maximum" => Some(BinOp::Max),
+        "minimum" => Some(BinOp::Min),
+        _ => None,
+    };
+    match (unary, binary, argc) {
+        (Some(op), _, 1) => Ok(Instr::Unary(op)),
+        (_, Some(op), 2) => Ok(Instr::Binary(op)),
+        (None, None, _) => Err(format!("unknown function `{}`", name)),
+        _ => Err(format!("wrong number of arguments to `{}`", name)),
+    }
+}
+
+pub fn compile(src: &str) -> Result<Program, String> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+        code: vec![],
+        vars: vec![],
+    };
+    parser.expr()?;
+    if let Some(t) = parser.peek() {
+        return Err(format!("unexpected token {:?}", t));
+    }
+
+    let mut depth = 0usize;
+    let mut max_depth = 0;
+    for instr in &parser.code {
+        match instr {
+            Instr::Const(_) | Instr::Load(_) => depth += 1,
+            Instr::Binary(_) => depth -= 1,
+            Instr::Unary(_) => {}
+        }
+        max_depth = max_depth.max(depth);
+    }
+    Ok(Program {
+        code: parser.code,
+        vars: parser.vars,
+        depth: max_depth,
+    })
+}
+
+const EVAL_CHUNK: usize = 4096;
+
+impl Program {
+    /// Runs the program over one chunk. `regs` holds one chunk-sized buffer
+    /// per stack slot and is reused between chunks, so nothing is allocated
+    /// per operation.
+    fn run_chunk(&self, inputs: &[Array], start: usize, regs: &mut [Vec<f64>], out: &mut [f64]) {
+        let len = out.len();
+        let mut sp = 0;
+        for instr in &self.code {
+            match *instr {
+                Instr::Const(x) => {
+                    regs[sp][..len].fill(x);
+                    sp += 1;
+                }
+                Instr::Load(i) => {
+                    inputs[i].gather_into(start, &mut regs[sp][..len]);
+                    sp += 1;
+                }
+                Instr::Unary(op) => {
+                    regs[sp - 1][..len]
+                        .iter_mut()
+                        .for_each(|x| *x = op.apply(*x));
+                }
+                Instr::Binary(op) => {
+                    let (lhs, rhs) = regs.split_at_mut(sp - 1);
+                    lhs[sp - 2][..len]
+                        .iter_mut()
+                        .zip(&rhs[0][..len])
+                        .for_each(|(x, &y)| *x = op.apply(*x, y));
+                    sp -= 1;
+                }
+            }
+        }
+        out.copy_from_slice(&regs[0][..len]);
+    }
+
+    /// Evaluates the program over the broadcast of `inputs` (in `vars`
+    /// order) in a single pass: each rayon task takes a chunk of the output
+    /// and runs the whole program over it, so no full-size temporaries are
+    /// ever created.
+    pub fn eval(&self, inputs: &[Array]) -> Result<Array, String> {
+        let shapes: Vec<&[usize]> = inputs.iter().map(|a| a.shape()).collect();
+        let shape = broadcast_shape(&shapes)?;
+        let inputs = inputs
+            .iter()
+            .map(|a| a.broadcast_to(&shape))
+            .collect::<Result<Vec<Array>, String>>()?;
+
+        let mut out = vec![0.; shape.iter().product()];
+        out.par_chunks_mut(EVAL_CHUNK).enumerate().for_each_init(
+            || vec![vec![0.; EVAL_CHUNK]; self.depth],
+            |regs, (ci, chunk)| self.run_chunk(&inputs, ci * EVAL_CHUNK, regs, chunk),
+        );
+        Ok(Array::from_vec(out, shape))
+    }
+}
+
+#[pyfunction]
+pub fn evaluate(py: Python, ex: &str, local_dict: Option<&PyDict>) -> PyResult<Array> {
+    let program = compile(ex).map_err(PyTypeError::new_err)?;
+    let (locals, globals) = match local_dict {
+        Some(dict) => (dict, None),
+        None => {
+            // like numexpr, fall back to the caller's frame
+            let frame = py.import("sys")?.call_method1("_getframe", (0,))?;
+            let locals = frame.getattr("f_locals")?.downcast::<PyDict>()?;
+            let globals = frame.getattr("f_globals")?.downcast::<PyDict>()?;
+            (locals, Some(globals))
+        }
+    };
+
+    let inputs = program
+        .vars
+        .iter()
+        .map(|name| {
+            let value = locals
+                .get_item(name)
+                .or_else(|| globals.and_then(|g| g.get_item(name)));
+            match value {
+                Some(value) => to_array(value),
+                None => Err(PyTypeError::new_err(format!(
+                    "Unknown variable `{}`.",
+                    name
+                ))),
+            }
+        })
+        .collect::<PyResult<Vec<Array>>>()?;
+    program.eval(&inputs).map_err(PyTypeError::new_err)
+}
+
+mod test {
+    #[allow(dead_code)]
+    fn eval_scalar(src: &str) -> f64 {
+        super::compile(src).unwrap().eval(&[]).unwrap().get(&[])
+    }
+
+    #[test]
+    fn precedence_test() {
+        assert_eq!(eval_scalar("1 + 2 * 3"), 7.);
+        assert_eq!(eval_scalar("-2**2"), -4.);
+        assert_eq!(eval_scalar("2**3**2"), 512.);
+        assert_eq!(eval_scalar("(1 + 2) * 3 / 4"), 2.25);
+        assert_eq!(eval_scalar("maximum(1.5e1, -abs(-20))"), 15.);
+        assert!(super::compile("foo(1)").is_err());
+        assert!(super::compile("1 +").is_err());
+        assert!(super::compile("sin(1, 2)").is_err());
+    }
+
+    #[test]
+    fn fused_eval_test() {
+        use crate::array::Array;
+        let n = 10_000;
+        let a = Array::from_vec((0..n).map(|e| e as f64).collect(), vec![n]);
+        let b = Array::from_vec(vec![2.; n], vec![n]);
+        let c = Array::from_vec(vec![0.5, -0.5], vec![2, 1]);
+        let d = Array::from_vec((0..n).map(|e| (e as f64) / 7.).collect(), vec![n]);
+
+        let program = super::compile("a*b + c*sin(d)").unwrap();
+        assert_eq!(program.vars, vec!["a", "b", "c", "d"]);
+        let out = program.eval(&[a, b, c, d]).unwrap();
+        assert_eq!(out.shape(), &[2, n]);
+        for &i in &[0, 4095, 4096, 9999] {
+            let x = i as f64;
+            let want = x * 2. - 0.5 * (x / 7.).sin();
+            assert!((out.get(&[1, i]) - want).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,633 @@
+use crate::array::{normalize_axis, to_array, Array};
+use crate::elementwise_ops::broadcast_shape;
+use crate::shape_ops::rust_ravel;
+use pyo3::exceptions::{PyIndexError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PySequence, PySlice, PyString, PyTuple};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
+};
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
+
+/// One entry of a numpy-style index expression.
+#[derive(Clone)]
+pub enum Index {
+    Int(isize),
+    Slice(Option<isize>, Option<isize>, Option<isize>),
+    NewAxis,
+    Ellipsis,
+    /// Integer index array; the values must be integral.
+    Ints(Array),
+    /// Boolean mask, any non-zero entry counts as `True`.
+    Mask(Array),
+}
+
+/// Python's `slice.indices`: returns the first index, the number of
+/// elements and the step.
+fn resolve_slice(
+    start: Option<isize>,
+    stop: Option<isize>,
+    step: Option<isize>,
+    n: usize,
+) -> Result<(usize, usize, isize), String> {
+    let n = n as isize;
+    let step = step.unwrap_or(1);
+    if step == 0 {
+        return Err("slice step cannot be zero".to_string());
+    }
+    let (lower, upper) = if step > 0 { (0, n) } else { (-1, n - 1) };
+    let clamp = |i: Option<isize>, default: isize| match i {
+        None => default,
+        Some(i) if i < 0 => (i + n).max(lower),
+        Some(i) => i.min(upper),
+    };
+    let start = clamp(start, if step > 0 { lower } else { upper });
+    let stop = clamp(stop, if step > 0 { upper } else { lower });
+    let len = if step > 0 && stop > start {
+        (stop - start + step - 1) / step
+    } else if step < 0 && start > stop {
+        (start - stop - step - 1) / -step
+    } else {
+        0
+    };
+    Ok((start.max(0) as usize, len as usize, step))
+}
+
+/// `a` after applying the basic part of an index, plus the integer arrays
+/// that still have to be gathered along some of its axes. `split` is set
+/// when the advanced indices, counting scalar integers among them, are not
+/// all next to each other in the key.
+struct Selection {
+    view: Array,
+    advanced: Vec<(usize, Array)>,
+    split: bool,
+}
+
+fn select(a: &Array, key: &[Index]) -> Result<Selection, String> {
+    let consumed = key
+        .iter()
+        .map(|k| match k {
+            Index::Int(_) | Index::Slice(..) | Index::Ints(_) => 1,
+            Index::Mask(m) => m.ndim(),
+            Index::NewAxis | Index::Ellipsis => 0,
+        })
+        .sum::<usize>();
+    let ellipses = key.iter().filter(|k| matches!(k, Index::Ellipsis)).count();
+    if ellipses > 1 {
+        return Err("an index can only have a single ellipsis ('...')".to_string());
+    }
+    if consumed > a.ndim() {
+        return Err(format!(
+            "too many indices for array: array is {}-dimensional, but {} were indexed",
+            a.ndim(),
+            consumed
+        ));
+    }
+    let fill = a.ndim() - consumed;
+    let implicit = [Index::Ellipsis];
+    let tail: &[Index] = if ellipses == 0 { &implicit } else { &[] };
+
+    let (mut shape, mut strides) = (vec![], vec![]);
+    let mut offset = a.offset() as isize;
+    let mut advanced = vec![];
+    let mut d = 0;
+    // Index axes consumed or created so far, and where the last advanced
+    // index (or scalar integer) ended.
+    let (mut pos, mut last_end, mut split) = (0, None, false);
+    for k in key.iter().chain(tail) {
+        let width = match k {
+            Index::Int(_) | Index::Slice(..) | Index::NewAxis | Index::Ints(_) => 1,
+            Index::Mask(m) => m.ndim(),
+            Index::Ellipsis => fill,
+        };
+        if matches!(k, Index::Int(_) | Index::Ints(_) | Index::Mask(_)) {
+            split |= last_end.is_some_and(|end| end != pos);
+            last_end = Some(pos + width);
+        }
+        pos += width;
+        match k {
+            Index::Int(i) => {
+                let n = a.shape()[d] as isize;
+                let j = if *i < 0 { i + n } else { *i };
+                if j < 0 || j >= n {
+                    return Err(format!(
+                        "index {} is out of bounds for axis {} with size {}",
+                        i, d, n
+                    ));
+                }
+                offset += j * a.strides()[d];
+                d += 1;
+            }
+            Index::Slice(start, stop, step) => {
+                let (start, len, step) = resolve_slice(*start, *stop, *step, a.shape()[d])?;
+                if len > 0 {
+                    offset += start as isize * a.strides()[d];
+                }
+                shape.push(len);
+                strides.push(a.strides()[d] * step);
+                d += 1;
+            }
+            Index::NewAxis => {
+                shape.push(1);
+                strides.push(0);
+            }
+            Index::Ellipsis => {
+                shape.extend_from_slice(&a.shape()[d..d + fill]);
+                strides.extend_from_slice(&a.strides()[d..d + fill]);
+                d += fill;
+            }
+            Index::Ints(idx) => {
+                advanced.push((shape.len(), idx.clone()));
+                shape.push(a.shape()[d]);
+                strides.push(a.strides()[d]);
+                d += 1;
+            }
+            Index::Mask(m) => {
+                if m.ndim() == 0 {
+                    return Err("boolean scalar indices are not supported".to_string());
+                }
+                if m.shape() != &a.shape()[d..d + m.ndim()] {
+                    return Err(format!(
+                        "boolean index did not match indexed array along dimension {}",
+                        d
+                    ));
+                }
+                for (k, idx) in rust_nonzero(m).into_iter().enumerate() {
+                    advanced.push((shape.len(), idx));
+                    shape.push(a.shape()[d + k]);
+                    strides.push(a.strides()[d + k]);
+                }
+                d += m.ndim();
+            }
+        }
+    }
+    Ok(Selection {
+        view: a.with_layout(shape, strides, offset as usize),
+        advanced,
+        split,
+    })
+}
+
+/// Flattened plan for the advanced part of a selection: block `j` of the
+/// result starts at buffer offset `offsets[j]` and holds the elements at
+/// `offsets[j] + rel[t]`.
+struct Gather {
+    bshape: Vec<usize>,
+    offsets: Vec<isize>,
+    rshape: Vec<usize>,
+    rel: Vec<isize>,
+    /// Result axes that precede the broadcast index axes; non-zero only when
+    /// the advanced indices (scalar integers included) are adjacent, as in
+    /// numpy.
+    lead: usize,
+}
+
+impl Gather {
+    fn new(sel: &Selection) -> Result<Gather, String> {
+        let view = &sel.view;
+        let shapes = sel
+            .advanced
+            .iter()
+            .map(|(_, idx)| idx.shape())
+            .collect::<Vec<_>>();
+        let bshape = broadcast_shape(&shapes)?;
+        let mut offsets = vec![view.offset() as isize; bshape.iter().product()];
+        for (ax, idx) in &sel.advanced {
+            let (n, stride) = (view.shape()[*ax] as isize, view.strides()[*ax]);
+            let idx = idx.broadcast_to(&bshape)?.to_vec();
+            offsets
+                .par_iter_mut()
+                .zip(idx.par_iter())
+                .try_for_each(|(off, &i)| {
+                    if i.fract() != 0. {
+                        return Err("arrays used as indices must be of integer type".to_string());
+                    }
+                    let j = if i < 0. { i as isize + n } else { i as isize };
+                    if j < 0 || j >= n {
+                        return Err(format!(
+                            "index {} is out of bounds for axis {} with size {}",
+                            i, ax, n
+                        ));
+                    }
+                    *off += j * stride;
+                    Ok(())
+                })?;
+        }
+
+        let axes = sel.advanced.iter().map(|(ax, _)| *ax).collect::<Vec<_>>();
+        let keep = (0..view.ndim())
+            .filter(|d| !axes.contains(d))
+            .collect::<Vec<_>>();
+        let rshape = keep.iter().map(|&d| view.shape()[d]).collect::<Vec<_>>();
+        let mut rel = vec![0isize];
+        for &d in &keep {
+            let (n, s) = (view.shape()[d], view.strides()[d]);
+            rel = rel
+                .iter()
+                .flat_map(|&o| (0..n).map(move |i| o + i as isize * s))
+                .collect();
+        }
+        Ok(Gather {
+            bshape,
+            offsets,
+            rshape,
+            rel,
+            lead: if sel.split { 0 } else { axes[0] },
+        })
+    }
+
+    /// Axes of the blocked `(bshape, rshape)` layout and where they go in
+    /// the result.
+    fn moved_axes(&self) -> (Vec<isize>, Vec<isize>) {
+        let nb = self.bshape.len() as isize;
+        let lead = self.lead as isize;
+        ((0..nb).collect(), (lead..lead + nb).collect())
+    }
+
+    fn result_shape(&self) -> Vec<usize> {
+        let mut shape = self.rshape.clone();
+        for (i, &n) in self.bshape.iter().enumerate() {
+            shape.insert(self.lead + i, n);
+        }
+        shape
+    }
+}
+
+pub fn rust_getitem(a: &Array, key: &[Index]) -> Result<Array, String> {
+    let sel = select(a, key)?;
+    if sel.advanced.is_empty() {
+        return Ok(sel.view);
+    }
+    let g = Gather::new(&sel)?;
+    let raw = a.raw();
+    let r = g.rel.len();
+    let mut out = vec![0.; g.offsets.len() * r];
+    if r > 0 {
+        out.par_chunks_mut(r)
+            .zip(g.offsets.par_iter())
+            .for_each(|(block, &base)| {
+                block
+                    .iter_mut()
+                    .zip(&g.rel)
+                    .for_each(|(o, &rel)| *o = raw[(base + rel) as usize])
+            });
+    }
+    let (src, dst) = g.moved_axes();
+    let blocked = Array::from_vec(out, [g.bshape.clone(), g.rshape.clone()].concat());
+    Ok(blocked.moveaxis(&src, &dst)?.to_contiguous())
+}
+
+/// Scatters `value` (broadcast to the shape of `a[key]`) into `a`. Blocks
+/// are written in parallel unless the indices repeat, in which case the
+/// last write wins as in numpy.
+pub fn rust_setitem(a: &Array, key: &[Index], value: &Array) -> Result<(), String> {
+    let sel = select(a, key)?;
+    if sel.advanced.is_empty() {
+        sel.view
+            .assign(&value.broadcast_to(sel.view.shape())?.to_vec());
+        return Ok(());
+    }
+    let g = Gather::new(&sel)?;
+    let (src, dst) = g.moved_axes();
+    let value = value
+        .broadcast_to(&g.result_shape())?
+        .moveaxis(&dst, &src)?
+        .to_vec();
+    scatter(a, &g.offsets, &g.rel, &value);
+    Ok(())
+}
+
+fn scatter(a: &Array, offsets: &[isize], rel: &[isize], values: &[f64]) {
+    let r = rel.len();
+    if r == 0 {
+        return;
+    }
+    let out = a.raw_unsafe();
+    let write = |(block, &base): (&[f64], &isize)| {
+        block
+            .iter()
+            .zip(rel)
+            .for_each(|(&x, &rel)| unsafe { out.write((base + rel) as usize, x) })
+    };
+    let mut sorted = offsets.to_vec();
+    sorted.par_sort_unstable();
+    if sorted.windows(2).all(|w| w[0] != w[1]) {
+        values.par_chunks(r).zip(offsets.par_iter()).for_each(write);
+    } else {
+        values.chunks(r).zip(offsets.iter()).for_each(write);
+    }
+}
+
+/// `np.take`: elements of `a` (flattened if `axis` is `None`) at `indices`.
+pub fn rust_take(a: &Array, indices: &Array, axis: Option<isize>) -> Result<Array, String> {
+    let (a, ax) = match axis {
+        Some(axis) => (a.clone(), normalize_axis(axis, a.ndim())?),
+        None => (rust_ravel(a), 0),
+    };
+    let mut key = vec![Index::Slice(None, None, None); ax];
+    key.push(Index::Ints(indices.clone()));
+    rust_getitem(&a, &key)
+}
+
+/// `np.put`: writes `values` (cycled as needed) at the flat `indices` of `a`.
+pub fn rust_put(a: &Array, indices: &Array, values: &Array) -> Result<(), String> {
+    a.check_writable()?;
+    if values.size() == 0 || indices.size() == 0 {
+        return Ok(());
+    }
+    if a.size() == 0 {
+        return Err("cannot replace elements of an empty array".to_string());
+    }
+    let n = a.size() as isize;
+    let flat = indices
+        .to_vec()
+        .par_iter()
+        .map(|&i| {
+            let j = if i < 0. { i as isize + n } else { i as isize };
+            if i.fract() != 0. || j < 0 || j >= n {
+                return Err(format!(
+                    "index {} is out of bounds for axis 0 with size {}",
+                    i, n
+                ));
+            }
+            Ok(j as usize)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let offsets = flat
+        .par_iter()
+        .map(|&k| {
+            let mut rem = k;
+            let mut off = a.offset() as isize;
+            for ax in (0..a.ndim()).rev() {
+                off += (rem % a.shape()[ax]) as isize * a.strides()[ax];
+                rem /= a.shape()[ax];
+            }
+            off
+        })
+        .collect::<Vec<_>>();
+    let vs = values.to_vec();
+    let cycled = (0..offsets.len())
+        .map(|j| vs[j % vs.len()])
+        .collect::<Vec<_>>();
+    scatter(a, &offsets, &[0], &cycled);
+    Ok(())
+}
+
+/// `np.where(cond, x, y)` with broadcasting; non-zero (and NaN) entries of
+/// `cond` select from `x`.
+pub fn rust_where(cond: &Array, x: &Array, y: &Array) -> Result<Array, String> {
+    let shape = broadcast_shape(&[cond.shape(), x.shape(), y.shape()])?;
+    let c = cond.broadcast_to(&shape)?.to_vec();
+    let x = x.broadcast_to(&shape)?.to_vec();
+    let y = y.broadcast_to(&shape)?.to_vec();
+    let out = c
+        .par_iter()
+        .zip(x.par_iter().zip(y.par_iter()))
+        .map(|(&c, (&x, &y))| if c != 0. { x } else { y })
+        .collect();
+    Ok(Array::from_vec(out, shape))
+}
+
+/// Per-axis indices of the non-zero elements, in row-major order.
+pub fn rust_nonzero(a: &Array) -> Vec<Array> {
+    let data = a.to_vec();
+    let hits = data
+        .par_iter()
+        .enumerate()
+        .filter(|(_, &x)| x != 0.)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let shape = if a.ndim() == 0 {
+        vec![1]
+    } else {
+        a.shape().to_vec()
+    };
+    (0..shape.len())
+        .map(|ax| {
+            let inner = shape[ax + 1..].iter().product::<usize>();
+            let n = shape[ax];
+            let idx = hits.par_iter().map(|&i| ((i / inner) % n) as f64).collect();
+            Array::from_vec(idx, vec![hits.len()])
+        })
+        .collect()
+}
+
+/// Strings are sequences whose items are strings again, so they are never
+/// descended into.
+fn is_text(obj: &PyAny) -> PyResult<bool> {
+    Ok(obj.is_instance_of::<PyString>()? || obj.is_instance_of::<PyBytes>()?)
+}
+
+/// Whether the innermost first element of a (nested) sequence is a bool,
+/// which makes it a mask rather than integer indices.
+fn is_mask(obj: &PyAny) -> PyResult<bool> {
+    let mut obj = obj;
+    while !is_text(obj)? {
+        let seq = match obj.downcast::<PySequence>() {
+            Ok(seq) => seq,
+            Err(_) => break,
+        };
+        if seq.len()? == 0 {
+            return Ok(false);
+        }
+        obj = seq.get_item(0)?;
+    }
+    obj.is_instance_of::<PyBool>()
+}
+
+fn parse_index(obj: &PyAny) -> PyResult<Index> {
+    if obj.is_none() {
+        return Ok(Index::NewAxis);
+    }
+    if obj.get_type().name()? == "ellipsis" {
+        return Ok(Index::Ellipsis);
+    }
+    if obj.is_instance_of::<PyBool>()? {
+        return Err(PyIndexError::new_err(
+            "boolean scalar indices are not supported",
+        ));
+    }
+    if is_text(obj)? {
+        return Err(PyIndexError::new_err(
+            "only integers, slices (`:`), ellipsis (`...`), numpy.newaxis (`None`) \
+             and integer or boolean arrays are valid indices",
+        ));
+    }
+    if let Ok(i) = obj.extract::<isize>() {
+        return Ok(Index::Int(i));
+    }
+    if let Ok(s) = obj.downcast::<PySlice>() {
+        return Ok(Index::Slice(
+            s.getattr("start")?.extract()?,
+            s.getattr("stop")?.extract()?,
+            s.getattr("step")?.extract()?,
+        ));
+    }
+    if let Ok(a) = obj.extract::<PyRef<Array>>() {
+        return Ok(Index::Ints(a.clone()));
+    }
+    if is_mask(obj)? {
+        Ok(Index::Mask(to_array(obj)?))
+    } else {
+        Ok(Index::Ints(to_array(obj)?))
+    }
+}
+
+/// A tuple indexes one axis per entry; anything else is a single entry.
+pub fn parse_key(key: &PyAny) -> PyResult<Vec<Index>> {
+    match key.downcast::<PyTuple>() {
+        Ok(items) => items.iter().map(parse_index).collect(),
+        Err(_) => Ok(vec![parse_index(key)?]),
+    }
+}
+
+#[pyfunction]
+pub fn take(a: &PyAny, indices: &PyAny, axis: Option<isize>) -> PyResult<Array> {
+    rust_take(&to_array(a)?, &to_array(indices)?, axis).map_err(PyIndexError::new_err)
+}
+
+#[pyfunction]
+pub fn put(a: PyRef<Array>, ind: &PyAny, v: &PyAny) -> PyResult<()> {
+    rust_put(&a, &to_array(ind)?, &to_array(v)?).map_err(PyIndexError::new_err)
+}
+
+/// Exported as `where`, which is a keyword in Rust.
+#[pyfunction]
+pub fn where_(
+    py: Python,
+    condition: &PyAny,
+    x: Option<&PyAny>,
+    y: Option<&PyAny>,
+) -> PyResult<PyObject> {
+    let cond = to_array(condition)?;
+    match (x, y) {
+        (Some(x), Some(y)) => Ok(rust_where(&cond, &to_array(x)?, &to_array(y)?)
+            .map_err(PyTypeError::new_err)?
+            .into_py(py)),
+        (None, None) => Ok(PyTuple::new(
+            py,
+            rust_nonzero(&cond).into_iter().map(|a| a.into_py(py)),
+        )
+        .into_py(py)),
+        _ => Err(PyTypeError::new_err(
+            "either both or neither of x and y should be given",
+        )),
+    }
+}
+
+#[pyfunction]
+pub fn nonzero(py: Python, a: &PyAny) -> PyResult<PyObject> {
+    let idx = rust_nonzero(&to_array(a)?);
+    Ok(PyTuple::new(py, idx.into_iter().map(|a| a.into_py(py))).into_py(py))
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::array::test::arange;
+
+    #[test]
+    fn basic_index_test() {
+        use super::Index::*;
+        let a = arange(vec![3, 4]);
+        let v = super::rust_getitem(&a, &[Int(-1), Slice(None, None, Some(-2))]).unwrap();
+        assert_eq!(v.shape(), &[2]);
+        assert!(v.shares_buffer(&a));
+        assert_eq!(v.to_vec(), vec![11., 9.]);
+
+        let v =
+            super::rust_getitem(&a, &[Ellipsis, NewAxis, Slice(Some(1), Some(3), None)]).unwrap();
+        assert_eq!(v.shape(), &[3, 1, 2]);
+        assert_eq!(v.to_vec(), vec![1., 2., 5., 6., 9., 10.]);
+        let empty = super::rust_getitem(&a, &[Slice(Some(5), None, None)]).unwrap();
+        assert_eq!(empty.shape(), &[0, 4]);
+
+        assert!(super::rust_getitem(&a, &[Int(3)]).is_err());
+        assert!(super::rust_getitem(&a, &[Int(0), Int(0), Int(0)]).is_err());
+    }
+
+    #[test]
+    fn fancy_index_test() {
+        use super::Index::*;
+        use crate::array::Array;
+        let a = arange(vec![3, 4]);
+        let rows = Array::from_vec(vec![2., 0.], vec![2]);
+        let g = super::rust_getitem(&a, &[Ints(rows.clone())]).unwrap();
+        assert_eq!(g.shape(), &[2, 4]);
+        assert_eq!(g.to_vec(), vec![8., 9., 10., 11., 0., 1., 2., 3.]);
+
+        let cols = Array::from_vec(vec![1., -1.], vec![2]);
+        let g = super::rust_getitem(&a, &[Ints(rows), Ints(cols.clone())]).unwrap();
+        assert_eq!(g.to_vec(), vec![9., 3.]);
+
+        let g = super::rust_getitem(&a, &[Slice(None, None, None), Ints(cols)]).unwrap();
+        assert_eq!(g.shape(), &[3, 2]);
+        assert_eq!(g.to_vec(), vec![1., 3., 5., 7., 9., 11.]);
+
+        let mask = Array::from_vec(vec![1., 0., 1.], vec![3]);
+        let g = super::rust_getitem(&a, &[Mask(mask), Int(0)]).unwrap();
+        assert_eq!(g.to_vec(), vec![0., 8.]);
+        let bad = Array::from_vec(vec![0.5], vec![1]);
+        assert!(super::rust_getitem(&a, &[Ints(bad)]).is_err());
+
+        // A scalar integer is an advanced index too: separated from `idx`
+        // by a slice, the broadcast axes go first.
+        let a = arange(vec![2, 3, 4]);
+        let idx = Array::from_vec(vec![3., 0.], vec![2]);
+        let all = Slice(None, None, None);
+        let key = [Int(0), all.clone(), Ints(idx.clone())];
+        let g = super::rust_getitem(&a, &key).unwrap();
+        assert_eq!(g.shape(), &[2, 3]);
+        assert_eq!(g.to_vec(), vec![3., 7., 11., 0., 4., 8.]);
+        let g = super::rust_getitem(&a, &[all.clone(), Int(0), Ints(idx.clone())]).unwrap();
+        assert_eq!(g.shape(), &[2, 2]);
+        assert_eq!(g.to_vec(), vec![3., 0., 15., 12.]);
+        let rows = Array::from_vec(vec![1., 0.], vec![2]);
+        let g = super::rust_getitem(&a, &[Ints(rows), Int(1), all]).unwrap();
+        assert_eq!(g.shape(), &[2, 4]);
+        assert_eq!(g.to_vec()[..4], [16., 17., 18., 19.]);
+    }
+
+    #[test]
+    fn setitem_test() {
+        use super::Index::*;
+        use crate::array::Array;
+        let a = arange(vec![2, 3]);
+        super::rust_setitem(&a, &[Slice(None, None, None), Int(1)], &Array::scalar(-1.)).unwrap();
+        assert_eq!(a.to_vec(), vec![0., -1., 2., 3., -1., 5.]);
+
+        let mask = Array::from_vec(vec![1., 0., 0., 0., 0., 1.], vec![2, 3]);
+        let vals = Array::from_vec(vec![7., 8.], vec![2]);
+        super::rust_setitem(&a, &[Mask(mask)], &vals).unwrap();
+        assert_eq!(a.to_vec(), vec![7., -1., 2., 3., -1., 8.]);
+
+        let idx = Array::from_vec(vec![0., 0.], vec![2]);
+        let vals = Array::from_vec(vec![1., 2.], vec![2]);
+        super::rust_setitem(&a, &[Int(1), Ints(idx)], &vals).unwrap();
+        assert_eq!(a.get(&[1, 0]), 2.);
+    }
+
+    #[test]
+    fn take_put_where_test() {
+        use crate::array::Array;
+        let a = arange(vec![2, 3]);
+        let idx = Array::from_vec(vec![2., 0.], vec![2]);
+        let t = super::rust_take(&a, &idx, Some(1)).unwrap();
+        assert_eq!(t.to_vec(), vec![2., 0., 5., 3.]);
+        assert_eq!(
+            super::rust_take(&a, &idx, None).unwrap().to_vec(),
+            vec![2., 0.]
+        );
+
+        let t = a.t();
+        let flat = Array::from_vec(vec![1., -1., 3.], vec![3]);
+        super::rust_put(&t, &flat, &Array::from_vec(vec![10., 20.], vec![2])).unwrap();
+        assert_eq!(a.to_vec(), vec![0., 1., 2., 10., 10., 20.]);
+
+        let w =
+            super::rust_where(&arange(vec![3]), &Array::scalar(1.), &arange(vec![2, 1])).unwrap();
+        assert_eq!(w.to_vec(), vec![0., 1., 1., 1., 1., 1.]);
+        let nz = super::rust_nonzero(&a);
+        assert_eq!(nz[0].to_vec(), vec![0., 0., 1., 1., 1.]);
+        assert_eq!(nz[1].to_vec(), vec![1., 2., 0., 1., 2.]);
+    }
+}
@@ -0,0 +1,675 @@
+use crate::array::{to_array, Array};
+use crate::linear_operator::{LinearOperator, PyOperator};
+use crate::vector_ops::rust_dot;
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+/// Stopping rule and limits shared by the solvers. An iteration stops once
+/// the residual norm is at most `max(tol * ||b||, atol)`.
+#[derive(Clone, Debug)]
+pub struct KrylovOptions {
+    pub tol: f64,
+    pub atol: f64,
+    pub maxiter: usize,
+    /// Krylov subspace size between GMRES restarts.
+    pub restart: usize,
+}
+
+impl KrylovOptions {
+    pub fn new(n: usize) -> Self {
+        KrylovOptions {
+            tol: 1e-5,
+            atol: 0.,
+            maxiter: 10 * n.max(1),
+            restart: 20,
+        }
+    }
+}
+
+/// `info` follows scipy: 0 on convergence, the iteration count if
+/// `maxiter` ran out, negative on breakdown. `residuals` starts with the
+/// initial residual norm and has one entry per iteration.
+#[derive(Clone, Debug)]
+pub struct KrylovResult {
+    pub x: Vec<f64>,
+    pub info: isize,
+    pub residuals: Vec<f64>,
+}
+
+fn norm(x: &[f64]) -> f64 {
+    rust_dot(x, x).sqrt()
+}
+
+/// `y += k x`
+fn axpy(k: f64, x: &[f64], y: &mut [f64]) {
+    y.par_iter_mut().zip(x).for_each(|(y, &x)| *y += k * x);
+}
+
+fn residual(a: &dyn LinearOperator, b: &[f64], x: &[f64]) -> Result<Vec<f64>, String> {
+    let ax = a.matvec(x)?;
+    Ok(b.iter().zip(ax).map(|(&b, ax)| b - ax).collect())
+}
+
+fn precondition(m: Option<&dyn LinearOperator>, r: &[f64]) -> Result<Vec<f64>, String> {
+    match m {
+        Some(m) => m.matvec(r),
+        None => Ok(r.to_vec()),
+    }
+}
+
+/// Conjugate gradients for symmetric positive definite `A`, with `M` an
+/// SPD approximation of `A^-1`.
+pub fn rust_cg(
+    a: &dyn LinearOperator,
+    m: Option<&dyn LinearOperator>,
+    b: &[f64],
+    x0: Option<Vec<f64>>,
+    o: &KrylovOptions,
+) -> Result<KrylovResult, String> {
+    let target = (o.tol * norm(b)).max(o.atol);
+    let mut x = x0.unwrap_or_else(|| vec![0.; b.len()]);
+    let mut r = residual(a, b, &x)?;
+    let mut residuals = vec![norm(&r)];
+    if residuals[0] <= target {
+        return Ok(KrylovResult {
+            x,
+            info: 0,
+            residuals,
+        });
+    }
+    let mut z = precondition(m, &r)?;
+    let mut p = z.clone();
+    let mut rz = rust_dot(&r, &z);
+    for _ in 0..o.maxiter {
+        let ap = a.matvec(&p)?;
+        let pap = rust_dot(&p, &ap);
+        if pap <= 0. {
+            // A is not positive definite along p.
+            return Ok(KrylovResult {
+                x,
+                info: -1,
+                residuals,
+            });
+        }
+        let alpha = rz / pap;
+        axpy(alpha, &p, &mut x);
+        axpy(-alpha, &ap, &mut r);
+        residuals.push(norm(&r));
+        if residuals[residuals.len() - 1] <= target {
+            return Ok(KrylovResult {
+                x,
+                info: 0,
+                residuals,
+            });
+        }
+        z = precondition(m, &r)?;
+        let rz_next = rust_dot(&r, &z);
+        let beta = rz_next / rz;
+        rz = rz_next;
+        p.par_iter_mut()
+            .zip(&z)
+            .for_each(|(p, &z)| *p = z + beta * *p);
+    }
+    Ok(KrylovResult {
+        x,
+        info: o.maxiter as isize,
+        residuals,
+    })
+}
+
+/// Plane rotation `(c, s)` taking `(f, g)` to `(r, 0)`.
+fn givens(f: f64, g: f64) -> (f64, f64) {
+    if g == 0. {
+        return (1., 0.);
+    }
+    let r = f.hypot(g);
+    (f / r, g / r)
+}
+
+/// Restarted GMRES with right preconditioning, so the residuals recorded
+/// are those of the original system. Arnoldi uses modified Gram-Schmidt;
+/// every restart recomputes the true residual.
+pub fn rust_gmres(
+    a: &dyn LinearOperator,
+    m: Option<&dyn LinearOperator>,
+    b: &[f64],
+    x0: Option<Vec<f64>>,
+    o: &KrylovOptions,
+) -> Result<KrylovResult, String> {
+    let target = (o.tol * norm(b)).max(o.atol);
+    let restart = o.restart.max(1);
+    let mut x = x0.unwrap_or_else(|| vec![0.; b.len()]);
+    let mut r = residual(a, b, &x)?;
+    let mut beta = norm(&r);
+    let mut residuals = vec![beta];
+    let mut iters = 0;
+    while beta > target {
+        if iters >= o.maxiter {
+            return Ok(KrylovResult {
+                x,
+                info: iters as isize,
+                residuals,
+            });
+        }
+        let mut v = vec![r.iter().map(|r| r / beta).collect::<Vec<_>>()];
+        // Column j of the Hessenberg matrix, already rotated, is h[j].
+        let mut h: Vec<Vec<f64>> = vec![];
+        let mut rotations: Vec<(f64, f64)> = vec![];
+        let mut g = vec![beta];
+        while h.len() < restart && iters < o.maxiter {
+            let j = h.len();
+            let mut w = a.matvec(&precondition(m, &v[j])?)?;
+            iters += 1;
+            let mut col = Vec::with_capacity(j + 2);
+            for vi in &v {
+                let hij = rust_dot(&w, vi);
+                axpy(-hij, vi, &mut w);
+                col.push(hij);
+            }
+            let h_next = norm(&w);
+            for (i, &(c, s)) in rotations.iter().enumerate() {
+                let (x, y) = (col[i], col[i + 1]);
+                col[i] = c * x + s * y;
+                col[i + 1] = -s * x + c * y;
+            }
+            let (c, s) = givens(col[j], h_next);
+            col[j] = c * col[j] + s * h_next;
+            if col[j] == 0. || !col[j].is_finite() {
+                // A is singular on the Krylov space, so H y = g has no
+                // solution there.
+                return Ok(KrylovResult {
+                    x,
+                    info: -1,
+                    residuals,
+                });
+            }
+            g.push(-s * g[j]);
+            g[j] *= c;
+            rotations.push((c, s));
+            h.push(col);
+            residuals.push(g[j + 1].abs());
+            if h_next == 0. || g[j + 1].abs() <= target {
+                break;
+            }
+            v.push(w.iter().map(|w| w / h_next).collect());
+        }
+        // Back substitution for y in H y = g, then x += M (V y).
+        let k = h.len();
+        let mut y = vec![0.; k];
+        for i in (0..k).rev() {
+            let s = ((i + 1)..k).map(|j| h[j][i] * y[j]).sum::<f64>();
+            y[i] = (g[i] - s) / h[i][i];
+        }
+        let mut update = vec![0.; b.len()];
+        for (vi, &yi) in v.iter().zip(&y) {
+            axpy(yi, vi, &mut update);
+        }
+        let mut next = x.clone();
+        axpy(1., &precondition(m, &update)?, &mut next);
+        r = residual(a, b, &next)?;
+        beta = norm(&r);
+        if !beta.is_finite() {
+            return Ok(KrylovResult {
+                x,
+                info: -1,
+                residuals,
+            });
+        }
+        x = next;
+    }
+    Ok(KrylovResult {
+        x,
+        info: 0,
+        residuals,
+    })
+}
+
+/// BiCGSTAB with right preconditioning; each iteration applies `A` twice.
+pub fn rust_bicgstab(
+    a: &dyn LinearOperator,
+    m: Option<&dyn LinearOperator>,
+    b: &[f64],
+    x0: Option<Vec<f64>>,
+    o: &KrylovOptions,
+) -> Result<KrylovResult, String> {
+    let target = (o.tol * norm(b)).max(o.atol);
+    let n = b.len();
+    let mut x = x0.unwrap_or_else(|| vec![0.; n]);
+    let mut r = residual(a, b, &x)?;
+    let mut residuals = vec![norm(&r)];
+    if residuals[0] <= target {
+        return Ok(KrylovResult {
+            x,
+            info: 0,
+            residuals,
+        });
+    }
+    let r_hat = r.clone();
+    let (mut rho, mut alpha, mut omega) = (1., 1., 1.);
+    let (mut p, mut v) = (vec![0.; n], vec![0.; n]);
+    for _ in 0..o.maxiter {
+        let rho_next = rust_dot(&r_hat, &r);
+        if rho_next == 0. || omega == 0. || !rho_next.is_finite() {
+            return Ok(KrylovResult {
+                x,
+                info: -1,
+                residuals,
+            });
+        }
+        let beta = (rho_next / rho) * (alpha / omega);
+        rho = rho_next;
+        p.par_iter_mut()
+            .zip(&r)
+            .zip(&v)
+            .for_each(|((p, &r), &v)| *p = r + beta * (*p - omega * v));
+        let p_hat = precondition(m, &p)?;
+        v = a.matvec(&p_hat)?;
+        let rv = rust_dot(&r_hat, &v);
+        alpha = rho / rv;
+        if rv == 0. || !alpha.is_finite() {
+            return Ok(KrylovResult {
+                x,
+                info: -1,
+                residuals,
+            });
+        }
+        let mut s = r.clone();
+        axpy(-alpha, &v, &mut s);
+        axpy(alpha, &p_hat, &mut x);
+        let s_norm = norm(&s);
+        if s_norm <= target {
+            residuals.push(s_norm);
+            return Ok(KrylovResult {
+                x,
+                info: 0,
+                residuals,
+            });
+        }
+        let s_hat = precondition(m, &s)?;
+        let t = a.matvec(&s_hat)?;
+        let tt = rust_dot(&t, &t);
+        omega = if tt == 0. { 0. } else { rust_dot(&t, &s) / tt };
+        if !omega.is_finite() {
+            return Ok(KrylovResult {
+                x,
+                info: -1,
+                residuals,
+            });
+        }
+        axpy(omega, &s_hat, &mut x);
+        axpy(-omega, &t, &mut s);
+        r = s;
+        residuals.push(norm(&r));
+        if residuals[residuals.len() - 1] <= target {
+            return Ok(KrylovResult {
+                x,
+                info: 0,
+                residuals,
+            });
+        }
+    }
+    Ok(KrylovResult {
+        x,
+        info: o.maxiter as isize,
+        residuals,
+    })
+}
+
+/// MINRES (Paige-Saunders) for symmetric, possibly indefinite `A`, with
+/// `M` symmetric positive definite. The residual recorded is the estimate
+/// the recurrence provides, measured in the `M` norm when preconditioned.
+pub fn rust_minres(
+    a: &dyn LinearOperator,
+    m: Option<&dyn LinearOperator>,
+    b: &[f64],
+    x0: Option<Vec<f64>>,
+    o: &KrylovOptions,
+) -> Result<KrylovResult, String> {
+    let target = (o.tol * norm(b)).max(o.atol);
+    let n = b.len();
+    let mut x = x0.unwrap_or_else(|| vec![0.; n]);
+    let mut r1 = residual(a, b, &x)?;
+    let mut y = precondition(m, &r1)?;
+    let ry = rust_dot(&r1, &y);
+    if ry < 0. {
+        return Err("minres: preconditioner is not positive definite".to_string());
+    }
+    let beta1 = ry.sqrt();
+    let mut residuals = vec![beta1];
+    if beta1 <= target {
+        return Ok(KrylovResult {
+            x,
+            info: 0,
+            residuals,
+        });
+    }
+    let mut r2 = r1.clone();
+    let (mut beta, mut old_beta) = (beta1, 0.);
+    let (mut dbar, mut epsilon, mut phibar) = (0., 0., beta1);
+    let (mut cs, mut sn) = (-1., 0.);
+    let (mut w, mut w2) = (vec![0.; n], vec![0.; n]);
+    for k in 0..o.maxiter {
+        let v = y.iter().map(|y| y / beta).collect::<Vec<_>>();
+        y = a.matvec(&v)?;
+        if k > 0 {
+            axpy(-beta / old_beta, &r1, &mut y);
+        }
+        let alpha = rust_dot(&v, &y);
+        axpy(-alpha / beta, &r2, &mut y);
+        r1 = std::mem::replace(&mut r2, y);
+        y = precondition(m, &r2)?;
+        old_beta = beta;
+        let ry = rust_dot(&r2, &y);
+        if ry < 0. {
+            return Err("minres: preconditioner is not positive definite".to_string());
+        }
+        beta = ry.sqrt();
+
+        let old_epsilon = epsilon;
+        let delta = cs * dbar + sn * alpha;
+        let gbar = sn * dbar - cs * alpha;
+        epsilon = sn * beta;
+        dbar = -cs * beta;
+        let gamma = gbar.hypot(beta).max(f64::EPSILON);
+        cs = gbar / gamma;
+        sn = beta / gamma;
+        let phi = cs * phibar;
+        phibar *= sn;
+
+        let w1 = std::mem::replace(&mut w2, w);
+        w = v
+            .iter()
+            .zip(&w1)
+            .zip(&w2)
+            .map(|((v, w1), w2)| (v - old_epsilon * w1 - delta * w2) / gamma)
+            .collect();
+        axpy(phi, &w, &mut x);
+        residuals.push(phibar);
+        if phibar <= target {
+            return Ok(KrylovResult {
+                x,
+                info: 0,
+                residuals,
+            });
+        }
+        if beta == 0. {
+            // The Krylov space is invariant, so x is exact for the
+            // part of b it can reach.
+            return Ok(KrylovResult {
+                x,
+                info: -1,
+                residuals,
+            });
+        }
+    }
+    Ok(KrylovResult {
+        x,
+        info: o.maxiter as isize,
+        residuals,
+    })
+}
+
+type Solver = fn(
+    &dyn LinearOperator,
+    Option<&dyn LinearOperator>,
+    &[f64],
+    Option<Vec<f64>>,
+    &KrylovOptions,
+) -> Result<KrylovResult, String>;
+
+/// Shared Python entry point: checks shapes, runs `solver` and returns
+/// `(x, info, residuals)`.
+#[allow(clippy::too_many_arguments, non_snake_case)]
+fn run_solver(
+    py: Python,
+    solver: Solver,
+    a: &PyAny,
+    b: &PyAny,
+    x0: Option<&PyAny>,
+    tol: f64,
+    atol: f64,
+    maxiter: Option<usize>,
+    M: Option<&PyAny>,
+    restart: Option<usize>,
+) -> PyResult<PyObject> {
+    let op_a = PyOperator::extract(a)?;
+    let shape = op_a.shape();
+    let b = to_array(b)?.to_vec();
+    if shape.0 != shape.1 || b.len() != shape.0 {
+        return Err(PyTypeError::new_err(format!(
+            "A must be square and match b: got {:?} and ({},)",
+            shape,
+            b.len()
+        )));
+    }
+    let n = b.len();
+    let x0 = x0.map(|x| to_array(x).map(|x| x.to_vec())).transpose()?;
+    if x0.as_ref().is_some_and(|x| x.len() != n) {
+        return Err(PyTypeError::new_err("x0 must have the length of b"));
+    }
+    let op_m = M.map(PyOperator::extract).transpose()?;
+    if let Some(s) = op_m.as_ref().map(|m| m.shape()).filter(|&s| s != (n, n)) {
+        return Err(PyTypeError::new_err(format!(
+            "M must have shape ({}, {}), got {:?}",
+            n, n, s
+        )));
+    }
+    let mut o = KrylovOptions::new(n);
+    o.tol = tol;
+    o.atol = atol;
+    o.maxiter = maxiter.unwrap_or(o.maxiter);
+    o.restart = restart.unwrap_or(o.restart);
+    let m = op_m.as_ref().map(|m| m as &dyn LinearOperator);
+    let res = solver(&op_a, m, &b, x0, &o).map_err(PyTypeError::new_err)?;
+    Ok(PyTuple::new(
+        py,
+        [
+            Array::from_vec(res.x, vec![n]).into_py(py),
+            res.info.into_py(py),
+            res.residuals.into_py(py),
+        ],
+    )
+    .into_py(py))
+}
+
+#[allow(clippy::too_many_arguments, non_snake_case)]
+#[pyfunction(tol = "1e-5", atol = "0.")]
+pub fn cg(
+    py: Python,
+    A: &PyAny,
+    b: &PyAny,
+    x0: Option<&PyAny>,
+    tol: f64,
+    atol: f64,
+    maxiter: Option<usize>,
+    M: Option<&PyAny>,
+) -> PyResult<PyObject> {
+    run_solver(py, rust_cg, A, b, x0, tol, atol, maxiter, M, None)
+}
+
+#[allow(clippy::too_many_arguments, non_snake_case)]
+#[pyfunction(tol = "1e-5", atol = "0.", restart = "20")]
+pub fn gmres(
+    py: Python,
+    A: &PyAny,
+    b: &PyAny,
+    x0: Option<&PyAny>,
+    tol: f64,
+    atol: f64,
+    restart: usize,
+    maxiter: Option<usize>,
+    M: Option<&PyAny>,
+) -> PyResult<PyObject> {
+    run_solver(
+        py,
+        rust_gmres,
+        A,
+        b,
+        x0,
+        tol,
+        atol,
+        maxiter,
+        M,
+        Some(restart),
+    )
+}
+
+#[allow(clippy::too_many_arguments, non_snake_case)]
+#[pyfunction(tol = "1e-5", atol = "0.")]
+pub fn bicgstab(
+    py: Python,
+    A: &PyAny,
+    b: &PyAny,
+    x0: Option<&PyAny>,
+    tol: f64,
+    atol: f64,
+    maxiter: Option<usize>,
+    M: Option<&PyAny>,
+) -> PyResult<PyObject> {
+    run_solver(py, rust_bicgstab, A, b, x0, tol, atol, maxiter, M, None)
+}
+
+#[allow(clippy::too_many_arguments, non_snake_case)]
+#[pyfunction(tol = "1e-5", atol = "0.")]
+pub fn minres(
+    py: Python,
+    A: &PyAny,
+    b: &PyAny,
+    x0: Option<&PyAny>,
+    tol: f64,
+    atol: f64,
+    maxiter: Option<usize>,
+    M: Option<&PyAny>,
+) -> PyResult<PyObject> {
+    run_solver(py, rust_minres, A, b, x0, tol, atol, maxiter, M, None)
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::sparse_ops::test::tridiagonal;
+
+    #[allow(dead_code)]
+    fn dense(a: Vec<f64>, n: usize) -> crate::array::Array {
+        crate::array::Array::from_vec(a, vec![n, n])
+    }
+
+    /// The diagonal matrix `diag(d)` in CSR.
+    #[allow(dead_code)]
+    fn diagonal(d: Vec<f64>) -> crate::sparse_ops::Csr {
+        let n = d.len();
+        crate::sparse_ops::Csr::new((n, n), (0..=n).collect(), (0..n).collect(), d).unwrap()
+    }
+
+    #[allow(dead_code)]
+    fn check(a: &crate::array::Array, x: &[f64], b: &[f64], tol: f64) {
+        use crate::linear_operator::LinearOperator;
+        let ax = a.matvec(x).unwrap();
+        let err = ax.iter().zip(b).map(|(p, q)| (p - q).powi(2)).sum::<f64>();
+        let b_norm = b.iter().map(|b| b * b).sum::<f64>();
+        assert!(err.sqrt() <= tol * b_norm.sqrt(), "{}", err.sqrt());
+    }
+
+    #[test]
+    fn cg_test() {
+        use super::{rust_cg, KrylovOptions};
+        let n = 50;
+        let a = tridiagonal(n, 2.).to_dense();
+        let b = (0..n).map(|i| (i as f64).sin()).collect::<Vec<_>>();
+        let mut o = KrylovOptions::new(n);
+        o.tol = 1e-10;
+        let res = rust_cg(&a, None, &b, None, &o).unwrap();
+        assert_eq!(res.info, 0);
+        check(&a, &res.x, &b, 1e-9);
+        // Exact arithmetic would need at most n steps.
+        assert!(res.residuals.len() <= n + 2);
+
+        // A Jacobi preconditioner on a badly scaled system.
+        let mut a = tridiagonal(n, 2.).to_dense().to_vec();
+        (0..n).for_each(|i| a[i * n + i] += (i * i) as f64);
+        let jacobi = diagonal((0..n).map(|i| 1. / a[i * n + i]).collect());
+        let a = dense(a, n);
+        let plain = rust_cg(&a, None, &b, None, &o).unwrap();
+        let pre = rust_cg(&a, Some(&jacobi), &b, None, &o).unwrap();
+        assert_eq!(pre.info, 0);
+        check(&a, &pre.x, &b, 1e-9);
+        assert!(pre.residuals.len() < plain.residuals.len());
+
+        o.maxiter = 3;
+        let capped = rust_cg(&a, None, &b, None, &o).unwrap();
+        assert_eq!((capped.info, capped.residuals.len()), (3, 4));
+    }
+
+    #[test]
+    fn nonsymmetric_test() {
+        use super::{rust_bicgstab, rust_gmres, KrylovOptions};
+        let n = 40;
+        // Convection-diffusion: diagonally dominant but not symmetric.
+        let mut a = tridiagonal(n, 3.).to_dense().to_vec();
+        for i in 1..n {
+            a[i * n + i - 1] = -1.5;
+        }
+        let a = dense(a, n);
+        let b = vec![1.; n];
+        let mut o = KrylovOptions::new(n);
+        o.tol = 1e-10;
+        o.restart = 5;
+        let res = rust_gmres(&a, None, &b, None, &o).unwrap();
+        assert_eq!(res.info, 0);
+        check(&a, &res.x, &b, 1e-9);
+        assert!(res.residuals.windows(2).all(|w| w[1] <= w[0] * (1. + 1e-8)));
+
+        let jacobi = diagonal(vec![1. / 3.; n]);
+        let res = rust_gmres(&a, Some(&jacobi), &b, None, &o).unwrap();
+        assert_eq!(res.info, 0);
+        check(&a, &res.x, &b, 1e-9);
+
+        // Solving the transposed system through a transposed view.
+        let res = rust_bicgstab(&a.t(), None, &b, None, &o).unwrap();
+        assert_eq!(res.info, 0);
+        check(&a.t(), &res.x, &b, 1e-9);
+
+        let res = rust_bicgstab(&a, None, &b, None, &o).unwrap();
+        assert_eq!(res.info, 0);
+        check(&a, &res.x, &b, 1e-9);
+        let x0 = Some(res.x.clone());
+        o.tol = 1e-6;
+        let res = rust_bicgstab(&a, Some(&jacobi), &b, x0, &o).unwrap();
+        assert_eq!(res.residuals.len(), 1);
+
+        // b is outside the range of a singular A.
+        let a = dense(vec![1., 0., 0., 0.], 2);
+        let res = rust_gmres(&a, None, &[0., 1.], None, &o).unwrap();
+        assert_eq!(res.info, -1);
+        assert!(res.x.iter().all(|x| x.is_finite()));
+        assert_eq!(res.residuals, vec![1.]);
+
+        // r_hat . A r_hat = 0 on the first step of bicgstab.
+        let a = dense(vec![0., 1., 1., 0.], 2);
+        let res = rust_bicgstab(&a, None, &[1., 0.], None, &o).unwrap();
+        assert_eq!(res.info, -1);
+        assert!(res.x.iter().all(|x| x.is_finite()));
+        assert_eq!(res.residuals, vec![1.]);
+    }
+
+    #[test]
+    fn minres_test() {
+        use super::{rust_minres, KrylovOptions};
+        let n = 30;
+        // Symmetric but indefinite, which CG cannot handle.
+        let a = tridiagonal(n, 0.5).to_dense();
+        let b = (0..n).map(|i| 1. + i as f64).collect::<Vec<_>>();
+        let mut o = KrylovOptions::new(n);
+        o.tol = 1e-10;
+        let res = rust_minres(&a, None, &b, None, &o).unwrap();
+        assert_eq!(res.info, 0);
+        check(&a, &res.x, &b, 1e-8);
+
+        let scale = diagonal(vec![2.; n]);
+        let res = rust_minres(&a, Some(&scale), &b, None, &o).unwrap();
+        check(&a, &res.x, &b, 1e-8);
+        let negative = diagonal(vec![-1.; n]);
+        assert!(rust_minres(&a, Some(&negative), &b, None, &o).is_err());
+    }
+}
@@ -1,34 +1,200 @@
+// pyo3 0.16's `#[pymethods]` expands to impls nested inside a static
+#![allow(non_local_definitions)]
+
 use pyo3::prelude::*;
 
+mod array;
+mod creation_ops;
 mod cwslice;
+mod elementwise_ops;
+mod expr_eval;
+mod index_ops;
+mod krylov_ops;
 mod linear_eqn_ops;
+mod linear_operator;
+mod matrix_market;
 mod matrix_mult;
 mod matrix_ops;
 mod my_util;
+mod npy_io;
+mod out_of_core_ops;
+mod precond_ops;
+mod reduction_ops;
+mod scan_ops;
+mod shape_ops;
+mod sort_ops;
+mod sparse_ops;
+mod tensor_ops;
+mod text_io;
 mod vector_ops;
 
+use array::*;
+use creation_ops::*;
+use elementwise_ops::*;
+use expr_eval::*;
+use index_ops::*;
+use krylov_ops::*;
 use linear_eqn_ops::*;
+use linear_operator::*;
+use matrix_market::*;
 use matrix_mult::*;
 use matrix_ops::*;
+use npy_io::*;
+use out_of_core_ops::*;
+use precond_ops::*;
+use reduction_ops::*;
+use scan_ops::*;
+use shape_ops::*;
+use sort_ops::*;
+use sparse_ops::*;
+use tensor_ops::*;
+use text_io::*;
 use vector_ops::*;
 
 /// A Python module implemented in Rust.
 #[pymodule]
 fn numpar(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Array>()?;
+    m.add_function(wrap_pyfunction!(ascontiguousarray, m)?)?;
+
+    m.add_function(wrap_pyfunction!(load, m)?)?;
+    m.add_function(wrap_pyfunction!(memmap, m)?)?;
+    m.add_function(wrap_pyfunction!(save, m)?)?;
+    m.add_function(wrap_pyfunction!(savez, m)?)?;
+    m.add_function(wrap_pyfunction!(loadtxt, m)?)?;
+    m.add_function(wrap_pyfunction!(genfromtxt, m)?)?;
+    m.add_function(wrap_pyfunction!(savetxt, m)?)?;
+    m.add_function(wrap_pyfunction!(fromfile, m)?)?;
+    m.add_function(wrap_pyfunction!(mmread, m)?)?;
+    m.add_function(wrap_pyfunction!(mmwrite, m)?)?;
+    m.add_function(wrap_pyfunction!(mminfo, m)?)?;
+
+    m.add_function(wrap_pyfunction!(zeros, m)?)?;
+    m.add_function(wrap_pyfunction!(ones, m)?)?;
+    m.add_function(wrap_pyfunction!(full, m)?)?;
+    m.add_function(wrap_pyfunction!(eye, m)?)?;
+    m.add_function(wrap_pyfunction!(identity, m)?)?;
+    m.add_function(wrap_pyfunction!(arange, m)?)?;
+    m.add_function(wrap_pyfunction!(linspace, m)?)?;
+    m.add_function(wrap_pyfunction!(logspace, m)?)?;
+    m.add_function(wrap_pyfunction!(meshgrid, m)?)?;
+
+    m.add_function(wrap_pyfunction!(reshape, m)?)?;
+    m.add_function(wrap_pyfunction!(ravel, m)?)?;
+    m.add_function(wrap_pyfunction!(concatenate, m)?)?;
+    m.add_function(wrap_pyfunction!(stack, m)?)?;
+    m.add_function(wrap_pyfunction!(hstack, m)?)?;
+    m.add_function(wrap_pyfunction!(vstack, m)?)?;
+    m.add_function(wrap_pyfunction!(split, m)?)?;
+    m.add_function(wrap_pyfunction!(tile, m)?)?;
+    m.add_function(wrap_pyfunction!(repeat, m)?)?;
+    m.add_function(wrap_pyfunction!(pad, m)?)?;
+
+    m.add_function(wrap_pyfunction!(take, m)?)?;
+    m.add_function(wrap_pyfunction!(put, m)?)?;
+    m.add("where", wrap_pyfunction!(where_, m)?)?;
+    m.add_function(wrap_pyfunction!(nonzero, m)?)?;
+
+    m.add_function(wrap_pyfunction!(sort, m)?)?;
+    m.add_function(wrap_pyfunction!(argsort, m)?)?;
+    m.add_function(wrap_pyfunction!(partition, m)?)?;
+    m.add_function(wrap_pyfunction!(argpartition, m)?)?;
+    m.add_function(wrap_pyfunction!(topk, m)?)?;
+    m.add_function(wrap_pyfunction!(searchsorted, m)?)?;
+    m.add_function(wrap_pyfunction!(unique, m)?)?;
+
+    m.add_function(wrap_pyfunction!(add, m)?)?;
+    m.add_function(wrap_pyfunction!(subtract, m)?)?;
+    m.add_function(wrap_pyfunction!(multiply, m)?)?;
+    m.add_function(wrap_pyfunction!(divide, m)?)?;
+    m.add_function(wrap_pyfunction!(power, m)?)?;
+    m.add_function(wrap_pyfunction!(maximum, m)?)?;
+    m.add_function(wrap_pyfunction!(minimum, m)?)?;
+
+    m.add_function(wrap_pyfunction!(exp, m)?)?;
+    m.add_function(wrap_pyfunction!(log, m)?)?;
+    m.add_function(wrap_pyfunction!(log1p, m)?)?;
+    m.add_function(wrap_pyfunction!(expm1, m)?)?;
+    m.add_function(wrap_pyfunction!(sin, m)?)?;
+    m.add_function(wrap_pyfunction!(cos, m)?)?;
+    m.add_function(wrap_pyfunction!(tan, m)?)?;
+    m.add_function(wrap_pyfunction!(tanh, m)?)?;
+    m.add_function(wrap_pyfunction!(sqrt, m)?)?;
+    m.add_function(wrap_pyfunction!(abs, m)?)?;
+    m.add_function(wrap_pyfunction!(sign, m)?)?;
+    m.add_function(wrap_pyfunction!(floor, m)?)?;
+    m.add_function(wrap_pyfunction!(ceil, m)?)?;
+    m.add_function(wrap_pyfunction!(clip, m)?)?;
+
+    m.add_function(wrap_pyfunction!(evaluate, m)?)?;
+
+    m.add_function(wrap_pyfunction!(sum, m)?)?;
+    m.add_function(wrap_pyfunction!(prod, m)?)?;
+    m.add_function(wrap_pyfunction!(mean, m)?)?;
+    m.add_function(wrap_pyfunction!(var, m)?)?;
+    m.add_function(wrap_pyfunction!(std, m)?)?;
+    m.add_function(wrap_pyfunction!(min, m)?)?;
+    m.add_function(wrap_pyfunction!(max, m)?)?;
+    m.add_function(wrap_pyfunction!(argmin, m)?)?;
+    m.add_function(wrap_pyfunction!(argmax, m)?)?;
+
+    m.add_function(wrap_pyfunction!(cumsum, m)?)?;
+    m.add_function(wrap_pyfunction!(cumprod, m)?)?;
+    m.add_function(wrap_pyfunction!(cummax, m)?)?;
+    m.add_function(wrap_pyfunction!(cummin, m)?)?;
+    m.add_function(wrap_pyfunction!(diff, m)?)?;
+
     m.add_function(wrap_pyfunction!(dot, m)?)?;
     m.add_function(wrap_pyfunction!(norm, m)?)?;
     m.add_function(wrap_pyfunction!(outer, m)?)?;
+    m.add_function(wrap_pyfunction!(cross, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize, m)?)?;
+    m.add_function(wrap_pyfunction!(angle_between, m)?)?;
+    m.add_function(wrap_pyfunction!(project, m)?)?;
 
     m.add_function(wrap_pyfunction!(trace, m)?)?;
     m.add_function(wrap_pyfunction!(transpose, m)?)?;
+    m.add_function(wrap_pyfunction!(transpose_inplace, m)?)?;
+    m.add_function(wrap_pyfunction!(swapaxes, m)?)?;
+    m.add_function(wrap_pyfunction!(moveaxis, m)?)?;
     m.add_function(wrap_pyfunction!(det, m)?)?;
     m.add_function(wrap_pyfunction!(inv, m)?)?;
 
     // m.add_function(wrap_pyfunction!(matmul1, m)?)?;
     m.add_function(wrap_pyfunction!(matmul, m)?)?;
+    m.add_function(wrap_pyfunction!(matmul_blocked, m)?)?;
+    m.add_function(wrap_pyfunction!(multi_dot, m)?)?;
+    m.add_function(wrap_pyfunction!(tensordot, m)?)?;
+    m.add_function(wrap_pyfunction!(einsum, m)?)?;
+    m.add_function(wrap_pyfunction!(einsum_path, m)?)?;
+    m.add_function(wrap_pyfunction!(kron, m)?)?;
+    m.add_function(wrap_pyfunction!(hadamard, m)?)?;
+    m.add_function(wrap_pyfunction!(khatri_rao, m)?)?;
+    m.add_function(wrap_pyfunction!(kron_matvec, m)?)?;
     m.add_function(wrap_pyfunction!(matrix_power, m)?)?;
 
+    m.add_class::<SparseMatrix>()?;
+    m.add_function(wrap_pyfunction!(csr_matrix, m)?)?;
+    m.add_function(wrap_pyfunction!(csc_matrix, m)?)?;
+    m.add_function(wrap_pyfunction!(coo_matrix, m)?)?;
+    m.add_function(wrap_pyfunction!(issparse, m)?)?;
+    m.add_function(wrap_pyfunction!(spmv, m)?)?;
+    m.add_function(wrap_pyfunction!(spgemm, m)?)?;
+
     m.add_function(wrap_pyfunction!(solve, m)?)?;
+    m.add_function(wrap_pyfunction!(solve_blocked, m)?)?;
+    m.add_class::<PyLinearOperator>()?;
+    m.add_function(wrap_pyfunction!(cg, m)?)?;
+    m.add_function(wrap_pyfunction!(gmres, m)?)?;
+    m.add_function(wrap_pyfunction!(bicgstab, m)?)?;
+    m.add_function(wrap_pyfunction!(minres, m)?)?;
+    m.add_class::<Preconditioner>()?;
+    m.add_function(wrap_pyfunction!(jacobi, m)?)?;
+    m.add_function(wrap_pyfunction!(block_jacobi, m)?)?;
+    m.add_function(wrap_pyfunction!(ssor, m)?)?;
+    m.add_function(wrap_pyfunction!(ilu0, m)?)?;
+    m.add_function(wrap_pyfunction!(ilut, m)?)?;
+    m.add_function(wrap_pyfunction!(ic0, m)?)?;
     m.add_function(wrap_pyfunction!(matrix_rank, m)?)?;
 
     Ok(())
@@ -69,7 +69,7 @@ pub fn bwd_subs(a: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
 pub fn solve(a: &PyList, b: &PyList) -> PyResult<Vec<f64>> {
     match (a.extract::<Vec<Vec<f64>>>(), b.extract::<Vec<f64>>()) {
         (Ok(a_mat), Ok(b_vec)) => {
-            if is_square_matrix(&a_mat) {
+            if is_square_matrix(&a_mat) && a_mat.len() == b_vec.len() {
                 Ok(rust_solve(&a_mat, &b_vec))
             } else {
                 Err(PyTypeError::new_err("Malformed parameter"))
@@ -79,8 +79,9 @@ pub fn solve(a: &PyList, b: &PyList) -> PyResult<Vec<f64>> {
     }
 }
 
-pub fn rust_solve(a: &Vec<Vec<f64>>, b: &Vec<f64>) -> Vec<f64> {
-    let augmented = augment(a, &b.par_iter().map(|&e| vec![e]).collect());
+pub fn rust_solve(a: &[Vec<f64>], b: &Vec<f64>) -> Vec<f64> {
+    let column = b.par_iter().map(|&e| vec![e]).collect::<Vec<_>>();
+    let augmented = augment(a, &column);
     let fwd = fwd_elim(&augmented);
     let solved = bwd_subs(&fwd);
     let simple = simplify_soln(&solved);
@@ -0,0 +1,368 @@
+use crate::array::{to_array, Array};
+use crate::precond_ops::{Precond, Preconditioner};
+use crate::sparse_ops::{as_sparse, Csr, Semiring, Sparse};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::slice::ParallelSlice;
+
+/// A matrix known only through its products with vectors. Callers pass
+/// vectors of the right length: `shape().1` for `matvec` and `shape().0`
+/// for `rmatvec`.
+pub trait LinearOperator {
+    fn shape(&self) -> (usize, usize);
+    /// `A x`
+    fn matvec(&self, x: &[f64]) -> Result<Vec<f64>, String>;
+    /// `A^T x`
+    fn rmatvec(&self, x: &[f64]) -> Result<Vec<f64>, String>;
+}
+
+/// Row-major `A x` with `cols` columns, one row per task.
+pub fn dense_matvec(a: &[f64], cols: usize, x: &[f64]) -> Vec<f64> {
+    if cols == 0 {
+        return vec![0.; a.len()];
+    }
+    a.par_chunks(cols)
+        .map(|row| row.iter().zip(x).map(|(a, x)| a * x).sum())
+        .collect()
+}
+
+/// Row-major `A^T x` with `cols` columns, summing scaled rows per task.
+pub fn dense_rmatvec(a: &[f64], cols: usize, x: &[f64]) -> Vec<f64> {
+    if cols == 0 {
+        return vec![];
+    }
+    a.par_chunks(cols)
+        .zip(x)
+        .fold(
+            || vec![0.; cols],
+            |mut y, (row, &xi)| {
+                y.iter_mut().zip(row).for_each(|(y, a)| *y += a * xi);
+                y
+            },
+        )
+        .reduce(
+            || vec![0.; cols],
+            |mut y, z| {
+                y.iter_mut().zip(z).for_each(|(y, z)| *y += z);
+                y
+            },
+        )
+}
+
+/// Dense 2-D arrays; transposed views of contiguous matrices are read in
+/// place.
+impl LinearOperator for Array {
+    fn shape(&self) -> (usize, usize) {
+        (self.shape()[0], self.shape()[1])
+    }
+
+    fn matvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        let (m, n) = LinearOperator::shape(self);
+        Ok(match (self.as_slice(), self.as_transposed_slice()) {
+            (Some(a), _) => dense_matvec(a, n, x),
+            (_, Some(t)) => dense_rmatvec(t, m, x),
+            _ => dense_matvec(self.to_contiguous().as_slice().unwrap(), n, x),
+        })
+    }
+
+    fn rmatvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        let (m, n) = LinearOperator::shape(self);
+        Ok(match (self.as_slice(), self.as_transposed_slice()) {
+            (Some(a), _) => dense_rmatvec(a, n, x),
+            (_, Some(t)) => dense_matvec(t, m, x),
+            _ => dense_rmatvec(self.to_contiguous().as_slice().unwrap(), n, x),
+        })
+    }
+}
+
+impl LinearOperator for Csr {
+    fn shape(&self) -> (usize, usize) {
+        self.shape
+    }
+
+    fn matvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        Ok(Csr::matvec(self, Semiring::PLUS_TIMES, x))
+    }
+
+    fn rmatvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        Ok(Csr::rmatvec(self, Semiring::PLUS_TIMES, x))
+    }
+}
+
+impl LinearOperator for Sparse {
+    fn shape(&self) -> (usize, usize) {
+        Sparse::shape(self)
+    }
+
+    fn matvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        Sparse::matvec(self, Semiring::PLUS_TIMES, x)
+    }
+
+    fn rmatvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        Sparse::rmatvec(self, Semiring::PLUS_TIMES, x)
+    }
+}
+
+/// `M ≈ A^-1`; the transpose is available for the symmetric kinds and for
+/// block-Jacobi.
+impl LinearOperator for Preconditioner {
+    fn shape(&self) -> (usize, usize) {
+        (self.n, self.n)
+    }
+
+    fn matvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        Ok(self.inner.apply(x))
+    }
+
+    fn rmatvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        match &self.inner {
+            Precond::Jacobi(_) | Precond::Ic { .. } => Ok(self.inner.apply(x)),
+            Precond::BlockJacobi { size, inverses } => Ok(x
+                .chunks(*size)
+                .zip(inverses)
+                .flat_map(|(x, inv)| dense_rmatvec(inv, x.len(), x))
+                .collect()),
+            _ => Err(format!(
+                "rmatvec is not available for {} preconditioners",
+                self.inner.kind()
+            )),
+        }
+    }
+}
+
+/// `numpar.LinearOperator(shape, matvec, rmatvec=None)`: a matrix given by
+/// Python callables taking and returning vectors.
+#[pyclass(name = "LinearOperator")]
+pub struct PyLinearOperator {
+    shape: (usize, usize),
+    matvec: PyObject,
+    rmatvec: Option<PyObject>,
+}
+
+/// Calls `f(x)` and checks that it returns `len` values.
+fn call_python(f: &PyObject, x: &[f64], len: usize) -> Result<Vec<f64>, String> {
+    let y = Python::with_gil(|py| {
+        let arg = Array::from_vec(x.to_vec(), vec![x.len()]);
+        let y = f.call1(py, (arg,))?;
+        to_array(y.as_ref(py)).map(|y| y.to_vec())
+    })
+    .map_err(|e| e.to_string())?;
+    if y.len() != len {
+        return Err(format!(
+            "operator returned {} values, expected {}",
+            y.len(),
+            len
+        ));
+    }
+    Ok(y)
+}
+
+impl LinearOperator for PyLinearOperator {
+    fn shape(&self) -> (usize, usize) {
+        self.shape
+    }
+
+    fn matvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        call_python(&self.matvec, x, self.shape.0)
+    }
+
+    fn rmatvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        match &self.rmatvec {
+            Some(f) => call_python(f, x, self.shape.1),
+            None => Err("this LinearOperator has no rmatvec".to_string()),
+        }
+    }
+}
+
+/// Checks the length of `x` for `A x` (or `A^T x` if `transposed`).
+fn checked_product(a: &dyn LinearOperator, x: &PyAny, transposed: bool) -> PyResult<Array> {
+    let x = to_array(x)?.to_vec();
+    let (m, n) = a.shape();
+    let (len, out) = if transposed { (m, n) } else { (n, m) };
+    if x.len() != len {
+        return Err(PyTypeError::new_err(format!(
+            "dimension mismatch: operator of shape ({}, {}) and vector of length {}",
+            m,
+            n,
+            x.len()
+        )));
+    }
+    let y = match transposed {
+        true => a.rmatvec(&x),
+        false => a.matvec(&x),
+    };
+    Ok(Array::from_vec(y.map_err(PyTypeError::new_err)?, vec![out]))
+}
+
+#[pymethods]
+impl PyLinearOperator {
+    #[new]
+    #[args(rmatvec = "None")]
+    fn new(shape: (usize, usize), matvec: PyObject, rmatvec: Option<PyObject>) -> Self {
+        PyLinearOperator {
+            shape,
+            matvec,
+            rmatvec,
+        }
+    }
+
+    #[getter(shape)]
+    fn py_shape(&self, py: Python) -> PyObject {
+        PyTuple::new(py, [self.shape.0, self.shape.1]).into_py(py)
+    }
+
+    #[pyo3(name = "matvec")]
+    fn py_matvec(&self, x: &PyAny) -> PyResult<Array> {
+        checked_product(self, x, false)
+    }
+
+    #[pyo3(name = "rmatvec")]
+    fn py_rmatvec(&self, x: &PyAny) -> PyResult<Array> {
+        checked_product(self, x, true)
+    }
+
+    fn __matmul__(&self, x: &PyAny) -> PyResult<Array> {
+        checked_product(self, x, false)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<{}x{} LinearOperator>", self.shape.0, self.shape.1)
+    }
+}
+
+/// A Python argument usable as a linear operator: a dense matrix, a sparse
+/// matrix, a preconditioner, a `LinearOperator`, or any other object with
+/// `shape` and `matvec` (and optionally `rmatvec`), such as scipy's. Sparse
+/// matrices and strided dense views are converted once, not on every product.
+pub enum PyOperator<'py> {
+    Dense(Array),
+    Sparse(Csr),
+    Precond(PyRef<'py, Preconditioner>),
+    Callback(PyRef<'py, PyLinearOperator>),
+    Duck(&'py PyAny, (usize, usize)),
+}
+
+impl<'py> PyOperator<'py> {
+    pub fn extract(obj: &'py PyAny) -> PyResult<PyOperator<'py>> {
+        if let Some(s) = as_sparse(obj) {
+            return Ok(PyOperator::Sparse(s.inner.csr().into_owned()));
+        }
+        if let Ok(m) = obj.extract::<PyRef<Preconditioner>>() {
+            return Ok(PyOperator::Precond(m));
+        }
+        if let Ok(op) = obj.extract::<PyRef<PyLinearOperator>>() {
+            return Ok(PyOperator::Callback(op));
+        }
+        if obj.hasattr("matvec")? {
+            let shape = obj.getattr("shape")?.extract::<(usize, usize)>()?;
+            return Ok(PyOperator::Duck(obj, shape));
+        }
+        let a = to_array(obj)?;
+        if a.ndim() != 2 {
+            return Err(PyTypeError::new_err("Operator must be a 2-D array"));
+        }
+        // Other strided views would be copied on every product.
+        if a.as_slice().is_none() && a.as_transposed_slice().is_none() {
+            return Ok(PyOperator::Dense(a.to_contiguous()));
+        }
+        Ok(PyOperator::Dense(a))
+    }
+
+    fn as_dyn(&self) -> Option<&dyn LinearOperator> {
+        match self {
+            PyOperator::Dense(a) => Some(a),
+            PyOperator::Sparse(s) => Some(s),
+            PyOperator::Precond(m) => Some(&**m),
+            PyOperator::Callback(op) => Some(&**op),
+            PyOperator::Duck(..) => None,
+        }
+    }
+
+    fn call_duck(obj: &PyAny, method: &str, x: &[f64], len: usize) -> Result<Vec<f64>, String> {
+        let f = obj.getattr(method).map_err(|e| e.to_string())?;
+        call_python(&f.into(), x, len)
+    }
+}
+
+impl<'py> LinearOperator for PyOperator<'py> {
+    fn shape(&self) -> (usize, usize) {
+        match (self, self.as_dyn()) {
+            (PyOperator::Duck(_, shape), _) => *shape,
+            (_, Some(op)) => op.shape(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn matvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        match (self, self.as_dyn()) {
+            (PyOperator::Duck(obj, shape), _) => PyOperator::call_duck(obj, "matvec", x, shape.0),
+            (_, Some(op)) => op.matvec(x),
+            _ => unreachable!(),
+        }
+    }
+
+    fn rmatvec(&self, x: &[f64]) -> Result<Vec<f64>, String> {
+        match (self, self.as_dyn()) {
+            (PyOperator::Duck(obj, shape), _) => PyOperator::call_duck(obj, "rmatvec", x, shape.1),
+            (_, Some(op)) => op.rmatvec(x),
+            _ => unreachable!(),
+        }
+    }
+}
+
+mod test {
+    #[test]
+    fn dense_and_sparse_test() {
+        use super::LinearOperator;
+        use crate::array::Array;
+        use crate::sparse_ops::{Csc, Csr, Sparse};
+        let a = Array::from_vec(vec![1., 0., 2., 0., -1., 3.], vec![2, 3]);
+        let s = Csr::from_dense(&a).unwrap();
+        let ops: Vec<Box<dyn LinearOperator>> = vec![
+            Box::new(a.clone()),
+            Box::new(a.t().t()),
+            Box::new(s.clone()),
+            Box::new(Sparse::Csc(Csc::from_csr(&s))),
+        ];
+        for op in &ops {
+            assert_eq!(op.shape(), (2, 3));
+            assert_eq!(op.matvec(&[1., 1., 1.]).unwrap(), vec![3., 2.]);
+            assert_eq!(op.rmatvec(&[1., 2.]).unwrap(), vec![1., -2., 8.]);
+        }
+        // A transposed view is read in place.
+        let t = a.t();
+        assert!(t.as_slice().is_none());
+        assert_eq!(LinearOperator::shape(&t), (3, 2));
+        assert_eq!(t.matvec(&[1., 2.]).unwrap(), vec![1., -2., 8.]);
+        assert_eq!(t.rmatvec(&[1., 1., 1.]).unwrap(), vec![3., 2.]);
+    }
+
+    #[test]
+    fn preconditioner_operator_test() {
+        use super::LinearOperator;
+        use crate::precond_ops::{rust_block_jacobi, rust_ilu0, Preconditioner};
+        use crate::sparse_ops::Csr;
+        let a = Csr::new(
+            (3, 3),
+            vec![0, 2, 4, 5],
+            vec![0, 1, 0, 1, 2],
+            vec![2., 1., 0., 4., 5.],
+        )
+        .unwrap();
+        let m = Preconditioner {
+            inner: rust_block_jacobi(&a, 2).unwrap(),
+            n: 3,
+        };
+        // The first block is [[2, 1], [0, 4]], so the transpose differs.
+        assert_eq!(m.matvec(&[1., 4., 5.]).unwrap(), vec![0., 1., 1.]);
+        assert_eq!(m.rmatvec(&[2., 1., 5.]).unwrap(), vec![1., 0., 1.]);
+        let ilu = Preconditioner {
+            inner: rust_ilu0(&a).unwrap(),
+            n: 3,
+        };
+        assert_eq!(ilu.shape(), (3, 3));
+        assert!(ilu.rmatvec(&[1., 1., 1.]).is_err());
+    }
+}
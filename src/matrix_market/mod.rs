@@ -0,0 +1,630 @@
+use crate::array::{to_array, Array};
+use crate::elementwise_ops::BinOp;
+use crate::npy_io::{extract_path, with_suffix};
+use crate::sparse_ops::{as_sparse, Coo, Csr, Sparse, SparseMatrix};
+use crate::text_io::{format_value, parse_format, parse_table, Piece, TextOptions};
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::slice::ParallelSlice;
+
+const BANNER: &str = "%%MatrixMarket";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MmFormat {
+    Coordinate,
+    Array,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MmField {
+    Real,
+    Integer,
+    Pattern,
+}
+
+/// Which part of the matrix is stored: symmetric and hermitian (the same
+/// for real data) files hold the lower triangle, skew-symmetric ones the
+/// strictly lower triangle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MmSymmetry {
+    General,
+    Symmetric,
+    SkewSymmetric,
+    Hermitian,
+}
+
+impl MmFormat {
+    fn name(self) -> &'static str {
+        match self {
+            MmFormat::Coordinate => "coordinate",
+            MmFormat::Array => "array",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "coordinate" => Ok(MmFormat::Coordinate),
+            "array" => Ok(MmFormat::Array),
+            _ => Err(format!("unknown Matrix Market format '{}'", s)),
+        }
+    }
+}
+
+impl MmField {
+    fn name(self) -> &'static str {
+        match self {
+            MmField::Real => "real",
+            MmField::Integer => "integer",
+            MmField::Pattern => "pattern",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "real" | "double" => Ok(MmField::Real),
+            "integer" => Ok(MmField::Integer),
+            "pattern" => Ok(MmField::Pattern),
+            f => Err(format!("unsupported Matrix Market field '{}'", f)),
+        }
+    }
+}
+
+impl MmSymmetry {
+    fn name(self) -> &'static str {
+        match self {
+            MmSymmetry::General => "general",
+            MmSymmetry::Symmetric => "symmetric",
+            MmSymmetry::SkewSymmetric => "skew-symmetric",
+            MmSymmetry::Hermitian => "hermitian",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "general" => Ok(MmSymmetry::General),
+            "symmetric" => Ok(MmSymmetry::Symmetric),
+            "skew-symmetric" => Ok(MmSymmetry::SkewSymmetric),
+            "hermitian" => Ok(MmSymmetry::Hermitian),
+            _ => Err(format!("unknown Matrix Market symmetry '{}'", s)),
+        }
+    }
+
+    /// Sign of the mirrored entry, if the upper triangle is implied.
+    fn mirror(self) -> Option<f64> {
+        match self {
+            MmSymmetry::General => None,
+            MmSymmetry::Symmetric | MmSymmetry::Hermitian => Some(1.),
+            MmSymmetry::SkewSymmetric => Some(-1.),
+        }
+    }
+}
+
+/// The banner and size line of a Matrix Market file; the data starts after
+/// `header_lines` lines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MmHeader {
+    pub format: MmFormat,
+    pub field: MmField,
+    pub symmetry: MmSymmetry,
+    pub rows: usize,
+    pub cols: usize,
+    pub entries: usize,
+    pub header_lines: usize,
+}
+
+pub fn parse_mm_header(text: &[u8]) -> Result<MmHeader, String> {
+    let mut lines = text.split(|&b| b == b'\n').map(String::from_utf8_lossy);
+    let banner = lines.next().unwrap_or_default();
+    let words = banner.split_whitespace().collect::<Vec<_>>();
+    if words.len() != 5 || words[0] != BANNER || !words[1].eq_ignore_ascii_case("matrix") {
+        return Err("not a Matrix Market matrix file".to_string());
+    }
+    if words[3].eq_ignore_ascii_case("complex") {
+        return Err("complex Matrix Market files are not supported".to_string());
+    }
+    let (format, field, symmetry) = (
+        MmFormat::parse(words[2])?,
+        MmField::parse(words[3])?,
+        MmSymmetry::parse(words[4])?,
+    );
+    if format == MmFormat::Array && field == MmField::Pattern {
+        return Err("pattern is only valid in coordinate format".to_string());
+    }
+    for (k, line) in lines.enumerate() {
+        let header_lines = k + 2;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('%') {
+            continue;
+        }
+        let size = line
+            .split_whitespace()
+            .map(|w| w.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid size line '{}'", line))?;
+        let (rows, cols, entries) = match (format, size.as_slice()) {
+            (MmFormat::Coordinate, &[m, n, nnz]) => (m, n, nnz),
+            (MmFormat::Array, &[m, n]) => (m, n, m * n),
+            _ => return Err(format!("invalid size line '{}'", line)),
+        };
+        if symmetry != MmSymmetry::General && rows != cols {
+            return Err(format!("{} matrix must be square", symmetry.name()));
+        }
+        return Ok(MmHeader {
+            format,
+            field,
+            symmetry,
+            rows,
+            cols,
+            entries,
+            header_lines,
+        });
+    }
+    Err("Matrix Market file has no size line".to_string())
+}
+
+/// Contents of a Matrix Market file. Coordinate entries are 0-based, with
+/// the implied triangle added and duplicates kept.
+pub enum MmData {
+    Dense(Array),
+    Sparse(Coo),
+}
+
+fn read_coordinate(h: &MmHeader, values: &[f64], width: usize) -> Result<Coo, String> {
+    let entries = values
+        .par_chunks(width.max(1))
+        .map(|e| {
+            let (i, j) = (e[0], e[1]);
+            if i.fract() != 0. || j.fract() != 0. || i < 1. || j < 1. {
+                return Err(format!("invalid entry index ({}, {})", i, j));
+            }
+            let (i, j) = (i as usize - 1, j as usize - 1);
+            if i >= h.rows || j >= h.cols {
+                return Err(format!(
+                    "entry ({}, {}) is outside a {}x{} matrix",
+                    i + 1,
+                    j + 1,
+                    h.rows,
+                    h.cols
+                ));
+            }
+            let v = if h.field == MmField::Pattern {
+                1.
+            } else {
+                e[2]
+            };
+            Ok((i, j, v))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let mirrored = match h.symmetry.mirror() {
+        Some(sign) => entries
+            .iter()
+            .filter(|e| e.0 != e.1)
+            .map(|&(i, j, v)| (j, i, sign * v))
+            .collect(),
+        None => vec![],
+    };
+    let all = entries.into_iter().chain(mirrored);
+    let mut t = Coo {
+        shape: (h.rows, h.cols),
+        rows: vec![],
+        cols: vec![],
+        data: vec![],
+    };
+    for (i, j, v) in all {
+        t.rows.push(i);
+        t.cols.push(j);
+        t.data.push(v);
+    }
+    Ok(t)
+}
+
+/// Positions, in column-major order, of the values an array file stores.
+fn stored_positions(h: &MmHeader) -> Vec<(usize, usize)> {
+    let skip = match h.symmetry {
+        MmSymmetry::General => None,
+        MmSymmetry::SkewSymmetric => Some(1),
+        _ => Some(0),
+    };
+    (0..h.cols)
+        .flat_map(|j| {
+            let first = skip.map_or(0, |s| j + s);
+            (first..h.rows).map(move |i| (i, j))
+        })
+        .collect()
+}
+
+fn read_array(h: &MmHeader, values: &[f64]) -> Result<Array, String> {
+    let positions = stored_positions(h);
+    if positions.len() != values.len() {
+        return Err(format!(
+            "expected {} values, found {}",
+            positions.len(),
+            values.len()
+        ));
+    }
+    let mut data = vec![0.; h.rows * h.cols];
+    for (&(i, j), &v) in positions.iter().zip(values) {
+        data[i * h.cols + j] = v;
+        if let (Some(sign), true) = (h.symmetry.mirror(), i != j) {
+            data[j * h.cols + i] = sign * v;
+        }
+    }
+    Ok(Array::from_vec(data, vec![h.rows, h.cols]))
+}
+
+/// Parses a Matrix Market file; the entries go through the parallel text
+/// parser.
+pub fn parse_mm(bytes: &[u8]) -> Result<(MmHeader, MmData), String> {
+    let h = parse_mm_header(bytes)?;
+    let o = TextOptions {
+        comments: vec!["%".to_string()],
+        skiprows: h.header_lines,
+        ..TextOptions::default()
+    };
+    let (values, lines, width) = parse_table(bytes, &o)?;
+    let data = match h.format {
+        MmFormat::Coordinate => {
+            let expected = if h.field == MmField::Pattern { 2 } else { 3 };
+            if lines != h.entries || (lines > 0 && width != expected) {
+                return Err(format!(
+                    "expected {} entries of {} values, found {} of {}",
+                    h.entries, expected, lines, width
+                ));
+            }
+            MmData::Sparse(read_coordinate(&h, &values, width)?)
+        }
+        MmFormat::Array => MmData::Dense(read_array(&h, &values)?),
+    };
+    Ok((h, data))
+}
+
+pub fn rust_mmread(path: &str) -> Result<(MmHeader, MmData), String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse_mm(&bytes)
+}
+
+/// The symmetry a square matrix actually has, `General` if none.
+pub fn detect_symmetry(a: &Array) -> MmSymmetry {
+    let n = a.shape()[0];
+    if a.shape()[1] != n {
+        return MmSymmetry::General;
+    }
+    let d = a.to_vec();
+    let holds = |f: fn(f64, f64) -> bool| {
+        (0..n)
+            .into_par_iter()
+            .all(|i| (0..=i).all(|j| f(d[i * n + j], d[j * n + i])))
+    };
+    if holds(|x, y| x == y) {
+        MmSymmetry::Symmetric
+    } else if holds(|x, y| x == -y) {
+        MmSymmetry::SkewSymmetric
+    } else {
+        MmSymmetry::General
+    }
+}
+
+/// A value as written to a Matrix Market file: integers plainly, reals with
+/// `precision` significant digits if given, else in their shortest exact
+/// form.
+fn format_entry(x: f64, field: MmField, precision: Option<&Piece>) -> String {
+    match (field, precision) {
+        (MmField::Integer, _) => format!("{}", x as i64),
+        (_, Some(Piece::Value(spec))) => format_value(spec, x),
+        _ if x.is_finite() && x.fract() == 0. && x.abs() < 1e16 => format!("{}", x),
+        _ if x != 0. && (x.abs() < 1e-4 || x.abs() >= 1e16) => format!("{:e}", x),
+        _ => format!("{}", x),
+    }
+}
+
+/// How `mmwrite` lays out a dense matrix.
+#[derive(Clone, Debug)]
+pub struct MmWriteOptions {
+    pub comment: String,
+    pub format: MmFormat,
+    pub field: MmField,
+    /// Detected from the data if `None`.
+    pub symmetry: Option<MmSymmetry>,
+    pub precision: Option<usize>,
+}
+
+/// Text of a dense matrix in Matrix Market form. Symmetric layouts store
+/// only the lower triangle, coordinate format only the nonzeros.
+pub fn format_mm(a: &Array, o: &MmWriteOptions) -> Result<String, String> {
+    let a = match a.ndim() {
+        1 => a.expand_dims(1),
+        2 => a.clone(),
+        _ => return Err("Matrix Market files hold 1-D or 2-D arrays".to_string()),
+    };
+    let (m, n) = (a.shape()[0], a.shape()[1]);
+    let symmetry = o.symmetry.unwrap_or_else(|| detect_symmetry(&a));
+    if symmetry != MmSymmetry::General && m != n {
+        return Err(format!("{} matrix must be square", symmetry.name()));
+    }
+    if o.format == MmFormat::Array && o.field == MmField::Pattern {
+        return Err("pattern is only valid in coordinate format".to_string());
+    }
+    let precision = precision_piece(o)?;
+    let precision = precision.as_ref().and_then(|p| p.first());
+    let h = MmHeader {
+        format: o.format,
+        field: o.field,
+        symmetry,
+        rows: m,
+        cols: n,
+        entries: 0,
+        header_lines: 0,
+    };
+    let data = a.to_vec();
+    let positions = match o.format {
+        MmFormat::Array => stored_positions(&h),
+        MmFormat::Coordinate => {
+            let mut p = stored_positions(&h);
+            p.retain(|&(i, j)| data[i * n + j] != 0.);
+            p.sort_unstable();
+            p
+        }
+    };
+    let lines = positions
+        .par_iter()
+        .map(|&(i, j)| {
+            let v = data[i * n + j];
+            match o.format {
+                MmFormat::Array => format_entry(v, o.field, precision) + "\n",
+                MmFormat::Coordinate => coordinate_line(i, j, v, o.field, precision),
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(mm_text(o, symmetry, (m, n), &lines))
+}
+
+fn precision_piece(o: &MmWriteOptions) -> Result<Option<Vec<Piece>>, String> {
+    o.precision
+        .map(|p| parse_format(&format!("%.{}g", p)))
+        .transpose()
+}
+
+fn coordinate_line(
+    i: usize,
+    j: usize,
+    v: f64,
+    field: MmField,
+    precision: Option<&Piece>,
+) -> String {
+    match field {
+        MmField::Pattern => format!("{} {}\n", i + 1, j + 1),
+        _ => format!(
+            "{} {} {}\n",
+            i + 1,
+            j + 1,
+            format_entry(v, field, precision)
+        ),
+    }
+}
+
+/// Banner, comments and size line followed by the formatted entries.
+fn mm_text(
+    o: &MmWriteOptions,
+    symmetry: MmSymmetry,
+    (m, n): (usize, usize),
+    lines: &[String],
+) -> String {
+    let mut text = format!(
+        "{} matrix {} {} {}\n",
+        BANNER,
+        o.format.name(),
+        o.field.name(),
+        symmetry.name()
+    );
+    for line in o.comment.lines() {
+        text.push_str(&format!("%{}\n", line));
+    }
+    match o.format {
+        MmFormat::Array => text.push_str(&format!("{} {}\n", m, n)),
+        MmFormat::Coordinate => text.push_str(&format!("{} {} {}\n", m, n, lines.len())),
+    }
+    lines.iter().for_each(|l| text.push_str(l));
+    text
+}
+
+/// Symmetry of a sparse matrix, found by comparing it to its transpose.
+pub fn detect_sparse_symmetry(a: &Csr) -> MmSymmetry {
+    if a.shape.0 != a.shape.1 {
+        return MmSymmetry::General;
+    }
+    let a = a.canonical();
+    let t = a.transpose();
+    if a == t {
+        MmSymmetry::Symmetric
+    } else if a.binary(BinOp::Add, &t).is_ok_and(|s| s.nnz() == 0) {
+        MmSymmetry::SkewSymmetric
+    } else {
+        MmSymmetry::General
+    }
+}
+
+/// Coordinate-format text of a sparse matrix, written straight from its
+/// stored entries (the lower triangle only for symmetric layouts).
+pub fn format_mm_sparse(a: &Csr, o: &MmWriteOptions) -> Result<String, String> {
+    let a = a.canonical();
+    let symmetry = o.symmetry.unwrap_or_else(|| detect_sparse_symmetry(&a));
+    if symmetry != MmSymmetry::General && a.shape.0 != a.shape.1 {
+        return Err(format!("{} matrix must be square", symmetry.name()));
+    }
+    let precision = precision_piece(o)?;
+    let precision = precision.as_ref().and_then(|p| p.first());
+    let keep = |i: usize, j: usize| match symmetry {
+        MmSymmetry::General => true,
+        MmSymmetry::SkewSymmetric => j < i,
+        _ => j <= i,
+    };
+    let lines = (0..a.shape.0)
+        .into_par_iter()
+        .flat_map_iter(|i| {
+            let (cols, vals) = a.row(i);
+            cols.iter()
+                .zip(vals)
+                .filter(move |e| keep(i, *e.0))
+                .map(move |(&j, &v)| coordinate_line(i, j, v, o.field, precision))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let o = MmWriteOptions {
+        format: MmFormat::Coordinate,
+        ..o.clone()
+    };
+    Ok(mm_text(&o, symmetry, a.shape, &lines))
+}
+
+/// Sparse data is written entry by entry in coordinate format and
+/// densified for array format.
+pub fn rust_mmwrite(path: &str, a: &MmData, o: &MmWriteOptions) -> Result<(), String> {
+    let text = match a {
+        MmData::Dense(a) => format_mm(a, o)?,
+        MmData::Sparse(c) if o.format == MmFormat::Coordinate => format_mm_sparse(&c.to_csr(), o)?,
+        MmData::Sparse(c) => format_mm(&c.to_csr().to_dense(), o)?,
+    };
+    std::fs::write(path, text).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// `scipy.io.mmread`: an `Array` for array files, a COO `SparseMatrix` for
+/// coordinate files.
+#[pyfunction]
+pub fn mmread(py: Python, source: &PyAny) -> PyResult<PyObject> {
+    match rust_mmread(&extract_path(source)?).map_err(PyIOError::new_err)? {
+        (_, MmData::Dense(a)) => Ok(a.into_py(py)),
+        (_, MmData::Sparse(c)) => Ok(SparseMatrix {
+            inner: Sparse::Coo(c),
+        }
+        .into_py(py)),
+    }
+}
+
+#[pyfunction(comment = "\"\"")]
+pub fn mmwrite(
+    target: &PyAny,
+    a: &PyAny,
+    comment: &str,
+    field: Option<&str>,
+    precision: Option<usize>,
+    symmetry: Option<&str>,
+    format: Option<&str>,
+) -> PyResult<()> {
+    let sparse = as_sparse(a);
+    let default_format = match sparse {
+        Some(_) => MmFormat::Coordinate,
+        None => MmFormat::Array,
+    };
+    let o = (|| {
+        Ok::<_, String>(MmWriteOptions {
+            comment: comment.to_string(),
+            format: format.map_or(Ok(default_format), MmFormat::parse)?,
+            field: field.map_or(Ok(MmField::Real), MmField::parse)?,
+            symmetry: symmetry.map(MmSymmetry::parse).transpose()?,
+            precision,
+        })
+    })()
+    .map_err(PyIOError::new_err)?;
+    let path = with_suffix(extract_path(target)?, ".mtx");
+    let data = match sparse {
+        Some(s) => MmData::Sparse(Coo::from_csr(&s.inner.csr())),
+        None => MmData::Dense(to_array(a)?),
+    };
+    rust_mmwrite(&path, &data, &o).map_err(PyIOError::new_err)
+}
+
+/// `(rows, cols, entries, format, field, symmetry)` from the header alone.
+#[pyfunction]
+pub fn mminfo(py: Python, source: &PyAny) -> PyResult<PyObject> {
+    use std::io::Read;
+    let path = extract_path(source)?;
+    let mut head = vec![];
+    std::fs::File::open(&path)
+        .and_then(|f| f.take(1 << 16).read_to_end(&mut head))
+        .map_err(|e| PyIOError::new_err(format!("{}: {}", path, e)))?;
+    let h = parse_mm_header(&head).map_err(PyIOError::new_err)?;
+    Ok(PyTuple::new(
+        py,
+        [
+            h.rows.into_py(py),
+            h.cols.into_py(py),
+            h.entries.into_py(py),
+            h.format.name().into_py(py),
+            h.field.name().into_py(py),
+            h.symmetry.name().into_py(py),
+        ],
+    )
+    .into_py(py))
+}
+
+mod test {
+    #[test]
+    fn mm_coordinate_test() {
+        use super::{MmData, MmSymmetry};
+        let text = b"%%MatrixMarket matrix coordinate real symmetric\n% a comment\n3 3 4\n1 1 2.0\n2 1 -1\n3 2 -1\n3 3 2e0\n";
+        let (h, data) = super::parse_mm(text).unwrap();
+        assert_eq!(h.symmetry, MmSymmetry::Symmetric);
+        let t = match data {
+            MmData::Sparse(t) => t,
+            _ => unreachable!(),
+        };
+        assert_eq!(t.data.len(), 6);
+        assert_eq!(
+            t.to_csr().to_dense().to_vec(),
+            vec![2., -1., 0., -1., 0., -1., 0., -1., 2.]
+        );
+
+        let skew = b"%%MatrixMarket matrix coordinate pattern skew-symmetric\n2 2 1\n2 1\n";
+        let (_, data) = super::parse_mm(skew).unwrap();
+        match data {
+            MmData::Sparse(t) => assert_eq!(t.to_csr().to_dense().to_vec(), vec![0., -1., 1., 0.]),
+            _ => unreachable!(),
+        }
+        let bad = b"%%MatrixMarket matrix coordinate real general\n2 2 1\n3 1 1.0\n";
+        assert!(super::parse_mm(bad).is_err());
+        let short = b"%%MatrixMarket matrix coordinate real general\n2 2 2\n1 1 1.0\n";
+        assert!(super::parse_mm(short).is_err());
+    }
+
+    #[test]
+    fn mm_array_roundtrip_test() {
+        use super::{MmData, MmField, MmFormat, MmSymmetry, MmWriteOptions};
+        use crate::array::Array;
+        let a = Array::from_vec(vec![1., 0.5, 0.5, 1e-300], vec![2, 2]);
+        let o = MmWriteOptions {
+            comment: "two\nlines".to_string(),
+            format: MmFormat::Array,
+            field: MmField::Real,
+            symmetry: None,
+            precision: None,
+        };
+        let text = super::format_mm(&a, &o).unwrap();
+        assert!(text.starts_with("%%MatrixMarket matrix array real symmetric\n%two\n%lines\n2 2\n"));
+        assert_eq!(text.lines().count(), 4 + 3);
+        let (_, data) = super::parse_mm(text.as_bytes()).unwrap();
+        match data {
+            MmData::Dense(b) => assert_eq!(b.to_vec(), a.to_vec()),
+            _ => unreachable!(),
+        }
+
+        let g = Array::from_vec(vec![0., 2., 3., 0., 0., 4.5], vec![2, 3]);
+        let o = MmWriteOptions {
+            format: MmFormat::Coordinate,
+            symmetry: Some(MmSymmetry::General),
+            ..o
+        };
+        let text = super::format_mm(&g, &o).unwrap();
+        assert!(text.ends_with("2 3 3\n1 2 2\n1 3 3\n2 3 4.5\n"));
+        let sparse = crate::sparse_ops::Csr::from_dense(&g).unwrap();
+        assert_eq!(super::format_mm_sparse(&sparse, &o).unwrap(), text);
+        let sym = crate::sparse_ops::Csr::from_dense(&a).unwrap();
+        assert_eq!(super::detect_sparse_symmetry(&sym), MmSymmetry::Symmetric);
+        let (_, data) = super::parse_mm(text.as_bytes()).unwrap();
+        match data {
+            MmData::Sparse(t) => assert_eq!(t.to_csr().to_dense().to_vec(), g.to_vec()),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -1,10 +1,15 @@
-use crate::my_util::{generate_identity_matrix_row_major, is_proper_matrix, is_square_matrix};
+use crate::array::{to_array, Array};
+use crate::elementwise_ops::broadcast_shape;
+use crate::my_util::{generate_identity_matrix_row_major, is_square_matrix};
+use crate::sparse_ops::as_sparse;
 use atomic_float::AtomicF64;
 use pyo3::exceptions::PyTypeError;
 use pyo3::types::{PyInt, PyList};
-use pyo3::{pyfunction, PyResult};
-use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use rayon::slice::ParallelSlice;
+use pyo3::{pyfunction, IntoPy, PyAny, PyObject, PyResult, Python};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
 use std::sync::atomic::Ordering::SeqCst;
 
 /// Generally loses to the other implementation
@@ -43,25 +48,280 @@ use std::sync::atomic::Ordering::SeqCst;
 // }
 
 #[pyfunction]
-pub fn matmul(a: &PyList, b: &PyList) -> PyResult<Vec<Vec<f64>>> {
-    match (a.extract::<Vec<Vec<f64>>>(), b.extract::<Vec<Vec<f64>>>()) {
-        (Ok(a_mat), Ok(b_mat)) => {
-            if a_mat.len() == 0 || b_mat.len() == 0 {
-                Err(PyTypeError::new_err("Malformed parameter(s)"))
-            } else if is_proper_matrix(&a_mat)
-                && is_proper_matrix(&b_mat)
-                && a_mat.len() == b_mat[0].len()
-            {
-                let res = rust_matmul2(&a_mat, &b_mat);
-                Ok(res)
-            } else {
-                Err(PyTypeError::new_err("Malformed parameter(s)"))
+pub fn matmul(py: Python, a: &PyAny, b: &PyAny) -> PyResult<PyObject> {
+    if let Some(s) = as_sparse(a) {
+        return s.matmul(py, b);
+    }
+    let res = match as_sparse(b) {
+        Some(s) => s.inner.rdot_dense(&to_array(a)?),
+        None => rust_array_matmul(&to_array(a)?, &to_array(b)?),
+    };
+    Ok(res.map_err(PyTypeError::new_err)?.into_py(py))
+}
+
+/// `np.matmul` semantics: 1-D operands are promoted to a row/column and the
+/// extra axis dropped afterwards, leading axes are broadcast as a batch.
+pub fn rust_array_matmul(a: &Array, b: &Array) -> Result<Array, String> {
+    if a.ndim() == 0 || b.ndim() == 0 {
+        return Err("matmul: Input operand does not have enough dimensions".to_string());
+    }
+    let a_vec = a.ndim() == 1;
+    let b_vec = b.ndim() == 1;
+    let a = if a_vec { a.expand_dims(0) } else { a.clone() };
+    let b = if b_vec { b.expand_dims(1) } else { b.clone() };
+
+    let (a_batch, a_mat) = a.shape().split_at(a.ndim() - 2);
+    let (b_batch, b_mat) = b.shape().split_at(b.ndim() - 2);
+    let (m, n, p) = (a_mat[0], a_mat[1], b_mat[1]);
+    if n != b_mat[0] {
+        return Err(format!(
+            "matmul: Input operand 1 has a mismatch in its core dimension 0 (size {} is different from {})",
+            b_mat[0], n
+        ));
+    }
+    let batch = broadcast_shape(&[a_batch, b_batch])?;
+    if batch.is_empty() {
+        let c = Array::from_vec(rust_gemm_arrays(&a, &b), vec![m, p]);
+        return Ok(match (a_vec, b_vec) {
+            (true, true) => c.reshaped(vec![]),
+            (true, false) => c.reshaped(vec![p]),
+            (false, true) => c.reshaped(vec![m]),
+            (false, false) => c,
+        });
+    }
+    let batches = batch.iter().product::<usize>();
+
+    let a_full = a
+        .broadcast_to(&[batch.as_slice(), &[m, n]].concat())?
+        .to_vec();
+    let b_full = b
+        .broadcast_to(&[batch.as_slice(), &[n, p]].concat())?
+        .to_vec();
+    let mut c = vec![0.; batches * m * p];
+    if m * p > 0 {
+        c.par_chunks_mut(m * p).enumerate().for_each(|(i, c_mat)| {
+            let a_mat = &a_full[i * m * n..(i + 1) * m * n];
+            let b_mat = &b_full[i * n * p..(i + 1) * n * p];
+            rust_flat_gemm_into(a_mat, b_mat, c_mat, n, p);
+        });
+    }
+
+    let mut shape = batch;
+    if !a_vec {
+        shape.push(m);
+    }
+    if !b_vec {
+        shape.push(p);
+    }
+    Ok(Array::from_vec(c, shape))
+}
+
+/// C += A * B for row-major A (m*n), B (n*p) and C (m*p). Each task owns a
+/// row of C and streams rows of B, so there is no write contention.
+pub fn rust_flat_gemm_into(a: &[f64], b: &[f64], c: &mut [f64], n: usize, p: usize) {
+    if p == 0 {
+        return;
+    }
+    c.par_chunks_mut(p).enumerate().for_each(|(i, c_row)| {
+        let a_row = &a[i * n..(i + 1) * n];
+        for (k, &a_ik) in a_row.iter().enumerate() {
+            let b_row = &b[k * p..(k + 1) * p];
+            c_row
+                .iter_mut()
+                .zip(b_row)
+                .for_each(|(c_ij, &b_kj)| *c_ij += a_ik * b_kj);
+        }
+    });
+}
+
+/// A matrix operand of a product, read either as a row-major buffer or as the
+/// transpose of one, so transposed views never have to be materialized.
+#[derive(Copy, Clone)]
+pub struct MatRef<'a> {
+    data: &'a [f64],
+    rows: usize,
+    cols: usize,
+    trans: bool,
+}
+
+impl<'a> MatRef<'a> {
+    /// `None` if `a` is neither a contiguous matrix nor the transpose of one.
+    pub fn of(a: &'a Array) -> Option<Self> {
+        if a.ndim() != 2 {
+            return None;
+        }
+        let (rows, cols) = (a.shape()[0], a.shape()[1]);
+        match (a.as_slice(), a.as_transposed_slice()) {
+            (Some(data), _) => Some(Self {
+                data,
+                rows,
+                cols,
+                trans: false,
+            }),
+            (None, Some(data)) => Some(Self {
+                data,
+                rows,
+                cols,
+                trans: true,
+            }),
+            _ => None,
+        }
+    }
+
+    fn at(&self, i: usize, j: usize) -> f64 {
+        if self.trans {
+            self.data[j * self.rows + i]
+        } else {
+            self.data[i * self.cols + j]
+        }
+    }
+}
+
+/// Row-parallel C = A * B. A transposed B is read column-by-column as
+/// contiguous rows of the underlying buffer (a dot product per element);
+/// otherwise rows of B are streamed as in `rust_flat_gemm_into`.
+pub fn rust_gemm(a: MatRef, b: MatRef) -> Vec<f64> {
+    let (m, n, p) = (a.rows, a.cols, b.cols);
+    let mut c = vec![0.; m * p];
+    if p == 0 {
+        return c;
+    }
+    c.par_chunks_mut(p).enumerate().for_each(|(i, c_row)| {
+        if b.trans {
+            for (j, c_ij) in c_row.iter_mut().enumerate() {
+                let b_col = &b.data[j * n..(j + 1) * n];
+                *c_ij = b_col
+                    .iter()
+                    .enumerate()
+                    .map(|(k, &b_kj)| a.at(i, k) * b_kj)
+                    .sum();
+            }
+        } else {
+            for k in 0..n {
+                let a_ik = a.at(i, k);
+                let b_row = &b.data[k * p..(k + 1) * p];
+                c_row
+                    .iter_mut()
+                    .zip(b_row)
+                    .for_each(|(c_ij, &b_kj)| *c_ij += a_ik * b_kj);
+            }
+        }
+    });
+    c
+}
+
+/// `rust_gemm` on two matrices, copying an operand only if it is neither
+/// contiguous nor a plain transpose.
+pub fn rust_gemm_arrays(a: &Array, b: &Array) -> Vec<f64> {
+    let a = if MatRef::of(a).is_some() {
+        a.clone()
+    } else {
+        a.to_contiguous()
+    };
+    let b = if MatRef::of(b).is_some() {
+        b.clone()
+    } else {
+        b.to_contiguous()
+    };
+    rust_gemm(MatRef::of(&a).unwrap(), MatRef::of(&b).unwrap())
+}
+
+#[pyfunction]
+pub fn multi_dot(py: Python, arrays: Vec<&PyAny>) -> PyResult<PyObject> {
+    let arrays = arrays
+        .into_iter()
+        .map(to_array)
+        .collect::<PyResult<Vec<Array>>>()?;
+    let res = rust_multi_dot(&arrays).map_err(PyTypeError::new_err)?;
+    if res.ndim() == 0 {
+        Ok(res.get(&[]).into_py(py))
+    } else {
+        Ok(res.into_py(py))
+    }
+}
+
+/// Cheapest parenthesization of a chain of matrices where matrix `i` is
+/// `dims[i] * dims[i + 1]`, by the textbook O(n^3) dynamic program. Returns
+/// the total multiply count and `split[i][j]`, the last split of `i..=j`.
+pub fn matrix_chain_order(dims: &[usize]) -> (usize, Vec<Vec<usize>>) {
+    let n = dims.len() - 1;
+    let mut cost = vec![vec![0usize; n]; n];
+    let mut split = vec![vec![0usize; n]; n];
+    for len in 2..=n {
+        for i in 0..=n - len {
+            let j = i + len - 1;
+            cost[i][j] = usize::MAX;
+            for k in i..j {
+                let c = cost[i][k] + cost[k + 1][j] + dims[i] * dims[k + 1] * dims[j + 1];
+                if c < cost[i][j] {
+                    cost[i][j] = c;
+                    split[i][j] = k;
+                }
             }
         }
-        _ => Err(PyTypeError::new_err("Malformed parameter(s)")),
     }
+    (cost[0][n - 1], split)
 }
 
+fn chain_product(mats: &[Array], split: &[Vec<usize>], i: usize, j: usize) -> Array {
+    if i == j {
+        return mats[i].clone();
+    }
+    let k = split[i][j];
+    let (l, r) = rayon::join(
+        || chain_product(mats, split, i, k),
+        || chain_product(mats, split, k + 1, j),
+    );
+    let (rows, cols) = (l.shape()[0], r.shape()[1]);
+    Array::from_vec(rust_gemm_arrays(&l, &r), vec![rows, cols])
+}
+
+/// `np.linalg.multi_dot`: multiplies the chain in the order picked by
+/// `matrix_chain_order`, computing independent sub-chains concurrently.
+/// The first and last operands may be vectors.
+pub fn rust_multi_dot(arrays: &[Array]) -> Result<Array, String> {
+    if arrays.len() < 2 {
+        return Err("Expecting at least two arrays.".to_string());
+    }
+    let last = arrays.len() - 1;
+    let first_vec = arrays[0].ndim() == 1;
+    let last_vec = arrays[last].ndim() == 1;
+    let mats: Vec<Array> = arrays
+        .iter()
+        .enumerate()
+        .map(|(i, a)| match (i, a.ndim()) {
+            (0, 1) => a.expand_dims(0),
+            (i, 1) if i == last => a.expand_dims(1),
+            _ => a.clone(),
+        })
+        .collect();
+    if mats.iter().any(|a| a.ndim() != 2) {
+        return Err("Only the first and last arrays may be 1-D, the rest must be 2-D.".to_string());
+    }
+    let mut dims = vec![mats[0].shape()[0]];
+    for (i, a) in mats.iter().enumerate() {
+        if a.shape()[0] != dims[i] {
+            return Err(format!(
+                "shapes {:?} and {:?} not aligned",
+                mats[i - 1].shape(),
+                a.shape()
+            ));
+        }
+        dims.push(a.shape()[1]);
+    }
+
+    let (_, split) = matrix_chain_order(&dims);
+    let res = chain_product(&mats, &split, 0, last);
+    Ok(match (first_vec, last_vec) {
+        (true, true) => res.reshaped(vec![]),
+        (true, false) => res.reshaped(vec![dims[last + 1]]),
+        (false, true) => res.reshaped(vec![dims[0]]),
+        (false, false) => res,
+    })
+}
+
+/// Generally loses to `rust_flat_gemm_into` and is only right for square inputs
+#[allow(dead_code)]
 // A = m*n, B = n*p, C = m*p
 pub fn rust_matmul2(a: &Vec<Vec<f64>>, b: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
     let m = a.len();
@@ -203,4 +463,65 @@ mod test {
         let c = super::rust_matmul2(&a, &b);
         assert_eq!(&ans, &c);
     }
+
+    #[test]
+    fn array_matmul_test() {
+        use crate::array::Array;
+        let a = Array::from_vec((1..=6).map(|e| e as f64).collect(), vec![2, 3]);
+        let b = Array::from_vec((1..=12).map(|e| e as f64).collect(), vec![3, 4]);
+        let c = super::rust_array_matmul(&a, &b).unwrap();
+        assert_eq!(c.shape(), &[2, 4]);
+        assert_eq!(c.to_vec(), vec![38., 44., 50., 56., 83., 98., 113., 128.]);
+
+        let v = Array::from_vec(vec![1., 0., -1.], vec![3]);
+        let av = super::rust_array_matmul(&a, &v).unwrap();
+        assert_eq!(av.shape(), &[2]);
+        assert_eq!(av.to_vec(), vec![-2., -2.]);
+
+        let batch = Array::from_vec((0..12).map(|e| e as f64).collect(), vec![2, 2, 3]);
+        let bc = super::rust_array_matmul(&batch, &b).unwrap();
+        assert_eq!(bc.shape(), &[2, 2, 4]);
+        assert_eq!(&bc.to_vec()[..4], &[23., 26., 29., 32.]);
+        assert!(super::rust_array_matmul(&b, &a).is_err());
+    }
+
+    #[test]
+    fn transposed_gemm_test() {
+        use crate::array::Array;
+        let a = Array::from_vec((0..6).map(|e| e as f64).collect(), vec![3, 2]);
+        let b = Array::from_vec((0..12).map(|e| e as f64).collect(), vec![4, 3]);
+        let want = super::rust_array_matmul(&a.t().to_contiguous(), &b.t().to_contiguous())
+            .unwrap()
+            .to_vec();
+        assert_eq!(super::rust_gemm_arrays(&a.t(), &b.t()), want);
+        let at_a = super::rust_gemm_arrays(&a.t(), &a);
+        assert_eq!(at_a, vec![20., 26., 26., 35.]);
+    }
+
+    #[test]
+    fn chain_order_test() {
+        // (10x100)(100x5)(5x50): ((AB)C) = 5000 + 2500, (A(BC)) = 25000 + 50000
+        let (cost, split) = super::matrix_chain_order(&[10, 100, 5, 50]);
+        assert_eq!(cost, 7500);
+        assert_eq!(split[0][2], 1);
+    }
+
+    #[test]
+    fn multi_dot_test() {
+        use crate::array::Array;
+        let a = Array::from_vec((0..20).map(|e| e as f64).collect(), vec![4, 5]);
+        let b = Array::from_vec((0..15).map(|e| e as f64 - 7.).collect(), vec![5, 3]);
+        let v = Array::from_vec(vec![1., 2., 3.], vec![3]);
+        let w = Array::from_vec(vec![1., 0., 0., 1.], vec![4]);
+
+        let ab = super::rust_array_matmul(&a, &b).unwrap();
+        let abv = super::rust_array_matmul(&ab, &v).unwrap();
+        let res = super::rust_multi_dot(&[a.clone(), b.clone(), v.clone()]).unwrap();
+        assert_eq!(res.shape(), &[4]);
+        assert_eq!(res.to_vec(), abv.to_vec());
+
+        let scalar = super::rust_multi_dot(&[w, a, b, v]).unwrap();
+        assert_eq!(scalar.ndim(), 0);
+        assert_eq!(scalar.get(&[]), abv.get(&[0]) + abv.get(&[3]));
+    }
 }
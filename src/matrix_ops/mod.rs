@@ -1,3 +1,4 @@
+use crate::array::{extract_axes, to_array, Array};
 use crate::cwslice::UnsafeSlice;
 use crate::linear_eqn_ops::*;
 use crate::my_util::{
@@ -6,9 +7,9 @@ use crate::my_util::{
 };
 use pyo3::exceptions::PyTypeError;
 use pyo3::types::PyList;
-use pyo3::{pyfunction, PyResult};
+use pyo3::{pyfunction, PyAny, PyCell, PyResult};
 use rayon::iter::IndexedParallelIterator;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
 #[pyfunction]
 pub fn trace(matrix: &PyList) -> PyResult<f64> {
@@ -52,36 +53,151 @@ pub fn rust_det(matrix: &Vec<Vec<f64>>) -> f64 {
 }
 
 #[pyfunction]
-pub fn transpose(matrix: &PyList) -> PyResult<Vec<Vec<f64>>> {
-    match matrix.extract::<Vec<Vec<f64>>>() {
-        Ok(r_matrix) => {
-            if r_matrix.len() == 0 {
-                Ok(vec![vec![]])
-            } else if !is_proper_matrix(&r_matrix) {
-                Err(PyTypeError::new_err("Parameter not a proper matrix."))
-            } else {
-                Ok(rust_transpose(&r_matrix))
-            }
-        }
-        _ => Err(PyTypeError::new_err("Parameter not a matrix.")),
+pub fn transpose(a: &PyAny, axes: Option<Vec<isize>>) -> PyResult<Array> {
+    let a = to_array(a)?;
+    a.transpose(axes.as_deref()).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn swapaxes(a: &PyAny, axis1: isize, axis2: isize) -> PyResult<Array> {
+    let a = to_array(a)?;
+    a.swapaxes(axis1, axis2).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn moveaxis(a: &PyAny, source: &PyAny, destination: &PyAny) -> PyResult<Array> {
+    let a = to_array(a)?;
+    let (src, dst) = (extract_axes(source)?, extract_axes(destination)?);
+    a.moveaxis(&src, &dst).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn transpose_inplace(a: &PyCell<Array>) -> PyResult<()> {
+    let mut a = a.borrow_mut();
+    if a.ndim() != 2 {
+        Err(PyTypeError::new_err("Parameter not a matrix."))
+    } else if !a.is_contiguous() {
+        Err(PyTypeError::new_err("Parameter not a contiguous matrix."))
+    } else if !a.is_writable() {
+        Err(PyTypeError::new_err("Parameter is read-only."))
+    } else {
+        a.transpose_inplace();
+        Ok(())
     }
 }
 
-// still slower than np
+#[allow(dead_code)]
 pub fn rust_transpose(r_matrix: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
     let row_len = r_matrix.len();
     let col_len = r_matrix[0].len();
+    let flat: Vec<f64> = r_matrix.par_iter().flatten().copied().collect();
+    row_major_to_matrix(&rust_flat_transpose(&flat, row_len, col_len), row_len)
+}
+
+const TILE: usize = 32;
+const PAR_BLOCK_MIN: usize = 128 * 128;
 
-    let mut row_major: Vec<f64> = vec![0.; col_len * row_len];
-    let unsafe_rm = UnsafeSlice::new(row_major.as_mut_slice());
+/// Transposes a row-major `rows * cols` matrix into a new buffer. The index
+/// space is halved along its longer side until a block fits in a tile, so
+/// every level of the cache sees mostly-local reads and writes without tuning
+/// for a particular cache size; large halves are handed to `rayon::join`.
+pub fn rust_flat_transpose(src: &[f64], rows: usize, cols: usize) -> Vec<f64> {
+    let mut dst = vec![0.; rows * cols];
+    let unsafe_dst = UnsafeSlice::new(dst.as_mut_slice());
+    transpose_block(src, &unsafe_dst, rows, cols, (0, rows), (0, cols));
+    dst
+}
+
+fn transpose_block(
+    src: &[f64],
+    dst: &UnsafeSlice<f64>,
+    rows: usize,
+    cols: usize,
+    (r0, r1): (usize, usize),
+    (c0, c1): (usize, usize),
+) {
+    let (h, w) = (r1 - r0, c1 - c0);
+    if h <= TILE && w <= TILE {
+        for r in r0..r1 {
+            for c in c0..c1 {
+                unsafe { dst.write(c * rows + r, src[r * cols + c]) };
+            }
+        }
+        return;
+    }
 
-    r_matrix.par_iter().enumerate().for_each(|(i_row, row)| {
-        row.par_iter().enumerate().for_each(|(i_elm, &elm)| unsafe {
-            unsafe_rm.write(i_elm * row_len + i_row, elm);
-        })
+    let (left, right) = if h >= w {
+        let mid = r0 + h / 2;
+        (((r0, mid), (c0, c1)), ((mid, r1), (c0, c1)))
+    } else {
+        let mid = c0 + w / 2;
+        (((r0, r1), (c0, mid)), ((r0, r1), (mid, c1)))
+    };
+    if h * w >= PAR_BLOCK_MIN {
+        rayon::join(
+            || transpose_block(src, dst, rows, cols, left.0, left.1),
+            || transpose_block(src, dst, rows, cols, right.0, right.1),
+        );
+    } else {
+        transpose_block(src, dst, rows, cols, left.0, left.1);
+        transpose_block(src, dst, rows, cols, right.0, right.1);
+    }
+}
+
+/// Transposes an `n * n` row-major matrix in place by swapping tile `(i, j)`
+/// with tile `(j, i)`. Each task owns one row of tiles on and above the
+/// diagonal, so no two tasks touch the same element.
+pub fn rust_transpose_square_inplace(a: &mut [f64], n: usize) {
+    let nb = n.div_ceil(TILE);
+    let unsafe_a = UnsafeSlice::new(a);
+
+    (0..nb).into_par_iter().for_each(|bi| {
+        for bj in bi..nb {
+            for r in bi * TILE..((bi + 1) * TILE).min(n) {
+                let c_start = if bi == bj { r + 1 } else { bj * TILE };
+                for c in c_start..((bj + 1) * TILE).min(n) {
+                    unsafe {
+                        let upper = unsafe_a.read(r * n + c);
+                        unsafe_a.write(r * n + c, unsafe_a.read(c * n + r));
+                        unsafe_a.write(c * n + r, upper);
+                    }
+                }
+            }
+        }
     });
+}
 
-    row_major_to_matrix(&row_major, row_len)
+/// Transposes a row-major `rows * cols` matrix in place by following the
+/// cycles of the permutation `i -> i * rows mod (rows * cols - 1)`. Only a
+/// visited bitmap is allocated, so the extra memory is 1/64th of the matrix.
+/// The cycles have irregular lengths and are walked sequentially; square
+/// matrices take the parallel tiled path instead.
+pub fn rust_transpose_inplace(a: &mut [f64], rows: usize, cols: usize) {
+    let len = rows * cols;
+    if rows == cols {
+        return rust_transpose_square_inplace(a, rows);
+    } else if rows <= 1 || cols <= 1 {
+        return;
+    }
+
+    let last = len - 1;
+    let mut visited = vec![0u64; len / 64 + 1];
+    for start in 1..last {
+        if visited[start / 64] & (1 << (start % 64)) != 0 {
+            continue;
+        }
+        let mut i = start;
+        let mut carry = a[start];
+        loop {
+            let next = (i as u128 * rows as u128 % last as u128) as usize;
+            std::mem::swap(&mut a[next], &mut carry);
+            visited[next / 64] |= 1 << (next % 64);
+            i = next;
+            if i == start {
+                break;
+            }
+        }
+    }
 }
 
 #[pyfunction]
@@ -98,7 +214,7 @@ pub fn inv(a: &PyList) -> PyResult<Vec<Vec<f64>>> {
     }
 }
 
-pub fn rust_inv(a: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+pub fn rust_inv(a: &[Vec<f64>]) -> Vec<Vec<f64>> {
     let n = a.len();
     let augmented = augment(a, &generate_identity_matrix(n));
     let fwd = fwd_elim(&augmented);
@@ -180,4 +296,36 @@ mod test {
         dbg!(res);
         assert!((2061. - res).abs() < THRESHOLD);
     }
+
+    #[allow(dead_code)]
+    fn naive_transpose(a: &[f64], rows: usize, cols: usize) -> Vec<f64> {
+        let mut out = vec![0.; rows * cols];
+        for r in 0..rows {
+            for c in 0..cols {
+                out[c * rows + r] = a[r * cols + c];
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn flat_trans_test() {
+        for &(rows, cols) in &[(1, 7), (70, 45), (300, 257)] {
+            let a: Vec<f64> = (0..rows * cols).map(|e| e as f64).collect();
+            assert_eq!(
+                naive_transpose(&a, rows, cols),
+                super::rust_flat_transpose(&a, rows, cols)
+            );
+        }
+    }
+
+    #[test]
+    fn inplace_trans_test() {
+        for &(rows, cols) in &[(67, 67), (13, 7), (70, 45), (1, 9)] {
+            let mut a: Vec<f64> = (0..rows * cols).map(|e| e as f64).collect();
+            let ans = naive_transpose(&a, rows, cols);
+            super::rust_transpose_inplace(&mut a, rows, cols);
+            assert_eq!(ans, a);
+        }
+    }
 }
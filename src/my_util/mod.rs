@@ -1,9 +1,7 @@
-use crate::cwslice::UnsafeSlice;
-use rayon::{
-    iter::{
-        IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
-    },
-    slice::ParallelSlice,
+use crate::array::Array;
+use crate::shape_ops::{rust_hstack, rust_reshape, rust_split_at};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
 };
 
 pub fn simplify_soln(a: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
@@ -16,45 +14,29 @@ pub fn simplify_soln(a: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
         .collect()
 }
 
+/// `a` without its last column, and that column, via `rust_split_at`.
 #[allow(dead_code)]
-pub fn split_last_col(a: &Vec<Vec<f64>>) -> (Vec<Vec<f64>>, Vec<f64>) {
-    let m = a.len();
-    let idx = a[0].len() - 1;
-
-    let mut o1 = vec![vec![]; idx];
-    let mut o2 = vec![0.; m];
-
-    let u1 = UnsafeSlice::new(o1.as_mut_slice());
-    let u2 = UnsafeSlice::new(o2.as_mut_slice());
-
-    a.par_iter().enumerate().for_each(|(i, row)| {
-        let (l, r) = row.split_at(idx);
-        unsafe {
-            u1.write(i, l.to_vec());
-            u2.write(i, r[0]);
-        }
-    });
-
-    (o1, o2)
+pub fn split_last_col(a: &[Vec<f64>]) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let a = Array::from_matrix(a);
+    let cols = a.shape()[1];
+    let parts = rust_split_at(&a, &[cols - 1], 1).unwrap();
+    (parts[0].to_matrix(), parts[1].to_vec())
 }
 
+/// `[a | b]`, via `rust_hstack`.
 #[allow(dead_code)]
-pub fn augment(a: &Vec<Vec<f64>>, b: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
-    a.par_iter()
-        .zip(b.par_iter())
-        .map(|(a_row, b_row)| {
-            let mut c = vec![];
-            c.extend(a_row);
-            c.extend(b_row);
-            c
-        })
-        .collect()
+pub fn augment(a: &[Vec<f64>], b: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    rust_hstack(&[Array::from_matrix(a), Array::from_matrix(b)])
+        .expect("augment: row counts differ")
+        .to_matrix()
 }
 
+/// The last `n` columns of `a`, via `rust_split_at`.
 #[allow(dead_code)]
-pub fn extract_last_n_cols(a: &Vec<Vec<f64>>, n: usize) -> Vec<Vec<f64>> {
-    let b = a[0].len() - n;
-    a.par_iter().map(|a_row| a_row[b..].to_vec()).collect()
+pub fn extract_last_n_cols(a: &[Vec<f64>], n: usize) -> Vec<Vec<f64>> {
+    let a = Array::from_matrix(a);
+    let cols = a.shape()[1];
+    rust_split_at(&a, &[cols - n], 1).unwrap()[1].to_matrix()
 }
 
 #[allow(dead_code)]
@@ -76,12 +58,13 @@ pub fn generate_identity_matrix_row_major(n: usize) -> Vec<f64> {
     res
 }
 
+/// Rows of a row-major buffer with `cols` columns, via `rust_reshape`.
 #[allow(dead_code)]
-pub fn row_major_to_matrix(row_major: &Vec<f64>, cols: usize) -> Vec<Vec<f64>> {
-    row_major
-        .par_chunks(cols)
-        .map(|chunk| chunk.to_vec())
-        .collect()
+pub fn row_major_to_matrix(row_major: &[f64], cols: usize) -> Vec<Vec<f64>> {
+    let flat = Array::from_vec(row_major.to_vec(), vec![row_major.len()]);
+    rust_reshape(&flat, &[-1, cols as isize])
+        .unwrap()
+        .to_matrix()
 }
 
 pub fn is_square_matrix(matrix: &Vec<Vec<f64>>) -> bool {
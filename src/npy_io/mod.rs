@@ -0,0 +1,885 @@
+use crate::array::{to_array, Array, MapMode};
+use crate::creation_ops::extract_shape;
+use pyo3::exceptions::{PyIOError, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+const HEADER_ALIGN: usize = 64;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DtypeKind {
+    Float,
+    Int,
+    UInt,
+    Bool,
+}
+
+/// A simple (non-structured) numpy dtype such as `<f8` or `|u1`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Dtype {
+    pub kind: DtypeKind,
+    pub size: usize,
+    pub big_endian: bool,
+}
+
+impl Dtype {
+    pub fn parse(descr: &str) -> Result<Dtype, String> {
+        let unsupported = || format!("unsupported dtype '{}'", descr);
+        let mut chars = descr.chars();
+        let (big_endian, rest) = match chars.next() {
+            Some('<') | Some('|') => (false, chars.as_str()),
+            Some('>') => (true, chars.as_str()),
+            Some('=') => (cfg!(target_endian = "big"), chars.as_str()),
+            _ => (cfg!(target_endian = "big"), descr),
+        };
+        let (kind, size) = match rest {
+            "?" => (DtypeKind::Bool, 1),
+            _ if rest.len() >= 2 => {
+                let kind = match &rest[..1] {
+                    "f" => DtypeKind::Float,
+                    "i" => DtypeKind::Int,
+                    "u" => DtypeKind::UInt,
+                    "b" => DtypeKind::Bool,
+                    _ => return Err(unsupported()),
+                };
+                let size = rest[1..].parse::<usize>().map_err(|_| unsupported())?;
+                (kind, size)
+            }
+            _ => return Err(unsupported()),
+        };
+        let ok = match kind {
+            DtypeKind::Float => matches!(size, 2 | 4 | 8),
+            DtypeKind::Int | DtypeKind::UInt => matches!(size, 1 | 2 | 4 | 8),
+            DtypeKind::Bool => size == 1,
+        };
+        if !ok {
+            return Err(unsupported());
+        }
+        Ok(Dtype {
+            kind,
+            size,
+            big_endian,
+        })
+    }
+
+    /// A dtype by numpy name (`float32`, `int16`, `uint8`, ...), little-endian
+    /// unless spelled as an explicit descr such as `>f8`. `int`, `l` and `L`
+    /// are 64-bit, as numpy has them on LP64 platforms.
+    pub fn from_name(name: &str) -> Result<Dtype, String> {
+        let descr = match name {
+            "float64" | "double" | "float" | "d" => "<f8",
+            "float32" | "single" | "f" => "<f4",
+            "float16" | "half" | "e" => "<f2",
+            "int64" | "int" | "q" | "l" => "<i8",
+            "int32" | "intc" | "i" => "<i4",
+            "int16" | "short" | "h" => "<i2",
+            "int8" | "byte" => "|i1",
+            "uint64" | "Q" | "L" => "<u8",
+            "uint32" | "uintc" | "I" => "<u4",
+            "uint16" | "ushort" | "H" => "<u2",
+            "uint8" | "ubyte" | "B" => "|u1",
+            "bool" | "bool_" => "|b1",
+            _ if name.len() > 1 && !name.starts_with(['<', '>', '|', '=']) => {
+                return Dtype::parse(&format!("<{}", name))
+            }
+            _ => name,
+        };
+        Dtype::parse(descr)
+    }
+
+    /// Writes one element into exactly `self.size` bytes. Integers truncate
+    /// toward zero and saturate; float16 is not supported.
+    pub fn encode(&self, x: f64, out: &mut [u8]) {
+        let b = match (self.kind, self.size) {
+            (DtypeKind::Float, 4) => (x as f32).to_le_bytes().to_vec(),
+            (DtypeKind::Float, _) => x.to_le_bytes().to_vec(),
+            (DtypeKind::Bool, _) => vec![(x != 0.) as u8],
+            (DtypeKind::UInt, n) => {
+                (x as u64).min(u64::MAX >> (64 - 8 * n)).to_le_bytes()[..n].to_vec()
+            }
+            (DtypeKind::Int, n) => {
+                let max = i64::MAX >> (64 - 8 * n);
+                (x as i64).clamp(-max - 1, max).to_le_bytes()[..n].to_vec()
+            }
+        };
+        out.copy_from_slice(&b[..self.size]);
+        if self.big_endian {
+            out.reverse();
+        }
+    }
+
+    /// Reads one element, which must be exactly `self.size` bytes.
+    pub fn decode(&self, bytes: &[u8]) -> f64 {
+        let mut b = [0u8; 8];
+        b[..self.size].copy_from_slice(bytes);
+        if self.big_endian {
+            b[..self.size].reverse();
+        }
+        match (self.kind, self.size) {
+            (DtypeKind::Float, 2) => half_to_f64(u16::from_le_bytes([b[0], b[1]])),
+            (DtypeKind::Float, 4) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            (DtypeKind::Float, _) => f64::from_le_bytes(b),
+            (DtypeKind::Bool, _) => (b[0] != 0) as u8 as f64,
+            (DtypeKind::UInt, _) => u64::from_le_bytes(b) as f64,
+            (DtypeKind::Int, n) => {
+                // sign-extend from `n` bytes
+                let shift = 64 - 8 * n as u32;
+                ((u64::from_le_bytes(b) << shift) as i64 >> shift) as f64
+            }
+        }
+    }
+}
+
+fn half_to_f64(h: u16) -> f64 {
+    let sign = if h >> 15 == 1 { -1. } else { 1. };
+    let exp = ((h >> 10) & 0x1f) as i32;
+    let frac = (h & 0x3ff) as f64;
+    sign * match exp {
+        0 => frac * 2f64.powi(-24),
+        0x1f if frac == 0. => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (1. + frac / 1024.) * 2f64.powi(exp - 15),
+    }
+}
+
+/// The parsed header of an `.npy` file; the data starts at `data_offset`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NpyHeader {
+    pub dtype: Dtype,
+    pub fortran_order: bool,
+    pub shape: Vec<usize>,
+    pub data_offset: usize,
+}
+
+impl NpyHeader {
+    pub fn data_len(&self) -> usize {
+        self.shape.iter().product::<usize>() * self.dtype.size
+    }
+}
+
+/// Value of `key` in the header dict literal, up to the next top-level comma.
+fn dict_value<'a>(dict: &'a str, key: &str) -> Result<&'a str, String> {
+    let pat = format!("'{}'", key);
+    let start = dict
+        .find(&pat)
+        .ok_or_else(|| format!("npy header has no '{}' key", key))?;
+    let rest = dict[start + pat.len()..].trim_start();
+    let rest = rest
+        .strip_prefix(':')
+        .ok_or_else(|| "malformed npy header".to_string())?
+        .trim_start();
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|i| i + 1)
+    } else {
+        rest.find([',', '}'])
+    };
+    Ok(rest[..end.unwrap_or(rest.len())].trim())
+}
+
+/// Parses the magic string, version and header dict of NPY v1, v2 or v3.
+pub fn parse_header(bytes: &[u8]) -> Result<NpyHeader, String> {
+    if bytes.len() < 10 || &bytes[..6] != MAGIC {
+        return Err("not an npy file".to_string());
+    }
+    let (len, start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            12,
+        ),
+        v => return Err(format!("unsupported npy format version {}", v)),
+    };
+    let raw = bytes
+        .get(start..start + len)
+        .ok_or_else(|| "truncated npy header".to_string())?;
+    // v1 and v2 headers are latin-1, v3 is utf-8; the keys we need are ascii
+    // either way.
+    let dict = String::from_utf8_lossy(raw);
+
+    let descr = dict_value(&dict, "descr")?;
+    let descr = descr
+        .strip_prefix('\'')
+        .and_then(|d| d.strip_suffix('\''))
+        .ok_or_else(|| format!("unsupported dtype {}", descr))?;
+    let fortran_order = match dict_value(&dict, "fortran_order")? {
+        "True" => true,
+        "False" => false,
+        v => return Err(format!("invalid fortran_order {}", v)),
+    };
+    let shape = dict_value(&dict, "shape")?;
+    let shape = shape
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .ok_or_else(|| format!("invalid shape {}", shape))?
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim_end_matches('L').parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(NpyHeader {
+        dtype: Dtype::parse(descr)?,
+        fortran_order,
+        shape,
+        data_offset: start + len,
+    })
+}
+
+/// Decodes the data of an `.npy` file (in parallel) into a C-ordered Array.
+pub fn parse_npy(bytes: &[u8]) -> Result<Array, String> {
+    let header = parse_header(bytes)?;
+    let data = bytes
+        .get(header.data_offset..header.data_offset + header.data_len())
+        .ok_or_else(|| "npy file is shorter than its header says".to_string())?;
+    let dtype = header.dtype;
+    let values = data
+        .par_chunks_exact(dtype.size)
+        .map(|b| dtype.decode(b))
+        .collect();
+    Ok(from_ordered(values, header.shape, header.fortran_order))
+}
+
+/// Array from data in C or Fortran order.
+pub fn from_ordered(data: Vec<f64>, shape: Vec<usize>, fortran_order: bool) -> Array {
+    if fortran_order {
+        let reversed = shape.iter().rev().copied().collect();
+        Array::from_vec(data, reversed)
+            .transpose(None)
+            .unwrap()
+            .to_contiguous()
+    } else {
+        Array::from_vec(data, shape)
+    }
+}
+
+/// Header for `<f8` C-ordered data of the given shape, padded so that the
+/// data starts on a 64-byte boundary. Version 2 is only used when the header
+/// does not fit v1's 16-bit length.
+pub fn npy_header(shape: &[usize]) -> Vec<u8> {
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let dict = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': {}, }}",
+        shape
+    );
+    let mut out = MAGIC.to_vec();
+    for (major, prefix) in [(1u8, 10), (2, 12)] {
+        let len = (prefix + dict.len() + 1).div_ceil(HEADER_ALIGN) * HEADER_ALIGN - prefix;
+        if major == 1 && len > u16::MAX as usize {
+            continue;
+        }
+        out.extend_from_slice(&[major, 0]);
+        if major == 1 {
+            out.extend_from_slice(&(len as u16).to_le_bytes());
+        } else {
+            out.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+        out.extend_from_slice(dict.as_bytes());
+        out.resize(prefix + len - 1, b' ');
+        out.push(b'\n');
+        break;
+    }
+    out
+}
+
+pub fn npy_bytes(a: &Array) -> Vec<u8> {
+    let mut out = npy_header(a.shape());
+    let start = out.len();
+    out.resize(start + 8 * a.size(), 0);
+    out[start..]
+        .par_chunks_mut(8)
+        .zip(a.to_vec().par_iter())
+        .for_each(|(b, x)| b.copy_from_slice(&x.to_le_bytes()));
+    out
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |c, &b| {
+        CRC32_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
+
+const LOCAL_SIG: u32 = 0x04034b50;
+const CENTRAL_SIG: u32 = 0x02014b50;
+const END_SIG: u32 = 0x06054b50;
+const DOS_DATE_1980: u16 = 0x21;
+
+fn le16(b: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([b[at], b[at + 1]]) as usize
+}
+
+fn le32(b: &[u8], at: usize) -> usize {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]]) as usize
+}
+
+fn le64(b: &[u8], at: usize) -> usize {
+    let mut x = [0u8; 8];
+    x.copy_from_slice(&b[at..at + 8]);
+    u64::from_le_bytes(x) as usize
+}
+
+/// Zip archive with every entry stored uncompressed, the layout `np.savez`
+/// writes. Entries (and the archive) must stay below 4 GiB since no ZIP64
+/// records are written.
+pub fn zip_stored(entries: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    let mut out = vec![];
+    let mut central = vec![];
+    for (name, data) in entries {
+        let offset = out.len();
+        if data.len() > u32::MAX as usize || offset > u32::MAX as usize {
+            return Err("npz archives over 4 GiB are not supported".to_string());
+        }
+        let crc = crc32(data);
+        let fields = |buf: &mut Vec<u8>| {
+            buf.extend_from_slice(&20u16.to_le_bytes()); // version needed
+            buf.extend_from_slice(&0u16.to_le_bytes()); // flags
+            buf.extend_from_slice(&0u16.to_le_bytes()); // stored
+            buf.extend_from_slice(&0u16.to_le_bytes()); // time
+            buf.extend_from_slice(&DOS_DATE_1980.to_le_bytes());
+            buf.extend_from_slice(&crc.to_le_bytes());
+            buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            buf.extend_from_slice(&0u16.to_le_bytes()); // extra length
+        };
+        out.extend_from_slice(&LOCAL_SIG.to_le_bytes());
+        fields(&mut out);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        central.extend_from_slice(&CENTRAL_SIG.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        fields(&mut central);
+        central.extend_from_slice(&[0; 6]); // comment, disk, internal attributes
+        central.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        central.extend_from_slice(&(offset as u32).to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+    let cd_offset = out.len();
+    if cd_offset > u32::MAX as usize {
+        return Err("npz archives over 4 GiB are not supported".to_string());
+    }
+    out.extend_from_slice(&central);
+    out.extend_from_slice(&END_SIG.to_le_bytes());
+    out.extend_from_slice(&[0; 4]); // disk numbers
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(cd_offset as u32).to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    Ok(out)
+}
+
+/// Entries of a zip archive whose members are stored uncompressed, checked
+/// against their CRC. Understands the ZIP64 size fields `np.savez` emits.
+pub fn unzip_stored(bytes: &[u8]) -> Result<Vec<(String, &[u8])>, String> {
+    let corrupt = || "corrupt npz archive".to_string();
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .take((1 << 16) + 22)
+        .find(|&i| le32(bytes, i) == END_SIG as usize)
+        .ok_or_else(corrupt)?;
+    let count = le16(bytes, end + 10);
+    let mut at = le32(bytes, end + 16);
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if at + 46 > bytes.len() || le32(bytes, at) != CENTRAL_SIG as usize {
+            return Err(corrupt());
+        }
+        let method = le16(bytes, at + 10);
+        let crc = le32(bytes, at + 16) as u32;
+        let mut size = le32(bytes, at + 20);
+        let (name_len, extra_len, comment_len) = (
+            le16(bytes, at + 28),
+            le16(bytes, at + 30),
+            le16(bytes, at + 32),
+        );
+        let mut offset = le32(bytes, at + 42);
+        let name = bytes.get(at + 46..at + 46 + name_len).ok_or_else(corrupt)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        // ZIP64 extra field: the 64-bit values of whichever fields are
+        // saturated, in the order uncompressed, compressed, offset.
+        let extra = bytes
+            .get(at + 46 + name_len..at + 46 + name_len + extra_len)
+            .ok_or_else(corrupt)?;
+        let (full_size, full_offset) = (size == u32::MAX as usize, offset == u32::MAX as usize);
+        let full_uncompressed = le32(bytes, at + 24) == u32::MAX as usize;
+        let mut e = 0;
+        while e + 4 <= extra.len() {
+            let (id, len) = (le16(extra, e), le16(extra, e + 2));
+            if e + 4 + len > extra.len() {
+                return Err(corrupt());
+            }
+            if id == 1 {
+                let fields = &extra[e + 4..e + 4 + len];
+                let mut f = 0;
+                let mut next = || {
+                    let x = fields.get(f..f + 8).map(|_| le64(fields, f));
+                    f += 8;
+                    x.ok_or_else(corrupt)
+                };
+                if full_uncompressed {
+                    next()?;
+                }
+                if full_size {
+                    size = next()?;
+                }
+                if full_offset {
+                    offset = next()?;
+                }
+            }
+            e += 4 + len;
+        }
+        if method != 0 {
+            return Err(format!(
+                "npz entry '{}' is compressed; only stored entries (np.savez) are supported",
+                name
+            ));
+        }
+        if offset + 30 > bytes.len() || le32(bytes, offset) != LOCAL_SIG as usize {
+            return Err(corrupt());
+        }
+        let start = offset + 30 + le16(bytes, offset + 26) + le16(bytes, offset + 28);
+        let data = bytes.get(start..start + size).ok_or_else(corrupt)?;
+        if crc32(data) != crc {
+            return Err(format!("bad CRC for npz entry '{}'", name));
+        }
+        entries.push((name, data));
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+pub enum Loaded {
+    Array(Array),
+    Archive(Vec<(String, Array)>),
+}
+
+pub fn rust_save(path: &str, a: &Array) -> Result<(), String> {
+    std::fs::write(path, npy_bytes(a)).map_err(|e| e.to_string())
+}
+
+/// `np.fromfile`: `count` items (all if `None`) of a raw binary file read
+/// from byte `offset` as a 1-D array.
+pub fn rust_fromfile(
+    path: &str,
+    dtype: Dtype,
+    count: Option<usize>,
+    offset: usize,
+) -> Result<Array, String> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let avail = (file.metadata().map_err(|e| e.to_string())?.len() as usize).saturating_sub(offset);
+    let count = match count {
+        Some(c) if c * dtype.size > avail => {
+            return Err(format!(
+                "file holds {} items of {} bytes, {} requested",
+                avail / dtype.size,
+                dtype.size,
+                c
+            ))
+        }
+        Some(c) => c,
+        None if !avail.is_multiple_of(dtype.size) => {
+            return Err("file size is not a multiple of the item size".to_string())
+        }
+        None => avail / dtype.size,
+    };
+    let mut bytes = vec![0; count * dtype.size];
+    file.seek(SeekFrom::Start(offset as u64))
+        .and_then(|_| file.read_exact(&mut bytes))
+        .map_err(|e| e.to_string())?;
+    let data = bytes
+        .par_chunks_exact(dtype.size)
+        .map(|b| dtype.decode(b))
+        .collect();
+    Ok(Array::from_vec(data, vec![count]))
+}
+
+/// `ndarray.tofile`: the elements in C order as raw `dtype` items.
+pub fn rust_tofile(path: &str, a: &Array, dtype: Dtype) -> Result<(), String> {
+    if dtype.kind == DtypeKind::Float && dtype.size == 2 {
+        return Err("writing float16 is not supported".to_string());
+    }
+    let data = a.to_contiguous();
+    let mut bytes = vec![0; a.size() * dtype.size];
+    bytes
+        .par_chunks_exact_mut(dtype.size)
+        .zip(data.as_slice().unwrap())
+        .for_each(|(out, &x)| dtype.encode(x, out));
+    std::fs::write(path, bytes).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn rust_savez(path: &str, arrays: &[(String, Array)]) -> Result<(), String> {
+    let entries = arrays
+        .iter()
+        .map(|(name, a)| (format!("{}.npy", name), npy_bytes(a)))
+        .collect::<Vec<_>>();
+    std::fs::write(path, zip_stored(&entries)?).map_err(|e| e.to_string())
+}
+
+/// Loads an `.npy` array, or every array of an `.npz` archive (keyed by
+/// entry name without the `.npy` suffix).
+pub fn rust_load(path: &str) -> Result<Loaded, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    if bytes.starts_with(&LOCAL_SIG.to_le_bytes()) {
+        let arrays = unzip_stored(&bytes)?
+            .into_iter()
+            .map(|(name, data)| {
+                let name = name.strip_suffix(".npy").unwrap_or(&name).to_string();
+                Ok((name, parse_npy(data)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Loaded::Archive(arrays))
+    } else {
+        Ok(Loaded::Array(parse_npy(&bytes)?))
+    }
+}
+
+/// Parses a numpy memmap mode; `w+` is reported as `None` since it maps
+/// read-write after creating the file.
+pub fn parse_map_mode(mode: &str) -> Result<Option<MapMode>, String> {
+    match mode {
+        "r" | "readonly" => Ok(Some(MapMode::ReadOnly)),
+        "r+" | "readwrite" => Ok(Some(MapMode::ReadWrite)),
+        "c" | "copyonwrite" => Ok(Some(MapMode::CopyOnWrite)),
+        "w+" | "write" => Ok(None),
+        _ => Err(format!(
+            "mode must be one of 'r', 'r+', 'w+', 'c', got '{}'",
+            mode
+        )),
+    }
+}
+
+/// Maps `shape` (or, if `None`, all of the file after `offset`) as float64
+/// data in C or Fortran order. `r+` and `w+` grow a short file, `w+`
+/// truncates it first.
+pub fn rust_memmap(
+    path: &str,
+    mode: &str,
+    offset: usize,
+    shape: Option<Vec<usize>>,
+    fortran_order: bool,
+) -> Result<Array, String> {
+    let map_mode = parse_map_mode(mode)?;
+    let mut options = std::fs::OpenOptions::new();
+    match map_mode {
+        Some(MapMode::ReadOnly) | Some(MapMode::CopyOnWrite) => options.read(true),
+        Some(MapMode::ReadWrite) => options.read(true).write(true),
+        None => options.read(true).write(true).create(true).truncate(true),
+    };
+    let file = options.open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let file_len = file.metadata().map_err(|e| e.to_string())?.len() as usize;
+    let shape = match shape {
+        Some(shape) => shape,
+        None if map_mode.is_none() => return Err("shape must be given in 'w+' mode".to_string()),
+        None => {
+            let avail = file_len.saturating_sub(offset);
+            if !avail.is_multiple_of(8) {
+                return Err(
+                    "size of available data is not a multiple of the data-type size".to_string(),
+                );
+            }
+            vec![avail / 8]
+        }
+    };
+    let end = offset + shape.iter().product::<usize>() * 8;
+    if end > file_len {
+        match map_mode {
+            Some(MapMode::ReadWrite) | None => {
+                file.set_len(end as u64).map_err(|e| e.to_string())?
+            }
+            _ => return Err("mmap length is greater than file size".to_string()),
+        }
+    }
+    let mode = map_mode.unwrap_or(MapMode::ReadWrite);
+    if fortran_order {
+        let reversed = shape.iter().rev().copied().collect();
+        Array::mapped(&file, offset, reversed, mode)?.transpose(None)
+    } else {
+        Array::mapped(&file, offset, shape, mode)
+    }
+}
+
+/// Maps the data of a float64 `.npy` file instead of reading it.
+pub fn rust_load_mapped(path: &str, mode: &str) -> Result<Array, String> {
+    use std::io::Read;
+    if parse_map_mode(mode)?.is_none() {
+        return Err("cannot load an npy file in 'w+' mode".to_string());
+    }
+    let mut file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut bytes = vec![0; 12];
+    file.read_exact(&mut bytes)
+        .map_err(|_| "not an npy file".to_string())?;
+    let len = match bytes[6] {
+        1 => u16::from_le_bytes([bytes[8], bytes[9]]) as usize + 10,
+        _ => u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize + 12,
+    };
+    bytes.resize(len.max(12), 0);
+    file.read_exact(&mut bytes[12..])
+        .map_err(|_| "truncated npy header".to_string())?;
+    let header = parse_header(&bytes)?;
+    let native = Dtype::parse("<f8")?;
+    if header.dtype != native {
+        return Err("memory-mapping an npy file requires dtype '<f8'".to_string());
+    }
+    rust_memmap(
+        path,
+        mode,
+        header.data_offset,
+        Some(header.shape),
+        header.fortran_order,
+    )
+}
+
+/// Accepts a `str` or any path-like object.
+pub fn extract_path(file: &PyAny) -> PyResult<String> {
+    match file.extract::<String>() {
+        Ok(path) => Ok(path),
+        Err(_) => file.call_method0("__fspath__")?.extract(),
+    }
+}
+
+pub fn with_suffix(path: String, suffix: &str) -> String {
+    if path.ends_with(suffix) {
+        path
+    } else {
+        path + suffix
+    }
+}
+
+#[pyfunction]
+pub fn save(file: &PyAny, arr: &PyAny) -> PyResult<()> {
+    let path = with_suffix(extract_path(file)?, ".npy");
+    rust_save(&path, &to_array(arr)?).map_err(PyIOError::new_err)
+}
+
+#[pyfunction(args = "*", kwds = "**")]
+pub fn savez(file: &PyAny, args: &PyTuple, kwds: Option<&PyDict>) -> PyResult<()> {
+    let path = with_suffix(extract_path(file)?, ".npz");
+    let mut arrays = args
+        .iter()
+        .enumerate()
+        .map(|(i, a)| Ok((format!("arr_{}", i), to_array(a)?)))
+        .collect::<PyResult<Vec<_>>>()?;
+    for (name, a) in kwds.into_iter().flatten() {
+        let name = name.extract::<String>()?;
+        if arrays.iter().any(|(n, _)| *n == name) {
+            return Err(PyIOError::new_err(format!(
+                "Cannot use un-named variables and keyword {}",
+                name
+            )));
+        }
+        arrays.push((name, to_array(a)?));
+    }
+    rust_savez(&path, &arrays).map_err(PyIOError::new_err)
+}
+
+#[pyfunction]
+pub fn load(py: Python, file: &PyAny, mmap_mode: Option<&str>) -> PyResult<PyObject> {
+    if let Some(mode) = mmap_mode {
+        let a = rust_load_mapped(&extract_path(file)?, mode).map_err(PyIOError::new_err)?;
+        return Ok(a.into_py(py));
+    }
+    match rust_load(&extract_path(file)?).map_err(PyIOError::new_err)? {
+        Loaded::Array(a) => Ok(a.into_py(py)),
+        Loaded::Archive(arrays) => {
+            let dict = PyDict::new(py);
+            for (name, a) in arrays {
+                dict.set_item(name, a.into_py(py))?;
+            }
+            Ok(dict.into_py(py))
+        }
+    }
+}
+
+#[pyfunction(dtype = "\"float64\"", mode = "\"r+\"", offset = "0", order = "\"C\"")]
+pub fn memmap(
+    filename: &PyAny,
+    dtype: &str,
+    mode: &str,
+    offset: usize,
+    shape: Option<&PyAny>,
+    order: &str,
+) -> PyResult<Array> {
+    if !matches!(dtype, "float64" | "f8" | "<f8" | "double" | "d") {
+        return Err(PyTypeError::new_err(format!(
+            "memmap only supports float64, got '{}'",
+            dtype
+        )));
+    }
+    let fortran_order = match order {
+        "C" => false,
+        "F" => true,
+        _ => return Err(PyTypeError::new_err("order must be 'C' or 'F'")),
+    };
+    let shape = shape.map(extract_shape).transpose()?;
+    rust_memmap(&extract_path(filename)?, mode, offset, shape, fortran_order)
+        .map_err(PyIOError::new_err)
+}
+
+#[pyfunction(dtype = "\"float64\"", count = "-1", offset = "0")]
+pub fn fromfile(file: &PyAny, dtype: &str, count: isize, offset: usize) -> PyResult<Array> {
+    let dtype = Dtype::from_name(dtype).map_err(PyTypeError::new_err)?;
+    let count = (count >= 0).then_some(count as usize);
+    rust_fromfile(&extract_path(file)?, dtype, count, offset).map_err(PyIOError::new_err)
+}
+
+mod test {
+    #[test]
+    fn npy_roundtrip_test() {
+        use crate::array::Array;
+        let a = Array::from_vec((0..6).map(|i| i as f64 / 3.).collect(), vec![2, 3]);
+        let bytes = super::npy_bytes(&a.t());
+        assert_eq!(super::parse_header(&bytes).unwrap().data_offset % 64, 0);
+        let b = super::parse_npy(&bytes).unwrap();
+        assert_eq!(b.shape(), &[3, 2]);
+        assert_eq!(b.to_vec(), a.t().to_vec());
+
+        let s = super::parse_npy(&super::npy_bytes(&Array::scalar(2.5))).unwrap();
+        assert_eq!(s.ndim(), 0);
+        assert_eq!(s.get(&[]), 2.5);
+    }
+
+    #[test]
+    fn npy_dtype_test() {
+        // What np.save writes for np.array([[1, -2], [3, 4]], dtype='>i2', order='F').
+        let dict = b"{'descr': '>i2', 'fortran_order': True, 'shape': (2, 2), }";
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend_from_slice(&(dict.len() as u16 + 1).to_le_bytes());
+        bytes.extend_from_slice(dict);
+        bytes.push(b'\n');
+        for x in [1i16, 3, -2, 4] {
+            bytes.extend_from_slice(&x.to_be_bytes());
+        }
+        let a = super::parse_npy(&bytes).unwrap();
+        assert_eq!(a.to_vec(), vec![1., -2., 3., 4.]);
+
+        let half = super::Dtype::parse("<f2").unwrap();
+        assert_eq!(half.decode(&[0x00, 0x3c]), 1.);
+        assert_eq!(half.decode(&[0x00, 0xc0]), -2.);
+        assert!(super::Dtype::parse("<c16").is_err());
+        assert!(super::parse_npy(b"not npy").is_err());
+    }
+
+    #[test]
+    fn zip_test() {
+        assert_eq!(super::crc32(b"123456789"), 0xcbf43926);
+        let entries = vec![
+            ("a.npy".to_string(), b"hello".to_vec()),
+            ("b.npy".to_string(), vec![]),
+        ];
+        let zip = super::zip_stored(&entries).unwrap();
+        let back = super::unzip_stored(&zip).unwrap();
+        assert_eq!(back.len(), 2);
+        assert_eq!(back[0], ("a.npy".to_string(), &b"hello"[..]));
+        assert_eq!(back[1].1.len(), 0);
+
+        let mut bad = zip.clone();
+        bad[30 + 5] ^= 1;
+        assert!(super::unzip_stored(&bad).is_err());
+    }
+
+    #[test]
+    fn memmap_test() {
+        use crate::array::Array;
+        let path = std::env::temp_dir().join(format!("numpar_memmap_{}.npy", std::process::id()));
+        let path = path.to_str().unwrap();
+        let a = Array::from_vec((0..6).map(|i| i as f64).collect(), vec![2, 3]);
+        super::rust_save(path, &a).unwrap();
+
+        let m = super::rust_load_mapped(path, "r+").unwrap();
+        assert_eq!(m.to_vec(), a.to_vec());
+        m.set(&[1, 2], 50.);
+        m.flush().unwrap();
+        drop(m);
+        let b = super::parse_npy(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(b.get(&[1, 2]), 50.);
+
+        let r = super::rust_load_mapped(path, "r").unwrap();
+        assert!(r.check_writable().is_err());
+        let c = super::rust_load_mapped(path, "c").unwrap();
+        c.set(&[0, 0], -1.);
+        assert_eq!(super::rust_load_mapped(path, "r").unwrap().get(&[0, 0]), 0.);
+
+        let offset = super::parse_header(&std::fs::read(path).unwrap())
+            .unwrap()
+            .data_offset;
+        let f = super::rust_memmap(path, "r", offset, Some(vec![3, 2]), true).unwrap();
+        assert_eq!(f.to_vec(), vec![0., 3., 1., 4., 2., 50.]);
+        assert!(super::rust_memmap(path, "r", offset, Some(vec![100]), false).is_err());
+
+        let w = super::rust_memmap(path, "w+", 8, Some(vec![4]), false).unwrap();
+        assert_eq!(w.to_vec(), vec![0.; 4]);
+        assert_eq!(std::fs::metadata(path).unwrap().len(), 40);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn raw_binary_test() {
+        use super::Dtype;
+        use crate::array::Array;
+        let path = std::env::temp_dir().join(format!("numpar_raw_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let a = Array::from_vec(vec![1.5, -2., 300., 7.9], vec![2, 2]);
+        for (name, expected) in [
+            ("float64", vec![1.5, -2., 300., 7.9]),
+            ("float32", vec![1.5, -2., 300., 7.9f32 as f64]),
+            ("int16", vec![1., -2., 300., 7.]),
+            ("uint8", vec![1., 0., 255., 7.]),
+            (">i4", vec![1., -2., 300., 7.]),
+        ] {
+            let dtype = Dtype::from_name(name).unwrap();
+            super::rust_tofile(path, &a.t(), dtype).unwrap();
+            let b = super::rust_fromfile(path, dtype, None, 0).unwrap();
+            let t = |v: Vec<f64>| vec![v[0], v[2], v[1], v[3]];
+            assert_eq!(b.to_vec(), t(expected), "{}", name);
+        }
+        let dtype = Dtype::from_name("int16").unwrap();
+        super::rust_tofile(path, &a, dtype).unwrap();
+        let b = super::rust_fromfile(path, dtype, Some(2), 4).unwrap();
+        assert_eq!(b.to_vec(), vec![300., 7.]);
+        assert!(super::rust_fromfile(path, dtype, Some(3), 4).is_err());
+        std::fs::remove_file(path).unwrap();
+
+        // C `long` is 64 bits on LP64 platforms, as in numpy there.
+        assert_eq!(Dtype::from_name("l"), Dtype::parse("<i8"));
+        assert_eq!(Dtype::from_name("L"), Dtype::parse("<u8"));
+        assert_eq!(Dtype::from_name("i"), Dtype::parse("<i4"));
+    }
+}
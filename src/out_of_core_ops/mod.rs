@@ -0,0 +1,360 @@
+use crate::array::{to_array, Array};
+use crate::matrix_mult::rust_flat_gemm_into;
+use crate::npy_io::rust_memmap;
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use rayon::iter::ParallelIterator;
+use rayon::slice::ParallelSliceMut;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const F64_BYTES: usize = std::mem::size_of::<f64>();
+
+/// View of rows `r0..r1` and columns `c0..c1` of a matrix.
+fn tile(a: &Array, r0: usize, r1: usize, c0: usize, c1: usize) -> Array {
+    a.slice_axis(0, r0, r1 - r0, 1)
+        .slice_axis(1, c0, c1 - c0, 1)
+}
+
+/// Writable `rows x cols` matrix: in memory if it fits the budget, otherwise
+/// mapped from an unlinked temporary file.
+fn scratch(rows: usize, cols: usize, budget: usize) -> Result<Array, String> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    if rows * cols * F64_BYTES <= budget {
+        return Ok(Array::from_vec(vec![0.; rows * cols], vec![rows, cols]));
+    }
+    let path = std::env::temp_dir().join(format!(
+        "numpar_scratch_{}_{}.bin",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let path = path.to_str().ok_or("invalid temporary directory")?;
+    let a = rust_memmap(path, "w+", 0, Some(vec![rows, cols]), false)?;
+    // The mapping keeps the data alive after the name is gone.
+    std::fs::remove_file(path).map_err(|e| e.to_string())?;
+    Ok(a)
+}
+
+/// Copies `src` into `dst` in bands of rows that fit the budget.
+fn copy_banded(src: &Array, dst: &Array, budget: usize) {
+    let (rows, cols) = (src.shape()[0], src.shape()[1]);
+    let band = (budget / (cols.max(1) * F64_BYTES)).clamp(1, rows.max(1));
+    for r0 in (0..rows).step_by(band) {
+        let r1 = (r0 + band).min(rows);
+        tile(dst, r0, r1, 0, cols).assign(&tile(src, r0, r1, 0, cols).to_vec());
+    }
+}
+
+fn check_matrix(a: &Array, name: &str) -> Result<(usize, usize), String> {
+    if a.ndim() != 2 {
+        return Err(format!("{} must be a matrix", name));
+    }
+    Ok((a.shape()[0], a.shape()[1]))
+}
+
+/// C = A * B, streaming square tiles of A, B and C so that at most
+/// `budget` bytes of them are in memory at once. Each C tile accumulates
+/// over the inner dimension with the in-memory kernel and is then written
+/// to `out`, which may be memory-mapped.
+pub fn rust_matmul_blocked(a: &Array, b: &Array, out: &Array, budget: usize) -> Result<(), String> {
+    let (m, n) = check_matrix(a, "a")?;
+    let (n2, p) = check_matrix(b, "b")?;
+    if n != n2 {
+        return Err(format!(
+            "matmul: Input operand 1 has a mismatch in its core dimension 0 (size {} is different from {})",
+            n2, n
+        ));
+    }
+    if out.shape() != [m, p] {
+        return Err(format!(
+            "Output has shape {:?}, expected {:?}",
+            out.shape(),
+            [m, p]
+        ));
+    }
+    out.check_writable()?;
+    let t = ((budget / (3 * F64_BYTES)) as f64).sqrt() as usize;
+    if t == 0 {
+        return Err("memory budget is too small".to_string());
+    }
+    for i0 in (0..m).step_by(t) {
+        let i1 = (i0 + t).min(m);
+        for j0 in (0..p).step_by(t) {
+            let j1 = (j0 + t).min(p);
+            let mut c = vec![0.; (i1 - i0) * (j1 - j0)];
+            for k0 in (0..n).step_by(t) {
+                let k1 = (k0 + t).min(n);
+                let a_t = tile(a, i0, i1, k0, k1).to_vec();
+                let b_t = tile(b, k0, k1, j0, j1).to_vec();
+                rust_flat_gemm_into(&a_t, &b_t, &mut c, k1 - k0, j1 - j0);
+            }
+            tile(out, i0, i1, j0, j1).assign(&c);
+        }
+    }
+    Ok(())
+}
+
+/// LU with partial pivoting of an in-memory `h x w` panel (h >= w), in
+/// place. Returns the pivot row chosen at each step, relative to the panel.
+fn factor_panel(panel: &mut [f64], h: usize, w: usize) -> Result<Vec<usize>, String> {
+    let mut pivots = Vec::with_capacity(w);
+    for j in 0..w {
+        let p = (j..h)
+            .max_by(|&x, &y| panel[x * w + j].abs().total_cmp(&panel[y * w + j].abs()))
+            .unwrap();
+        if panel[p * w + j] == 0. {
+            return Err("Singular matrix".to_string());
+        }
+        swap_rows(panel, w, j, p);
+        pivots.push(p);
+        let (top, rest) = panel.split_at_mut((j + 1) * w);
+        let pivot_row = &top[j * w..];
+        rest.par_chunks_mut(w).for_each(|row| {
+            row[j] /= pivot_row[j];
+            let l = row[j];
+            row[j + 1..]
+                .iter_mut()
+                .zip(&pivot_row[j + 1..])
+                .for_each(|(x, &u)| *x -= l * u);
+        });
+    }
+    Ok(pivots)
+}
+
+fn swap_rows(data: &mut [f64], cols: usize, i: usize, j: usize) {
+    if i != j {
+        for c in 0..cols {
+            data.swap(i * cols + c, j * cols + c);
+        }
+    }
+}
+
+/// Solves `L x = b` in place for the unit lower triangle of a `w x w` block
+/// stored with row length `ld`; `b` is `w x r`.
+fn unit_lower_solve(l: &[f64], ld: usize, w: usize, b: &mut [f64], r: usize) {
+    for i in 1..w {
+        let (done, rest) = b.split_at_mut(i * r);
+        for k in 0..i {
+            let l_ik = l[i * ld + k];
+            rest[..r]
+                .iter_mut()
+                .zip(&done[k * r..(k + 1) * r])
+                .for_each(|(x, &y)| *x -= l_ik * y);
+        }
+    }
+}
+
+/// Solves `U x = b` in place for the upper triangle of a `w x w` block
+/// stored with row length `ld`; `b` is `w x r`.
+fn upper_solve(u: &[f64], ld: usize, w: usize, b: &mut [f64], r: usize) {
+    for i in (0..w).rev() {
+        let (head, tail) = b.split_at_mut((i + 1) * r);
+        let row = &mut head[i * r..];
+        for k in i + 1..w {
+            let u_ik = u[i * ld + k];
+            row.iter_mut()
+                .zip(&tail[(k - i - 1) * r..(k - i) * r])
+                .for_each(|(x, &y)| *x -= u_ik * y);
+        }
+        row.iter_mut().for_each(|x| *x /= u[i * ld + i]);
+    }
+}
+
+/// Right-looking blocked LU of the square matrix `a`, in place: each panel
+/// of columns is factored in memory, then the trailing columns are streamed
+/// through in blocks of the same width. Pivots of a panel are applied to the
+/// panel and everything right of it only, so they must be replayed panel by
+/// panel when solving. Returns the panel width and the pivots.
+fn lu_blocked(a: &Array, budget: usize) -> Result<(usize, Vec<usize>), String> {
+    let n = a.shape()[0];
+    // A panel, a trailing block and the negated L21 of the panel.
+    let w = (budget / (3 * n.max(1) * F64_BYTES)).min(n);
+    if w == 0 {
+        return Err("memory budget is too small".to_string());
+    }
+    let mut pivots = Vec::with_capacity(n);
+    for k0 in (0..n).step_by(w) {
+        let k1 = (k0 + w).min(n);
+        let (h, pw) = (n - k0, k1 - k0);
+        let mut panel = tile(a, k0, n, k0, k1).to_vec();
+        let piv = factor_panel(&mut panel, h, pw)?;
+        tile(a, k0, n, k0, k1).assign(&panel);
+        let neg_l21 = panel[pw * pw..].iter().map(|v| -v).collect::<Vec<_>>();
+        for j0 in (k1..n).step_by(w) {
+            let j1 = (j0 + w).min(n);
+            let bw = j1 - j0;
+            let mut block = tile(a, k0, n, j0, j1).to_vec();
+            piv.iter()
+                .enumerate()
+                .for_each(|(i, &p)| swap_rows(&mut block, bw, i, p));
+            let (top, bottom) = block.split_at_mut(pw * bw);
+            unit_lower_solve(&panel, pw, pw, top, bw);
+            rust_flat_gemm_into(&neg_l21, top, bottom, pw, bw);
+            tile(a, k0, n, j0, j1).assign(&block);
+        }
+        pivots.extend(piv.into_iter().map(|p| p + k0));
+    }
+    Ok((w, pivots))
+}
+
+/// Solves `A X = B` for a square `A` that need not fit in memory, using
+/// blocked LU within `budget` bytes. `A` is factored in place if
+/// `overwrite_a` and it is writable, otherwise in a scratch copy (itself
+/// on disk if `A` is larger than the budget). `B` (n or n x r) is kept in
+/// memory.
+pub fn rust_solve_blocked(
+    a: &Array,
+    b: &Array,
+    budget: usize,
+    overwrite_a: bool,
+) -> Result<Array, String> {
+    let (n, n2) = check_matrix(a, "a")?;
+    if n != n2 {
+        return Err("Last 2 dimensions of the array must be square".to_string());
+    }
+    if b.ndim() == 0 || b.ndim() > 2 || b.shape()[0] != n {
+        return Err(format!(
+            "b must have shape ({},) or ({}, k), got {:?}",
+            n,
+            n,
+            b.shape()
+        ));
+    }
+    let r = if b.ndim() == 2 { b.shape()[1] } else { 1 };
+    let lu = if overwrite_a && a.is_writable() {
+        a.clone()
+    } else {
+        let s = scratch(n, n, budget)?;
+        copy_banded(a, &s, budget);
+        s
+    };
+    let (w, pivots) = lu_blocked(&lu, budget)?;
+
+    let mut x = b.to_vec();
+    for k0 in (0..n).step_by(w) {
+        let k1 = (k0 + w).min(n);
+        let pw = k1 - k0;
+        for (i, &p) in pivots[k0..k1].iter().enumerate() {
+            swap_rows(&mut x, r, k0 + i, p);
+        }
+        let panel = tile(&lu, k0, n, k0, k1).to_vec();
+        let (top, bottom) = x[k0 * r..].split_at_mut(pw * r);
+        unit_lower_solve(&panel, pw, pw, top, r);
+        let neg_l21 = panel[pw * pw..].iter().map(|v| -v).collect::<Vec<_>>();
+        rust_flat_gemm_into(&neg_l21, top, bottom, pw, r);
+    }
+    let panels = (0..n).step_by(w).collect::<Vec<_>>();
+    for &k0 in panels.iter().rev() {
+        let k1 = (k0 + w).min(n);
+        let pw = k1 - k0;
+        let panel = tile(&lu, 0, k1, k0, k1).to_vec();
+        let (above, rest) = x.split_at_mut(k0 * r);
+        let block = &mut rest[..pw * r];
+        upper_solve(&panel[k0 * pw..], pw, pw, block, r);
+        let neg_u01 = panel[..k0 * pw].iter().map(|v| -v).collect::<Vec<_>>();
+        rust_flat_gemm_into(&neg_u01, block, above, pw, r);
+    }
+    Ok(Array::from_vec(x, b.shape().to_vec()))
+}
+
+#[pyfunction(memory_budget = "1 << 30")]
+pub fn matmul_blocked(
+    py: Python,
+    a: &PyAny,
+    b: &PyAny,
+    out: Option<&PyCell<Array>>,
+    memory_budget: usize,
+) -> PyResult<PyObject> {
+    let (a, b) = (to_array(a)?, to_array(b)?);
+    match out {
+        Some(cell) => {
+            rust_matmul_blocked(&a, &b, &cell.borrow(), memory_budget)
+                .map_err(PyTypeError::new_err)?;
+            Ok(cell.into_py(py))
+        }
+        None => {
+            let m = a.shape().first().copied().unwrap_or(0);
+            let p = b.shape().last().copied().unwrap_or(0);
+            // Spills to a mapped temporary file when it exceeds the budget.
+            let out = scratch(m, p, memory_budget).map_err(PyTypeError::new_err)?;
+            rust_matmul_blocked(&a, &b, &out, memory_budget).map_err(PyTypeError::new_err)?;
+            Ok(out.into_py(py))
+        }
+    }
+}
+
+#[pyfunction(memory_budget = "1 << 30", overwrite_a = "false")]
+pub fn solve_blocked(
+    a: &PyAny,
+    b: &PyAny,
+    memory_budget: usize,
+    overwrite_a: bool,
+) -> PyResult<Array> {
+    rust_solve_blocked(&to_array(a)?, &to_array(b)?, memory_budget, overwrite_a)
+        .map_err(PyTypeError::new_err)
+}
+
+mod test {
+    #[allow(dead_code)]
+    fn random_matrix(rows: usize, cols: usize, seed: u64) -> crate::array::Array {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let data = (0..rows * cols).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        crate::array::Array::from_vec(data, vec![rows, cols])
+    }
+
+    #[test]
+    fn matmul_blocked_test() {
+        use crate::array::Array;
+        use crate::matrix_mult::rust_array_matmul;
+        let a = random_matrix(37, 23, 1);
+        let b = random_matrix(23, 41, 2);
+        let expected = rust_array_matmul(&a, &b).unwrap().to_vec();
+        // Tiles of 5x5, so every edge case of a partial tile shows up.
+        let out = Array::from_vec(vec![0.; 37 * 41], vec![37, 41]);
+        super::rust_matmul_blocked(&a, &b, &out, 3 * 25 * 8).unwrap();
+        out.to_vec()
+            .iter()
+            .zip(&expected)
+            .for_each(|(x, y)| assert!((x - y).abs() < 1e-12));
+        // Transposed operands are read through their views.
+        let out_t = Array::from_vec(vec![0.; 41 * 37], vec![41, 37]);
+        super::rust_matmul_blocked(&b.t(), &a.t(), &out_t, 1 << 20).unwrap();
+        assert!((out_t.get(&[3, 5]) - out.get(&[5, 3])).abs() < 1e-12);
+        assert!(super::rust_matmul_blocked(&a, &b, &out, 8).is_err());
+
+        // An output larger than the budget lives in a temporary file.
+        let budget = 3 * 25 * 8;
+        let mapped = super::scratch(37, 41, budget).unwrap();
+        super::rust_matmul_blocked(&a, &b, &mapped, budget).unwrap();
+        assert_eq!(mapped.to_vec(), out.to_vec());
+    }
+
+    #[test]
+    fn solve_blocked_test() {
+        use crate::array::Array;
+        use crate::matrix_mult::rust_array_matmul;
+        let n = 30;
+        let a = random_matrix(n, n, 3);
+        let x = random_matrix(n, 2, 4);
+        let b = rust_array_matmul(&a, &x).unwrap();
+        // A budget of 4 columns per panel, and too small to hold A, so the
+        // scratch copy goes to a temporary file.
+        let budget = 3 * n * 4 * 8;
+        let got = super::rust_solve_blocked(&a, &b, budget, false).unwrap();
+        assert_eq!(got.shape(), &[n, 2]);
+        got.to_vec()
+            .iter()
+            .zip(x.to_vec())
+            .for_each(|(g, e)| assert!((g - e).abs() < 1e-9));
+
+        let a0 = a.to_vec();
+        let v = b.slice_axis(1, 0, 1, 1).to_contiguous().reshaped(vec![n]);
+        let got = super::rust_solve_blocked(&a, &v, 1 << 20, false).unwrap();
+        assert_eq!(a.to_vec(), a0);
+        assert!((got.get(&[7]) - x.get(&[7, 0])).abs() < 1e-9);
+
+        let singular = Array::from_vec(vec![1., 2., 2., 4.], vec![2, 2]);
+        let rhs = Array::from_vec(vec![1., 1.], vec![2]);
+        assert!(super::rust_solve_blocked(&singular, &rhs, 1 << 20, false).is_err());
+    }
+}
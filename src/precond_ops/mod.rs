@@ -0,0 +1,661 @@
+use crate::array::{to_array, Array};
+use crate::cwslice::UnsafeSlice;
+use crate::linear_operator::dense_matvec;
+use crate::sparse_ops::{as_sparse, Csr};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    IntoParallelRefMutIterator, ParallelIterator,
+};
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
+use std::collections::BTreeSet;
+
+/// Levels with fewer rows than this are solved on the calling thread.
+const MIN_PAR_LEVEL: usize = 64;
+
+/// A sparse triangular matrix kept as its strict part and diagonal, with
+/// rows grouped into levels: a row only depends on rows of earlier levels,
+/// so the rows of one level are solved in parallel.
+#[derive(Clone, Debug)]
+pub struct Triangular {
+    strict: Csr,
+    diag: Vec<f64>,
+    levels: Vec<Vec<usize>>,
+}
+
+impl Triangular {
+    pub fn new(strict: Csr, diag: Vec<f64>, lower: bool) -> Triangular {
+        let n = strict.shape.0;
+        let mut level = vec![0; n];
+        let order: Box<dyn Iterator<Item = usize>> = if lower {
+            Box::new(0..n)
+        } else {
+            Box::new((0..n).rev())
+        };
+        for i in order {
+            level[i] = strict
+                .row(i)
+                .0
+                .iter()
+                .map(|&j| level[j] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+        let mut levels = vec![vec![]; level.iter().max().map_or(0, |l| l + 1)];
+        (0..n).for_each(|i| levels[level[i]].push(i));
+        Triangular {
+            strict,
+            diag,
+            levels,
+        }
+    }
+
+    pub fn nlevels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// `x` with `T x = b`, level by level.
+    pub fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let mut x = vec![0.; b.len()];
+        let out = UnsafeSlice::new(&mut x);
+        let solve_row = |&i: &usize| {
+            let (cols, vals) = self.strict.row(i);
+            // SAFETY: entries read here belong to earlier levels, which are
+            // complete, and each row of a level writes only its own entry.
+            let s = cols
+                .iter()
+                .zip(vals)
+                .map(|(&j, &v)| v * unsafe { out.read(j) })
+                .sum::<f64>();
+            unsafe { out.write(i, (b[i] - s) / self.diag[i]) };
+        };
+        for level in &self.levels {
+            if level.len() < MIN_PAR_LEVEL {
+                level.iter().for_each(solve_row);
+            } else {
+                level.par_iter().for_each(solve_row);
+            }
+        }
+        x
+    }
+}
+
+/// Strictly lower part, diagonal and strictly upper part of a square matrix.
+fn split_triangles(a: &Csr) -> (Csr, Vec<f64>, Csr) {
+    let parts = (0..a.shape.0)
+        .into_par_iter()
+        .map(|i| {
+            let (cols, vals) = a.row(i);
+            let (mut lower, mut upper): ((Vec<_>, Vec<_>), (Vec<_>, Vec<_>)) = Default::default();
+            let mut d = 0.;
+            for (&j, &v) in cols.iter().zip(vals) {
+                match j.cmp(&i) {
+                    std::cmp::Ordering::Less => {
+                        lower.0.push(j);
+                        lower.1.push(v);
+                    }
+                    std::cmp::Ordering::Equal => d += v,
+                    std::cmp::Ordering::Greater => {
+                        upper.0.push(j);
+                        upper.1.push(v);
+                    }
+                }
+            }
+            (lower, d, upper)
+        })
+        .collect::<Vec<_>>();
+    let mut lower = Vec::with_capacity(parts.len());
+    let mut upper = Vec::with_capacity(parts.len());
+    let mut diag = Vec::with_capacity(parts.len());
+    for (l, d, u) in parts {
+        lower.push(l);
+        diag.push(d);
+        upper.push(u);
+    }
+    (
+        Csr::from_rows(a.shape, lower),
+        diag,
+        Csr::from_rows(a.shape, upper),
+    )
+}
+
+fn check_square(a: &Csr) -> Result<usize, String> {
+    if a.shape.0 != a.shape.1 {
+        return Err(format!(
+            "preconditioners need a square matrix, got {:?}",
+            a.shape
+        ));
+    }
+    Ok(a.shape.0)
+}
+
+/// Inverse of a dense `n x n` block by Gauss-Jordan with partial pivoting.
+fn invert_block(mut a: Vec<f64>, n: usize) -> Result<Vec<f64>, String> {
+    let mut inv = vec![0.; n * n];
+    (0..n).for_each(|i| inv[i * n + i] = 1.);
+    for k in 0..n {
+        let p = (k..n)
+            .max_by(|&i, &j| a[i * n + k].abs().total_cmp(&a[j * n + k].abs()))
+            .unwrap();
+        if a[p * n + k] == 0. {
+            return Err("block_jacobi: singular diagonal block".to_string());
+        }
+        for j in 0..n {
+            a.swap(k * n + j, p * n + j);
+            inv.swap(k * n + j, p * n + j);
+        }
+        let pivot = a[k * n + k];
+        for j in 0..n {
+            a[k * n + j] /= pivot;
+            inv[k * n + j] /= pivot;
+        }
+        for i in (0..n).filter(|&i| i != k) {
+            let f = a[i * n + k];
+            for j in 0..n {
+                a[i * n + j] -= f * a[k * n + j];
+                inv[i * n + j] -= f * inv[k * n + j];
+            }
+        }
+    }
+    Ok(inv)
+}
+
+/// An approximation of `A^-1` that the Krylov solvers apply as `M`.
+#[derive(Clone, Debug)]
+pub enum Precond {
+    /// Reciprocals of the diagonal.
+    Jacobi(Vec<f64>),
+    /// Inverses of consecutive `size x size` diagonal blocks; the last block
+    /// may be smaller.
+    BlockJacobi {
+        size: usize,
+        inverses: Vec<Vec<f64>>,
+    },
+    /// `ω(2-ω) (D + ωU)^-1 D (D + ωL)^-1`, with `scale` = `ω(2-ω) D`.
+    Ssor {
+        lower: Triangular,
+        upper: Triangular,
+        scale: Vec<f64>,
+    },
+    /// Incomplete LU: `U^-1 L^-1` with unit lower `L`.
+    Ilu {
+        lower: Triangular,
+        upper: Triangular,
+    },
+    /// Incomplete Cholesky: `L^-T L^-1`.
+    Ic {
+        lower: Triangular,
+        upper: Triangular,
+    },
+}
+
+impl Precond {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Precond::Jacobi(_) => "jacobi",
+            Precond::BlockJacobi { .. } => "block_jacobi",
+            Precond::Ssor { .. } => "ssor",
+            Precond::Ilu { .. } => "ilu",
+            Precond::Ic { .. } => "ic",
+        }
+    }
+
+    /// Level counts of the lower and upper triangular solves, if any.
+    pub fn levels(&self) -> Option<(usize, usize)> {
+        match self {
+            Precond::Ssor { lower, upper, .. }
+            | Precond::Ilu { lower, upper }
+            | Precond::Ic { lower, upper } => Some((lower.nlevels(), upper.nlevels())),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, r: &[f64]) -> Vec<f64> {
+        match self {
+            Precond::Jacobi(d) => r.par_iter().zip(d).map(|(r, d)| r * d).collect(),
+            Precond::BlockJacobi { size, inverses } => {
+                let mut z = vec![0.; r.len()];
+                z.par_chunks_mut(*size)
+                    .zip(r.par_chunks(*size))
+                    .zip(inverses)
+                    .for_each(|((z, r), inv)| z.copy_from_slice(&dense_matvec(inv, r.len(), r)));
+                z
+            }
+            Precond::Ssor {
+                lower,
+                upper,
+                scale,
+            } => {
+                let mut y = lower.solve(r);
+                y.par_iter_mut().zip(scale).for_each(|(y, s)| *y *= s);
+                upper.solve(&y)
+            }
+            Precond::Ilu { lower, upper } | Precond::Ic { lower, upper } => {
+                upper.solve(&lower.solve(r))
+            }
+        }
+    }
+}
+
+pub fn rust_jacobi(a: &Csr) -> Result<Precond, String> {
+    check_square(a)?;
+    let (_, diag, _) = split_triangles(a);
+    if let Some(i) = diag.iter().position(|&d| d == 0.) {
+        return Err(format!("jacobi: zero on the diagonal at row {}", i));
+    }
+    Ok(Precond::Jacobi(diag.iter().map(|d| 1. / d).collect()))
+}
+
+pub fn rust_block_jacobi(a: &Csr, size: usize) -> Result<Precond, String> {
+    let n = check_square(a)?;
+    if size == 0 {
+        return Err("block_jacobi: block_size must be positive".to_string());
+    }
+    let inverses = (0..n.div_ceil(size))
+        .into_par_iter()
+        .map(|b| {
+            let (start, end) = (b * size, ((b + 1) * size).min(n));
+            let m = end - start;
+            let mut block = vec![0.; m * m];
+            for i in start..end {
+                let (cols, vals) = a.row(i);
+                for (&j, &v) in cols.iter().zip(vals) {
+                    if (start..end).contains(&j) {
+                        block[(i - start) * m + j - start] += v;
+                    }
+                }
+            }
+            invert_block(block, m)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(Precond::BlockJacobi { size, inverses })
+}
+
+pub fn rust_ssor(a: &Csr, omega: f64) -> Result<Precond, String> {
+    check_square(a)?;
+    if !(omega > 0. && omega < 2.) {
+        return Err(format!("ssor: omega must be in (0, 2), got {}", omega));
+    }
+    let (lower, diag, upper) = split_triangles(a);
+    if let Some(i) = diag.iter().position(|&d| d == 0.) {
+        return Err(format!("ssor: zero on the diagonal at row {}", i));
+    }
+    let scale = diag.iter().map(|d| omega * (2. - omega) * d).collect();
+    Ok(Precond::Ssor {
+        lower: Triangular::new(lower.map(|v| omega * v), diag.clone(), true),
+        upper: Triangular::new(upper.map(|v| omega * v), diag, false),
+        scale,
+    })
+}
+
+/// ILU(0): Gaussian elimination restricted to the pattern of `a`.
+pub fn rust_ilu0(a: &Csr) -> Result<Precond, String> {
+    let n = check_square(a)?;
+    let mut lu = a.canonical();
+    let diag = (0..n)
+        .map(|i| {
+            let cols = lu.row(i).0;
+            cols.binary_search(&i)
+                .map(|p| lu.indptr[i] + p)
+                .map_err(|_| format!("ilu0: no diagonal entry in row {}", i))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    // pos[j] is where column j sits in the row being eliminated.
+    let mut pos = vec![usize::MAX; n];
+    for i in 0..n {
+        let row = lu.indptr[i]..lu.indptr[i + 1];
+        row.clone().for_each(|p| pos[lu.indices[p]] = p);
+        for p in lu.indptr[i]..diag[i] {
+            let k = lu.indices[p];
+            lu.data[p] /= lu.data[diag[k]];
+            let l = lu.data[p];
+            for q in diag[k] + 1..lu.indptr[k + 1] {
+                if pos[lu.indices[q]] != usize::MAX {
+                    let target = pos[lu.indices[q]];
+                    lu.data[target] -= l * lu.data[q];
+                }
+            }
+        }
+        if lu.data[diag[i]] == 0. {
+            return Err(format!("ilu0: zero pivot in row {}", i));
+        }
+        row.for_each(|p| pos[lu.indices[p]] = usize::MAX);
+    }
+    let (lower, d, upper) = split_triangles(&lu);
+    Ok(Precond::Ilu {
+        lower: Triangular::new(lower, vec![1.; n], true),
+        upper: Triangular::new(upper, d, false),
+    })
+}
+
+/// The `p` entries of largest magnitude, back in column order.
+fn keep_largest(mut entries: Vec<(usize, f64)>, p: usize) -> (Vec<usize>, Vec<f64>) {
+    if entries.len() > p {
+        entries.select_nth_unstable_by(p, |x, y| y.1.abs().total_cmp(&x.1.abs()));
+        entries.truncate(p);
+    }
+    entries.sort_unstable_by_key(|e| e.0);
+    entries.into_iter().unzip()
+}
+
+/// ILUT (Saad): row-wise elimination dropping entries below `drop_tol`
+/// times the row's norm, and keeping at most `fill_factor` times the row's
+/// nonzeros in each of `L` and `U`.
+pub fn rust_ilut(a: &Csr, drop_tol: f64, fill_factor: f64) -> Result<Precond, String> {
+    let n = check_square(a)?;
+    let a = a.canonical();
+    let mut w = vec![0.; n];
+    let mut touched = vec![false; n];
+    let mut lower_rows = Vec::with_capacity(n);
+    let mut upper_rows: Vec<(Vec<usize>, Vec<f64>)> = Vec::with_capacity(n);
+    let mut diag: Vec<f64> = Vec::with_capacity(n);
+    for i in 0..n {
+        let (cols, vals) = a.row(i);
+        let tau = drop_tol * vals.iter().map(|v| v * v).sum::<f64>().sqrt();
+        let p = ((fill_factor * cols.len() as f64).ceil() as usize).max(1);
+        let mut nz = cols.to_vec();
+        let mut pending = BTreeSet::new();
+        for (&j, &v) in cols.iter().zip(vals) {
+            w[j] = v;
+            touched[j] = true;
+            if j < i {
+                pending.insert(j);
+            }
+        }
+        while let Some(k) = pending.pop_first() {
+            let l = w[k] / diag[k];
+            if l.abs() < tau {
+                w[k] = 0.;
+                continue;
+            }
+            w[k] = l;
+            let (u_cols, u_vals) = &upper_rows[k];
+            for (&j, &u) in u_cols.iter().zip(u_vals) {
+                if !touched[j] {
+                    touched[j] = true;
+                    nz.push(j);
+                    if j < i {
+                        pending.insert(j);
+                    }
+                }
+                w[j] -= l * u;
+            }
+        }
+        let pick = |range: &dyn Fn(usize) -> bool| {
+            nz.iter()
+                .filter(|&&j| range(j) && w[j] != 0. && w[j].abs() >= tau)
+                .map(|&j| (j, w[j]))
+                .collect::<Vec<_>>()
+        };
+        lower_rows.push(keep_largest(pick(&|j| j < i), p));
+        upper_rows.push(keep_largest(pick(&|j| j > i), p));
+        if w[i] == 0. {
+            return Err(format!("ilut: zero pivot in row {}", i));
+        }
+        diag.push(w[i]);
+        for &j in &nz {
+            w[j] = 0.;
+            touched[j] = false;
+        }
+    }
+    Ok(Precond::Ilu {
+        lower: Triangular::new(Csr::from_rows((n, n), lower_rows), vec![1.; n], true),
+        upper: Triangular::new(Csr::from_rows((n, n), upper_rows), diag, false),
+    })
+}
+
+/// `sum_k x_k y_k` over columns `k < end` of two sorted sparse rows.
+fn sparse_dot(x: (&[usize], &[f64]), y: (&[usize], &[f64]), end: usize) -> f64 {
+    let (mut p, mut q, mut s) = (0, 0, 0.);
+    while p < x.0.len() && q < y.0.len() && x.0[p] < end && y.0[q] < end {
+        match x.0[p].cmp(&y.0[q]) {
+            std::cmp::Ordering::Less => p += 1,
+            std::cmp::Ordering::Greater => q += 1,
+            std::cmp::Ordering::Equal => {
+                s += x.1[p] * y.1[q];
+                p += 1;
+                q += 1;
+            }
+        }
+    }
+    s
+}
+
+/// IC(0): Cholesky restricted to the lower triangle's pattern, for
+/// symmetric positive definite `a` (only its lower triangle is read).
+pub fn rust_ic0(a: &Csr) -> Result<Precond, String> {
+    let n = check_square(a)?;
+    let a = a.canonical();
+    let mut rows: Vec<(Vec<usize>, Vec<f64>)> = Vec::with_capacity(n);
+    let mut diag: Vec<f64> = Vec::with_capacity(n);
+    for i in 0..n {
+        let (cols, vals) = a.row(i);
+        let mut row: (Vec<usize>, Vec<f64>) = (vec![], vec![]);
+        let mut pivot = None;
+        for (&j, &v) in cols.iter().zip(vals).take_while(|e| *e.0 <= i) {
+            let other = if j < i { &rows[j] } else { &row };
+            let s = v - sparse_dot((&row.0[..], &row.1[..]), (&other.0[..], &other.1[..]), j);
+            if j < i {
+                row.0.push(j);
+                row.1.push(s / diag[j]);
+            } else if s > 0. {
+                pivot = Some(s.sqrt());
+            } else {
+                return Err(format!(
+                    "ic0: nonpositive pivot in row {}; the matrix is not positive definite",
+                    i
+                ));
+            }
+        }
+        diag.push(pivot.ok_or_else(|| format!("ic0: no diagonal entry in row {}", i))?);
+        rows.push(row);
+    }
+    let strict = Csr::from_rows((n, n), rows);
+    Ok(Precond::Ic {
+        upper: Triangular::new(strict.transpose(), diag.clone(), false),
+        lower: Triangular::new(strict, diag, true),
+    })
+}
+
+#[pyclass(name = "Preconditioner")]
+#[derive(Clone)]
+pub struct Preconditioner {
+    pub inner: Precond,
+    pub n: usize,
+}
+
+/// CSR form of a sparse or dense matrix argument.
+fn extract_csr(a: &PyAny) -> PyResult<Csr> {
+    match as_sparse(a) {
+        Some(s) => Ok(s.inner.csr().into_owned()),
+        None => Csr::from_dense(&to_array(a)?).map_err(PyTypeError::new_err),
+    }
+}
+
+fn build(a: &PyAny, f: impl Fn(&Csr) -> Result<Precond, String>) -> PyResult<Preconditioner> {
+    let a = extract_csr(a)?;
+    let inner = f(&a).map_err(PyTypeError::new_err)?;
+    Ok(Preconditioner {
+        inner,
+        n: a.shape.0,
+    })
+}
+
+#[pyfunction]
+pub fn jacobi(a: &PyAny) -> PyResult<Preconditioner> {
+    build(a, rust_jacobi)
+}
+
+#[pyfunction(block_size = "4")]
+pub fn block_jacobi(a: &PyAny, block_size: usize) -> PyResult<Preconditioner> {
+    build(a, |a| rust_block_jacobi(a, block_size))
+}
+
+#[pyfunction(omega = "1.")]
+pub fn ssor(a: &PyAny, omega: f64) -> PyResult<Preconditioner> {
+    build(a, |a| rust_ssor(a, omega))
+}
+
+#[pyfunction]
+pub fn ilu0(a: &PyAny) -> PyResult<Preconditioner> {
+    build(a, rust_ilu0)
+}
+
+#[pyfunction(drop_tol = "1e-4", fill_factor = "10.")]
+pub fn ilut(a: &PyAny, drop_tol: f64, fill_factor: f64) -> PyResult<Preconditioner> {
+    build(a, |a| rust_ilut(a, drop_tol, fill_factor))
+}
+
+#[pyfunction]
+pub fn ic0(a: &PyAny) -> PyResult<Preconditioner> {
+    build(a, rust_ic0)
+}
+
+#[pymethods]
+impl Preconditioner {
+    #[getter]
+    fn shape(&self, py: Python) -> PyObject {
+        PyTuple::new(py, [self.n, self.n]).into_py(py)
+    }
+
+    #[getter]
+    fn kind(&self) -> &'static str {
+        self.inner.kind()
+    }
+
+    /// `(lower, upper)` level counts of the triangular solves.
+    #[getter]
+    fn levels(&self) -> Option<(usize, usize)> {
+        self.inner.levels()
+    }
+
+    fn matvec(&self, x: &PyAny) -> PyResult<Array> {
+        let x = to_array(x)?.to_vec();
+        if x.len() != self.n {
+            return Err(PyTypeError::new_err(format!(
+                "expected a vector of length {}, got {}",
+                self.n,
+                x.len()
+            )));
+        }
+        Ok(Array::from_vec(self.inner.apply(&x), vec![self.n]))
+    }
+
+    fn __matmul__(&self, x: &PyAny) -> PyResult<Array> {
+        self.matvec(x)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<{}x{} {} preconditioner>",
+            self.n,
+            self.n,
+            self.inner.kind()
+        )
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::sparse_ops::test::tridiagonal;
+
+    #[allow(dead_code)]
+    fn assert_close(x: &[f64], y: &[f64], tol: f64) {
+        assert_eq!(x.len(), y.len());
+        x.iter()
+            .zip(y)
+            .for_each(|(x, y)| assert!((x - y).abs() <= tol, "{} vs {}", x, y));
+    }
+
+    #[test]
+    fn triangular_test() {
+        use super::{split_triangles, Triangular};
+        use crate::sparse_ops::{Csr, Semiring};
+        // Rows 0..3 are independent, 4 needs 0 and 2, 5 needs 4.
+        let mut dense = vec![0.; 36];
+        (0..6).for_each(|i| dense[i * 6 + i] = 2.);
+        dense[4 * 6] = 1.;
+        dense[4 * 6 + 2] = -1.;
+        dense[5 * 6 + 4] = 3.;
+        let a = Csr::from_dense(&crate::array::Array::from_vec(dense, vec![6, 6])).unwrap();
+        let (lower, diag, upper) = split_triangles(&a);
+        assert_eq!(upper.nnz(), 0);
+        let t = Triangular::new(lower, diag, true);
+        assert_eq!(t.levels, vec![vec![0, 1, 2, 3], vec![4], vec![5]]);
+        let x = vec![1., 2., 3., 4., 5., 6.];
+        let b = a.matvec(Semiring::PLUS_TIMES, &x);
+        assert_close(&t.solve(&b), &x, 1e-12);
+
+        let (_, diag, upper) = split_triangles(&a.transpose());
+        let t = Triangular::new(upper, diag, false);
+        assert_eq!(t.nlevels(), 3);
+        let b = a.rmatvec(Semiring::PLUS_TIMES, &x);
+        assert_close(&t.solve(&b), &x, 1e-12);
+    }
+
+    #[test]
+    fn incomplete_factor_test() {
+        use super::{rust_ic0, rust_ilu0, rust_ilut};
+        use crate::sparse_ops::{Csr, Semiring};
+        // Tridiagonal matrices have no fill, so the incomplete factors are
+        // exact and applying them solves the system.
+        let a = tridiagonal(20, 3.);
+        let x = (0..20).map(|i| i as f64 - 7.).collect::<Vec<_>>();
+        let b = a.matvec(Semiring::PLUS_TIMES, &x);
+        for m in [
+            rust_ilu0(&a).unwrap(),
+            rust_ic0(&a).unwrap(),
+            rust_ilut(&a, 0., 10.).unwrap(),
+        ] {
+            assert_close(&m.apply(&b), &x, 1e-10);
+        }
+
+        // A dense matrix: ILUT without dropping is a full LU.
+        let dense =
+            crate::array::Array::from_vec(vec![4., 1., 2., 1., 5., 1., 2., 1., 6.], vec![3, 3]);
+        let a = Csr::from_dense(&dense).unwrap();
+        let b = a.matvec(Semiring::PLUS_TIMES, &[1., 2., 3.]);
+        assert_close(
+            &rust_ilut(&a, 0., 10.).unwrap().apply(&b),
+            &[1., 2., 3.],
+            1e-12,
+        );
+        assert_close(&rust_ic0(&a).unwrap().apply(&b), &[1., 2., 3.], 1e-12);
+
+        assert!(rust_ic0(&tridiagonal(5, -2.)).is_err());
+        let hole = Csr::new((2, 2), vec![0, 1, 2], vec![1, 0], vec![1., 1.]).unwrap();
+        assert!(rust_ilu0(&hole).is_err());
+    }
+
+    #[test]
+    fn preconditioned_cg_test() {
+        use super::{rust_block_jacobi, rust_ic0, rust_jacobi, rust_ssor, Preconditioner};
+        use crate::krylov_ops::{rust_cg, KrylovOptions};
+        let n = 200;
+        let mut a = tridiagonal(n, 2.);
+        // Vary the diagonal so plain Jacobi scaling already helps.
+        for i in 0..n {
+            let p = a.indptr[i] + a.row(i).0.iter().position(|&j| j == i).unwrap();
+            a.data[p] += (i % 10) as f64 * 10.;
+        }
+        let b = vec![1.; n];
+        let mut o = KrylovOptions::new(n);
+        o.tol = 1e-10;
+        let plain = rust_cg(&a, None, &b, None, &o).unwrap().residuals.len();
+        for m in [
+            rust_jacobi(&a).unwrap(),
+            rust_block_jacobi(&a, 7).unwrap(),
+            rust_ssor(&a, 1.2).unwrap(),
+            rust_ic0(&a).unwrap(),
+        ] {
+            let m = Preconditioner { inner: m, n };
+            let res = rust_cg(&a, Some(&m), &b, None, &o).unwrap();
+            assert_eq!(res.info, 0, "{}", m.inner.kind());
+            assert!(res.residuals.len() < plain, "{}", m.inner.kind());
+        }
+        assert!(rust_ssor(&a, 2.).is_err());
+        assert!(rust_block_jacobi(&a, 0).is_err());
+    }
+}
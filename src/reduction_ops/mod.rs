@@ -0,0 +1,323 @@
+use crate::array::{extract_axes, normalize_axis, to_array, Array};
+use crate::elementwise_ops::BinOp;
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::slice::ParallelSlice;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Reduction {
+    Sum,
+    Prod,
+    Mean,
+    Var(f64),
+    Std(f64),
+    Min,
+    Max,
+    ArgMin,
+    ArgMax,
+}
+
+const PAIRWISE_BLOCK: usize = 128;
+const PAR_SUM_MIN: usize = 1 << 16;
+
+/// Pairwise summation of `f(x)` over `xs`, as numpy does for `np.sum`: the
+/// error grows with O(log n) instead of O(n). Halves above `PAR_SUM_MIN`
+/// elements are summed on separate threads.
+pub fn pairwise_sum_map<F>(xs: &[f64], f: &F) -> f64
+where
+    F: Fn(f64) -> f64 + Sync,
+{
+    if xs.len() <= PAIRWISE_BLOCK {
+        let mut acc = [0.; 8];
+        let mut chunks = xs.chunks_exact(8);
+        for chunk in &mut chunks {
+            for (a, &x) in acc.iter_mut().zip(chunk) {
+                *a += f(x);
+            }
+        }
+        let tail = chunks.remainder().iter().map(|&x| f(x)).sum::<f64>();
+        ((acc[0] + acc[1]) + (acc[2] + acc[3])) + ((acc[4] + acc[5]) + (acc[6] + acc[7])) + tail
+    } else {
+        let mid = xs.len() / 2 / 8 * 8;
+        let (l, r) = xs.split_at(mid);
+        if xs.len() >= PAR_SUM_MIN {
+            let (l, r) = rayon::join(|| pairwise_sum_map(l, f), || pairwise_sum_map(r, f));
+            l + r
+        } else {
+            pairwise_sum_map(l, f) + pairwise_sum_map(r, f)
+        }
+    }
+}
+
+pub fn pairwise_sum(xs: &[f64]) -> f64 {
+    pairwise_sum_map(xs, &|x| x)
+}
+
+// NaN wins, then the larger/smaller value, then the earlier index
+fn arg_pick(
+    better: fn(f64, f64) -> bool,
+    (i, x): (usize, f64),
+    (j, y): (usize, f64),
+) -> (usize, f64) {
+    match (x.is_nan(), y.is_nan()) {
+        (true, true) => (i.min(j), x),
+        (true, false) => (i, x),
+        (false, true) => (j, y),
+        _ if better(x, y) => (i, x),
+        _ if better(y, x) => (j, y),
+        _ => (i.min(j), x),
+    }
+}
+
+fn reduce_lane(op: Reduction, xs: &[f64]) -> f64 {
+    let n = xs.len() as f64;
+    match op {
+        Reduction::Sum => pairwise_sum(xs),
+        Reduction::Prod => xs.par_iter().product(),
+        Reduction::Mean => pairwise_sum(xs) / n,
+        Reduction::Var(ddof) => {
+            let mean = pairwise_sum(xs) / n;
+            let ss = pairwise_sum_map(xs, &|x| (x - mean) * (x - mean));
+            if n - ddof > 0. {
+                ss / (n - ddof)
+            } else {
+                f64::NAN
+            }
+        }
+        Reduction::Std(ddof) => reduce_lane(Reduction::Var(ddof), xs).sqrt(),
+        Reduction::Min => xs
+            .par_iter()
+            .copied()
+            .reduce_with(|x, y| BinOp::Min.apply(x, y))
+            .unwrap(),
+        Reduction::Max => xs
+            .par_iter()
+            .copied()
+            .reduce_with(|x, y| BinOp::Max.apply(x, y))
+            .unwrap(),
+        Reduction::ArgMin => {
+            let pick = |a, b| arg_pick(|x, y| x < y, a, b);
+            xs.par_iter()
+                .copied()
+                .enumerate()
+                .reduce_with(pick)
+                .unwrap()
+                .0 as f64
+        }
+        Reduction::ArgMax => {
+            let pick = |a, b| arg_pick(|x, y| x > y, a, b);
+            xs.par_iter()
+                .copied()
+                .enumerate()
+                .reduce_with(pick)
+                .unwrap()
+                .0 as f64
+        }
+    }
+}
+
+/// Reduces `a` over `axes` (all of them if `None`). The reduced axes are
+/// moved to the back so each output element owns one contiguous lane, which
+/// is then reduced on its own thread.
+pub fn rust_reduce(
+    op: Reduction,
+    a: &Array,
+    axes: Option<&[usize]>,
+    keepdims: bool,
+) -> Result<Array, String> {
+    let ndim = a.ndim();
+    let mut reduced: Vec<usize> = match axes {
+        Some(axes) => axes.to_vec(),
+        None => (0..ndim).collect(),
+    };
+    reduced.sort_unstable();
+    reduced.dedup();
+    if axes.is_some_and(|axes| axes.len() != reduced.len()) {
+        return Err("duplicate value in 'axis'".to_string());
+    }
+
+    let kept: Vec<usize> = (0..ndim).filter(|ax| !reduced.contains(ax)).collect();
+    let perm = [kept.as_slice(), reduced.as_slice()].concat();
+    let lanes = a.permute_axes(&perm)?.to_contiguous();
+    let data = lanes.as_slice().unwrap();
+    let lane_len = reduced.iter().map(|&ax| a.shape()[ax]).product::<usize>();
+    let out_len = kept.iter().map(|&ax| a.shape()[ax]).product::<usize>();
+
+    let out: Vec<f64> = if lane_len == 0 {
+        match op {
+            Reduction::Sum => vec![0.; out_len],
+            Reduction::Prod => vec![1.; out_len],
+            Reduction::Mean | Reduction::Var(_) | Reduction::Std(_) => vec![f64::NAN; out_len],
+            _ => {
+                return Err(format!(
+                    "zero-size array to reduction operation {:?} which has no identity",
+                    op
+                ))
+            }
+        }
+    } else if out_len == 1 {
+        vec![reduce_lane(op, data)]
+    } else {
+        data.par_chunks(lane_len)
+            .map(|lane| reduce_lane(op, lane))
+            .collect()
+    };
+
+    let shape = if keepdims {
+        (0..ndim)
+            .map(|ax| {
+                if reduced.contains(&ax) {
+                    1
+                } else {
+                    a.shape()[ax]
+                }
+            })
+            .collect()
+    } else {
+        kept.iter().map(|&ax| a.shape()[ax]).collect()
+    };
+    Ok(Array::from_vec(out, shape))
+}
+
+pub fn reduce_pyfunction(
+    py: Python,
+    op: Reduction,
+    a: &PyAny,
+    axes: Option<Vec<isize>>,
+    keepdims: bool,
+) -> PyResult<PyObject> {
+    let a = to_array(a)?;
+    let axes = match axes {
+        Some(axes) => Some(
+            axes.into_iter()
+                .map(|ax| normalize_axis(ax, a.ndim()))
+                .collect::<Result<Vec<usize>, String>>()
+                .map_err(PyTypeError::new_err)?,
+        ),
+        None => None,
+    };
+    let res = rust_reduce(op, &a, axes.as_deref(), keepdims).map_err(PyTypeError::new_err)?;
+    let is_arg = matches!(op, Reduction::ArgMin | Reduction::ArgMax);
+    match (res.ndim(), is_arg) {
+        (0, true) => Ok((res.get(&[]) as usize).into_py(py)),
+        (0, false) => Ok(res.get(&[]).into_py(py)),
+        _ => Ok(res.into_py(py)),
+    }
+}
+
+#[pyfunction(axis = "None", keepdims = "false")]
+pub fn sum(py: Python, a: &PyAny, axis: Option<&PyAny>, keepdims: bool) -> PyResult<PyObject> {
+    let axes = axis.map(extract_axes).transpose()?;
+    reduce_pyfunction(py, Reduction::Sum, a, axes, keepdims)
+}
+
+#[pyfunction(axis = "None", keepdims = "false")]
+pub fn prod(py: Python, a: &PyAny, axis: Option<&PyAny>, keepdims: bool) -> PyResult<PyObject> {
+    let axes = axis.map(extract_axes).transpose()?;
+    reduce_pyfunction(py, Reduction::Prod, a, axes, keepdims)
+}
+
+#[pyfunction(axis = "None", keepdims = "false")]
+pub fn mean(py: Python, a: &PyAny, axis: Option<&PyAny>, keepdims: bool) -> PyResult<PyObject> {
+    let axes = axis.map(extract_axes).transpose()?;
+    reduce_pyfunction(py, Reduction::Mean, a, axes, keepdims)
+}
+
+#[pyfunction(axis = "None", ddof = "0.0", keepdims = "false")]
+pub fn var(
+    py: Python,
+    a: &PyAny,
+    axis: Option<&PyAny>,
+    ddof: f64,
+    keepdims: bool,
+) -> PyResult<PyObject> {
+    let axes = axis.map(extract_axes).transpose()?;
+    reduce_pyfunction(py, Reduction::Var(ddof), a, axes, keepdims)
+}
+
+#[pyfunction(axis = "None", ddof = "0.0", keepdims = "false")]
+pub fn std(
+    py: Python,
+    a: &PyAny,
+    axis: Option<&PyAny>,
+    ddof: f64,
+    keepdims: bool,
+) -> PyResult<PyObject> {
+    let axes = axis.map(extract_axes).transpose()?;
+    reduce_pyfunction(py, Reduction::Std(ddof), a, axes, keepdims)
+}
+
+#[pyfunction(axis = "None", keepdims = "false")]
+pub fn min(py: Python, a: &PyAny, axis: Option<&PyAny>, keepdims: bool) -> PyResult<PyObject> {
+    let axes = axis.map(extract_axes).transpose()?;
+    reduce_pyfunction(py, Reduction::Min, a, axes, keepdims)
+}
+
+#[pyfunction(axis = "None", keepdims = "false")]
+pub fn max(py: Python, a: &PyAny, axis: Option<&PyAny>, keepdims: bool) -> PyResult<PyObject> {
+    let axes = axis.map(extract_axes).transpose()?;
+    reduce_pyfunction(py, Reduction::Max, a, axes, keepdims)
+}
+
+#[pyfunction(axis = "None", keepdims = "false")]
+pub fn argmin(py: Python, a: &PyAny, axis: Option<isize>, keepdims: bool) -> PyResult<PyObject> {
+    reduce_pyfunction(py, Reduction::ArgMin, a, axis.map(|ax| vec![ax]), keepdims)
+}
+
+#[pyfunction(axis = "None", keepdims = "false")]
+pub fn argmax(py: Python, a: &PyAny, axis: Option<isize>, keepdims: bool) -> PyResult<PyObject> {
+    reduce_pyfunction(py, Reduction::ArgMax, a, axis.map(|ax| vec![ax]), keepdims)
+}
+
+mod test {
+    #[test]
+    fn pairwise_accuracy_test() {
+        // 0.1 isn't representable, so a naive running sum drifts visibly
+        let xs = vec![0.1; 10_000_000];
+        let naive = xs.iter().fold(0., |acc, &x| acc + x);
+        let pairwise = super::pairwise_sum(&xs);
+        assert!((pairwise - 1e6).abs() < (naive - 1e6).abs());
+        assert!((pairwise - 1e6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn axis_reduce_test() {
+        use super::{rust_reduce, Reduction};
+        use crate::array::Array;
+        let a = Array::from_vec((0..24).map(|e| e as f64).collect(), vec![2, 3, 4]);
+
+        let s = rust_reduce(Reduction::Sum, &a, Some(&[1]), false).unwrap();
+        assert_eq!(s.shape(), &[2, 4]);
+        assert_eq!(&s.to_vec()[..4], &[12., 15., 18., 21.]);
+
+        let m = rust_reduce(Reduction::Max, &a, Some(&[0, 2]), true).unwrap();
+        assert_eq!(m.shape(), &[1, 3, 1]);
+        assert_eq!(m.to_vec(), vec![15., 19., 23.]);
+
+        let v = rust_reduce(Reduction::Var(1.), &a, None, false).unwrap();
+        assert_eq!(v.get(&[]), 50.);
+
+        let am = rust_reduce(Reduction::ArgMin, &a.t(), Some(&[0]), false).unwrap();
+        assert_eq!(am.to_vec(), vec![0.; 6]);
+        let empty = Array::from_vec(vec![], vec![0, 3]);
+        assert!(rust_reduce(Reduction::Min, &empty, Some(&[0]), false).is_err());
+    }
+
+    #[test]
+    fn nan_reduce_test() {
+        use super::{rust_reduce, Reduction};
+        use crate::array::Array;
+        let a = Array::from_vec(vec![3., f64::NAN, 7., f64::NAN], vec![4]);
+        assert!(rust_reduce(Reduction::Max, &a, None, false)
+            .unwrap()
+            .get(&[])
+            .is_nan());
+        assert_eq!(
+            rust_reduce(Reduction::ArgMax, &a, None, false)
+                .unwrap()
+                .get(&[]),
+            1.
+        );
+    }
+}
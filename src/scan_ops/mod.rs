@@ -0,0 +1,238 @@
+use crate::array::{normalize_axis, to_array, Array, Lanes};
+use crate::elementwise_ops::{rust_binary, BinOp};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Scan {
+    Sum,
+    Prod,
+    Max,
+    Min,
+    /// Sum with Neumaier compensation; the error stays O(1) ulps instead of
+    /// growing with the length of the lane.
+    CompensatedSum,
+}
+
+impl Scan {
+    fn identity(self) -> f64 {
+        match self {
+            Scan::Sum | Scan::CompensatedSum => 0.,
+            Scan::Prod => 1.,
+            Scan::Max => f64::NEG_INFINITY,
+            Scan::Min => f64::INFINITY,
+        }
+    }
+
+    fn op(self) -> BinOp {
+        match self {
+            Scan::Sum | Scan::CompensatedSum => BinOp::Add,
+            Scan::Prod => BinOp::Mul,
+            Scan::Max => BinOp::Max,
+            Scan::Min => BinOp::Min,
+        }
+    }
+}
+
+const SCAN_BLOCK: usize = 1 << 14;
+
+/// Adds `x` to the running sum `s` with compensation term `c`.
+fn neumaier(s: &mut f64, c: &mut f64, x: f64) {
+    let t = *s + x;
+    if t.is_finite() {
+        if s.abs() >= x.abs() {
+            *c += (*s - t) + x;
+        } else {
+            *c += (x - t) + *s;
+        }
+    }
+    *s = t;
+}
+
+/// Running state of a scan: the plain accumulator plus the compensation
+/// term, which only `CompensatedSum` uses.
+#[derive(Copy, Clone)]
+struct Carry(f64, f64);
+
+impl Carry {
+    fn push(&mut self, scan: Scan, x: f64) {
+        match scan {
+            Scan::CompensatedSum => neumaier(&mut self.0, &mut self.1, x),
+            _ => self.0 = scan.op().apply(self.0, x),
+        }
+    }
+
+    fn join(mut self, scan: Scan, other: Carry) -> Carry {
+        self.push(scan, other.0);
+        self.1 += other.1;
+        self
+    }
+
+    fn value(self) -> f64 {
+        self.0 + self.1
+    }
+}
+
+fn scan_from(scan: Scan, mut carry: Carry, lane: &mut [f64]) {
+    for x in lane.iter_mut() {
+        carry.push(scan, *x);
+        *x = carry.value();
+    }
+}
+
+/// Inclusive scan of one lane in three passes: block totals in parallel, a
+/// sequential prefix over the (few) totals, then every block rescanned in
+/// parallel from its carry. That is 2n combines, against n for the
+/// sequential scan, but spread over all threads.
+fn par_scan_lane(scan: Scan, lane: &mut [f64]) {
+    let init = Carry(scan.identity(), 0.);
+    let totals = lane
+        .par_chunks(SCAN_BLOCK)
+        .map(|block| {
+            let mut c = init;
+            block.iter().for_each(|&x| c.push(scan, x));
+            c
+        })
+        .collect::<Vec<_>>();
+    let mut carries = Vec::with_capacity(totals.len());
+    let mut acc = init;
+    for t in totals {
+        carries.push(acc);
+        acc = acc.join(scan, t);
+    }
+    lane.par_chunks_mut(SCAN_BLOCK)
+        .zip(carries)
+        .for_each(|(block, carry)| scan_from(scan, carry, block));
+}
+
+/// Inclusive scan along `axis` (the flattened array if `None`). Long single
+/// lanes use the blocked parallel scan, otherwise lanes run in parallel.
+pub fn rust_scan(scan: Scan, a: &Array, axis: Option<isize>) -> Result<Array, String> {
+    let lanes = Lanes::new(a, axis)?;
+    let n = lanes.lane_len();
+    let mut data = lanes.data.clone();
+    let init = Carry(scan.identity(), 0.);
+    if lanes.single() {
+        par_scan_lane(scan, &mut data);
+    } else if n > 0 {
+        data.par_chunks_mut(n)
+            .for_each(|lane| scan_from(scan, init, lane));
+    }
+    lanes.finish(data, n)
+}
+
+/// `n`-th discrete difference along `axis`, each pass one parallel
+/// subtraction of shifted views.
+pub fn rust_diff(a: &Array, n: usize, axis: isize) -> Result<Array, String> {
+    if a.ndim() == 0 {
+        return Err("diff requires input that is at least one dimensional".to_string());
+    }
+    let ax = normalize_axis(axis, a.ndim())?;
+    let mut out = a.clone();
+    for _ in 0..n {
+        let len = out.shape()[ax].saturating_sub(1);
+        let hi = out.slice_axis(ax, 1, len, 1);
+        let lo = out.slice_axis(ax, 0, len, 1);
+        out = rust_binary(BinOp::Sub, &hi, &lo)?;
+    }
+    Ok(out.to_contiguous())
+}
+
+#[pyfunction(compensated = "false")]
+pub fn cumsum(a: &PyAny, axis: Option<isize>, compensated: bool) -> PyResult<Array> {
+    let scan = if compensated {
+        Scan::CompensatedSum
+    } else {
+        Scan::Sum
+    };
+    rust_scan(scan, &to_array(a)?, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn cumprod(a: &PyAny, axis: Option<isize>) -> PyResult<Array> {
+    rust_scan(Scan::Prod, &to_array(a)?, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn cummax(a: &PyAny, axis: Option<isize>) -> PyResult<Array> {
+    rust_scan(Scan::Max, &to_array(a)?, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn cummin(a: &PyAny, axis: Option<isize>) -> PyResult<Array> {
+    rust_scan(Scan::Min, &to_array(a)?, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(n = "1", axis = "-1")]
+pub fn diff(a: &PyAny, n: usize, axis: isize) -> PyResult<Array> {
+    rust_diff(&to_array(a)?, n, axis).map_err(PyTypeError::new_err)
+}
+
+mod test {
+    #[test]
+    fn scan_axis_test() {
+        use super::Scan;
+        use crate::array::Array;
+        let a = Array::from_vec(vec![1., 2., 3., 4., 5., 6.], vec![2, 3]);
+        let s = super::rust_scan(Scan::Sum, &a, Some(1)).unwrap();
+        assert_eq!(s.to_vec(), vec![1., 3., 6., 4., 9., 15.]);
+        let p = super::rust_scan(Scan::Prod, &a, Some(0)).unwrap();
+        assert_eq!(p.to_vec(), vec![1., 2., 3., 4., 10., 18.]);
+        let s = super::rust_scan(Scan::Sum, &a, None).unwrap();
+        assert_eq!(s.shape(), &[6]);
+        assert_eq!(s.to_vec()[5], 21.);
+
+        let b = Array::from_vec(vec![2., 1., f64::NAN, 3.], vec![4]);
+        let m = super::rust_scan(Scan::Min, &b, Some(0)).unwrap().to_vec();
+        assert_eq!(&m[..2], &[2., 1.]);
+        assert!(m[2].is_nan() && m[3].is_nan());
+    }
+
+    #[test]
+    fn par_scan_test() {
+        use super::Scan;
+        use crate::array::Array;
+        let n = 5 * super::SCAN_BLOCK + 17;
+        let a = Array::from_vec((0..n).map(|i| (i % 7) as f64).collect(), vec![n]);
+        let s = super::rust_scan(Scan::Sum, &a, None).unwrap().to_vec();
+        let mut acc = 0.;
+        for (i, &x) in s.iter().enumerate() {
+            acc += (i % 7) as f64;
+            assert_eq!(x, acc);
+        }
+        let m = super::rust_scan(Scan::Max, &a, Some(-1)).unwrap().to_vec();
+        assert_eq!(m[n - 1], 6.);
+        assert_eq!(&m[..3], &[0., 1., 2.]);
+
+        // 0.1 is inexact, so the plain running sum drifts while the
+        // compensated one stays at the correctly rounded value.
+        let tenths = Array::from_vec(vec![0.1; n], vec![n]);
+        let plain = super::rust_scan(Scan::Sum, &tenths, None).unwrap().to_vec();
+        let comp = super::rust_scan(Scan::CompensatedSum, &tenths, None)
+            .unwrap()
+            .to_vec();
+        let exact = n as f64 / 10.;
+        assert!((comp[n - 1] - exact).abs() <= exact * f64::EPSILON);
+        assert!((plain[n - 1] - exact).abs() > (comp[n - 1] - exact).abs());
+    }
+
+    #[test]
+    fn diff_test() {
+        use crate::array::Array;
+        let a = Array::from_vec(vec![1., 4., 9., 16., 2., 2., 2., 2.], vec![2, 4]);
+        let d = super::rust_diff(&a, 1, -1).unwrap();
+        assert_eq!(d.shape(), &[2, 3]);
+        assert_eq!(d.to_vec(), vec![3., 5., 7., 0., 0., 0.]);
+        assert_eq!(
+            super::rust_diff(&a, 2, 1).unwrap().to_vec(),
+            vec![2., 2., 0., 0.]
+        );
+        assert_eq!(
+            super::rust_diff(&a, 1, 0).unwrap().to_vec(),
+            vec![1., -2., -7., -14.]
+        );
+        assert_eq!(super::rust_diff(&a, 5, 1).unwrap().shape(), &[2, 0]);
+    }
+}
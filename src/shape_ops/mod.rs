@@ -0,0 +1,433 @@
+use crate::array::{extract_axes, normalize_axis, to_array, Array};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::{pyfunction, PyAny, PyResult};
+
+/// Resolves a numpy-style shape with at most one `-1` against `size`.
+pub fn resolve_shape(shape: &[isize], size: usize) -> Result<Vec<usize>, String> {
+    let unknown = shape.iter().filter(|&&n| n == -1).count();
+    if unknown > 1 || shape.iter().any(|&n| n < -1) {
+        return Err(format!("invalid shape {:?}", shape));
+    }
+    let known = shape
+        .iter()
+        .filter(|&&n| n != -1)
+        .map(|&n| n as usize)
+        .product::<usize>();
+    let fill = if unknown == 1 && known > 0 {
+        size / known
+    } else {
+        0
+    };
+    let resolved = shape
+        .iter()
+        .map(|&n| if n == -1 { fill } else { n as usize })
+        .collect::<Vec<_>>();
+    if resolved.iter().product::<usize>() != size {
+        return Err(format!(
+            "cannot reshape array of size {} into shape {:?}",
+            size, shape
+        ));
+    }
+    Ok(resolved)
+}
+
+/// A view when `a` is contiguous, a copy otherwise.
+pub fn rust_reshape(a: &Array, shape: &[isize]) -> Result<Array, String> {
+    let shape = resolve_shape(shape, a.size())?;
+    Ok(a.to_contiguous().reshaped(shape))
+}
+
+pub fn rust_ravel(a: &Array) -> Array {
+    a.to_contiguous().reshaped(vec![a.size()])
+}
+
+pub fn rust_concatenate(arrays: &[Array], axis: isize) -> Result<Array, String> {
+    let first = arrays
+        .first()
+        .ok_or_else(|| "need at least one array to concatenate".to_string())?;
+    if first.ndim() == 0 {
+        return Err("zero-dimensional arrays cannot be concatenated".to_string());
+    }
+    let ax = normalize_axis(axis, first.ndim())?;
+    let mut shape = first.shape().to_vec();
+    shape[ax] = 0;
+    for a in arrays {
+        let same = a.ndim() == first.ndim()
+            && (0..a.ndim()).all(|d| d == ax || a.shape()[d] == first.shape()[d]);
+        if !same {
+            return Err(format!(
+                "all the input array dimensions except for the concatenation axis must match exactly, got {:?} and {:?}",
+                first.shape(),
+                a.shape()
+            ));
+        }
+        shape[ax] += a.shape()[ax];
+    }
+    let out = Array::from_vec(vec![0.; shape.iter().product()], shape);
+    let mut start = 0;
+    for a in arrays {
+        let n = a.shape()[ax];
+        out.slice_axis(ax, start, n, 1).assign(&a.to_vec());
+        start += n;
+    }
+    Ok(out)
+}
+
+pub fn rust_stack(arrays: &[Array], axis: isize) -> Result<Array, String> {
+    let first = arrays
+        .first()
+        .ok_or_else(|| "need at least one array to stack".to_string())?;
+    if arrays.iter().any(|a| a.shape() != first.shape()) {
+        return Err("all input arrays must have the same shape".to_string());
+    }
+    let ax = normalize_axis(axis, first.ndim() + 1)?;
+    let expanded = arrays.iter().map(|a| a.expand_dims(ax)).collect::<Vec<_>>();
+    rust_concatenate(&expanded, ax as isize)
+}
+
+pub fn rust_hstack(arrays: &[Array]) -> Result<Array, String> {
+    let arrays = arrays
+        .iter()
+        .map(|a| {
+            if a.ndim() == 0 {
+                a.expand_dims(0)
+            } else {
+                a.clone()
+            }
+        })
+        .collect::<Vec<_>>();
+    match arrays.first() {
+        Some(a) if a.ndim() == 1 => rust_concatenate(&arrays, 0),
+        _ => rust_concatenate(&arrays, 1),
+    }
+}
+
+pub fn rust_vstack(arrays: &[Array]) -> Result<Array, String> {
+    let arrays = arrays
+        .iter()
+        .map(|a| match a.ndim() {
+            0 => a.expand_dims(0).expand_dims(0),
+            1 => a.expand_dims(0),
+            _ => a.clone(),
+        })
+        .collect::<Vec<_>>();
+    rust_concatenate(&arrays, 0)
+}
+
+/// Splits `a` into views at the given indices along `axis`; indices past
+/// the end give empty pieces, as in numpy.
+pub fn rust_split_at(a: &Array, indices: &[usize], axis: isize) -> Result<Vec<Array>, String> {
+    let ax = normalize_axis(axis, a.ndim())?;
+    let n = a.shape()[ax];
+    let mut bounds = vec![0];
+    bounds.extend(indices.iter().map(|&i| i.min(n)));
+    bounds.push(n);
+    Ok(bounds
+        .windows(2)
+        .map(|w| {
+            let len = w[1].saturating_sub(w[0]);
+            a.slice_axis(ax, w[0], len, 1)
+        })
+        .collect())
+}
+
+pub fn rust_split(a: &Array, sections: usize, axis: isize) -> Result<Vec<Array>, String> {
+    let ax = normalize_axis(axis, a.ndim())?;
+    let n = a.shape()[ax];
+    if sections == 0 || !n.is_multiple_of(sections) {
+        return Err("array split does not result in an equal division".to_string());
+    }
+    let step = n / sections;
+    let indices = (1..sections).map(|i| i * step).collect::<Vec<_>>();
+    rust_split_at(a, &indices, axis)
+}
+
+pub fn rust_tile(a: &Array, reps: &[usize]) -> Result<Array, String> {
+    let d = a.ndim().max(reps.len());
+    let mut a = a.clone();
+    while a.ndim() < d {
+        a = a.expand_dims(0);
+    }
+    let reps = [vec![1; d - reps.len()], reps.to_vec()].concat();
+    // Interleave a broadcast axis of length `reps[i]` before every axis.
+    let mut wide = Vec::with_capacity(2 * d);
+    for (i, &r) in reps.iter().enumerate() {
+        a = a.expand_dims(2 * i);
+        wide.push(r);
+        wide.push(a.shape()[2 * i + 1]);
+    }
+    let shape = reps
+        .iter()
+        .zip(wide.chunks(2))
+        .map(|(r, w)| r * w[1])
+        .collect();
+    Ok(Array::from_vec(a.broadcast_to(&wide)?.to_vec(), shape))
+}
+
+/// Repeats every element along `axis` (the flattened array if `None`)
+/// either a fixed number of times or `repeats[i]` times for element `i`.
+pub fn rust_repeat(a: &Array, repeats: &[usize], axis: Option<isize>) -> Result<Array, String> {
+    let (a, ax) = match axis {
+        Some(axis) => (a.clone(), normalize_axis(axis, a.ndim())?),
+        None => (rust_ravel(a), 0),
+    };
+    let n = a.shape()[ax];
+    if let [r] = repeats {
+        let mut wide = a.shape().to_vec();
+        wide.insert(ax + 1, *r);
+        let mut shape = a.shape().to_vec();
+        shape[ax] *= r;
+        let data = a.expand_dims(ax + 1).broadcast_to(&wide)?.to_vec();
+        return Ok(Array::from_vec(data, shape));
+    }
+    if repeats.len() != n {
+        return Err(format!(
+            "operands could not be broadcast together with shape ({},) ({},)",
+            n,
+            repeats.len()
+        ));
+    }
+    let pieces = repeats
+        .iter()
+        .enumerate()
+        .map(|(i, &r)| {
+            let piece = a.slice_axis(ax, i, 1, 1);
+            let mut shape = piece.shape().to_vec();
+            shape[ax] = r;
+            piece.broadcast_to(&shape)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    rust_concatenate(&pieces, ax as isize)
+}
+
+pub enum PadMode {
+    Constant(f64),
+    Edge,
+    Reflect,
+}
+
+/// Pads every axis `i` with `widths[i].0` elements before and `widths[i].1`
+/// after.
+pub fn rust_pad(a: &Array, widths: &[(usize, usize)], mode: PadMode) -> Result<Array, String> {
+    if widths.len() != a.ndim() {
+        return Err("pad_width must have one (before, after) pair per axis".to_string());
+    }
+    if let PadMode::Constant(value) = mode {
+        let shape = a
+            .shape()
+            .iter()
+            .zip(widths)
+            .map(|(n, (b, e))| b + n + e)
+            .collect::<Vec<_>>();
+        let out = Array::from_vec(vec![value; shape.iter().product()], shape);
+        let interior = (0..a.ndim()).fold(out.clone(), |v, ax| {
+            v.slice_axis(ax, widths[ax].0, a.shape()[ax], 1)
+        });
+        interior.assign(&a.to_vec());
+        return Ok(out);
+    }
+    let mut out = a.clone();
+    for (ax, &(before, after)) in widths.iter().enumerate() {
+        let n = out.shape()[ax];
+        if before == 0 && after == 0 {
+            continue;
+        }
+        let (head, tail) = match mode {
+            PadMode::Edge if n > 0 => {
+                let mut shape = out.shape().to_vec();
+                shape[ax] = before;
+                let head = out.slice_axis(ax, 0, 1, 1).broadcast_to(&shape)?;
+                shape[ax] = after;
+                let tail = out.slice_axis(ax, n - 1, 1, 1).broadcast_to(&shape)?;
+                (head, tail)
+            }
+            PadMode::Reflect if before < n && after < n => (
+                out.slice_axis(ax, before, before, -1),
+                out.slice_axis(ax, n.saturating_sub(2), after, -1),
+            ),
+            _ => return Err("pad width too large for the array".to_string()),
+        };
+        out = rust_concatenate(&[head, out, tail], ax as isize)?;
+    }
+    Ok(out.to_contiguous())
+}
+
+fn to_arrays(arrays: Vec<&PyAny>) -> PyResult<Vec<Array>> {
+    arrays.into_iter().map(to_array).collect()
+}
+
+fn to_counts(xs: Vec<isize>) -> PyResult<Vec<usize>> {
+    xs.into_iter()
+        .map(|x| {
+            usize::try_from(x).map_err(|_| PyTypeError::new_err("Counts must be non-negative."))
+        })
+        .collect()
+}
+
+#[pyfunction]
+pub fn reshape(a: &PyAny, newshape: &PyAny) -> PyResult<Array> {
+    rust_reshape(&to_array(a)?, &extract_axes(newshape)?).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn ravel(a: &PyAny) -> PyResult<Array> {
+    Ok(rust_ravel(&to_array(a)?))
+}
+
+#[pyfunction(axis = "0")]
+pub fn concatenate(arrays: Vec<&PyAny>, axis: Option<isize>) -> PyResult<Array> {
+    let arrays = to_arrays(arrays)?;
+    match axis {
+        Some(axis) => rust_concatenate(&arrays, axis),
+        None => rust_concatenate(&arrays.iter().map(rust_ravel).collect::<Vec<_>>(), 0),
+    }
+    .map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(axis = "0")]
+pub fn stack(arrays: Vec<&PyAny>, axis: isize) -> PyResult<Array> {
+    rust_stack(&to_arrays(arrays)?, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn hstack(arrays: Vec<&PyAny>) -> PyResult<Array> {
+    rust_hstack(&to_arrays(arrays)?).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn vstack(arrays: Vec<&PyAny>) -> PyResult<Array> {
+    rust_vstack(&to_arrays(arrays)?).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(axis = "0")]
+pub fn split(a: &PyAny, indices_or_sections: &PyAny, axis: isize) -> PyResult<Vec<Array>> {
+    let a = to_array(a)?;
+    match indices_or_sections.extract::<usize>() {
+        Ok(sections) => rust_split(&a, sections, axis),
+        Err(_) => {
+            let indices = to_counts(indices_or_sections.extract::<Vec<isize>>()?)?;
+            rust_split_at(&a, &indices, axis)
+        }
+    }
+    .map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn tile(a: &PyAny, reps: &PyAny) -> PyResult<Array> {
+    let reps = to_counts(extract_axes(reps)?)?;
+    rust_tile(&to_array(a)?, &reps).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn repeat(a: &PyAny, repeats: &PyAny, axis: Option<isize>) -> PyResult<Array> {
+    let repeats = to_counts(extract_axes(repeats)?)?;
+    rust_repeat(&to_array(a)?, &repeats, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(mode = "\"constant\"", constant_values = "0.")]
+pub fn pad(a: &PyAny, pad_width: &PyAny, mode: &str, constant_values: f64) -> PyResult<Array> {
+    let a = to_array(a)?;
+    let widths = match (
+        pad_width.extract::<usize>(),
+        pad_width.extract::<Vec<usize>>(),
+        pad_width.extract::<Vec<Vec<usize>>>(),
+    ) {
+        (Ok(w), _, _) => vec![(w, w); a.ndim()],
+        (_, Ok(w), _) if w.len() == 1 => vec![(w[0], w[0]); a.ndim()],
+        (_, Ok(w), _) if w.len() == 2 => vec![(w[0], w[1]); a.ndim()],
+        (_, _, Ok(ws)) if ws.iter().all(|w| w.len() == 2) => {
+            ws.iter().map(|w| (w[0], w[1])).collect()
+        }
+        _ => {
+            return Err(PyTypeError::new_err(
+                "pad_width must be an int, a (before, after) pair or one pair per axis.",
+            ))
+        }
+    };
+    let mode = match mode {
+        "constant" => PadMode::Constant(constant_values),
+        "edge" => PadMode::Edge,
+        "reflect" => PadMode::Reflect,
+        _ => {
+            return Err(PyTypeError::new_err(
+                "Supported pad modes are 'constant', 'edge' and 'reflect'.",
+            ))
+        }
+    };
+    rust_pad(&a, &widths, mode).map_err(PyTypeError::new_err)
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::array::test::arange;
+
+    #[test]
+    fn reshape_test() {
+        let a = arange(vec![2, 3]);
+        let r = super::rust_reshape(&a, &[3, -1]).unwrap();
+        assert_eq!(r.shape(), &[3, 2]);
+        assert!(r.shares_buffer(&a));
+        let t = super::rust_reshape(&a.t(), &[-1]).unwrap();
+        assert_eq!(t.to_vec(), vec![0., 3., 1., 4., 2., 5.]);
+        assert!(super::rust_reshape(&a, &[4, -1]).is_err());
+    }
+
+    #[test]
+    fn join_split_test() {
+        let a = arange(vec![2, 2]);
+        let b = arange(vec![2, 1]);
+        let c = super::rust_concatenate(&[a.clone(), b.clone()], 1).unwrap();
+        assert_eq!(c.shape(), &[2, 3]);
+        assert_eq!(c.to_vec(), vec![0., 1., 0., 2., 3., 1.]);
+        assert!(super::rust_concatenate(&[a.clone(), b], 0).is_err());
+
+        let s = super::rust_stack(&[a.clone(), a.clone()], -1).unwrap();
+        assert_eq!(s.shape(), &[2, 2, 2]);
+        assert_eq!(s.to_vec(), vec![0., 0., 1., 1., 2., 2., 3., 3.]);
+        let v = super::rust_vstack(&[arange(vec![2]), arange(vec![2])]).unwrap();
+        assert_eq!(v.shape(), &[2, 2]);
+        let h = super::rust_hstack(&[arange(vec![2]), arange(vec![3])]).unwrap();
+        assert_eq!(h.to_vec(), vec![0., 1., 0., 1., 2.]);
+
+        let parts = super::rust_split(&c, 3, 1).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[2].to_vec(), vec![0., 1.]);
+        let parts = super::rust_split_at(&arange(vec![5]), &[2, 9], 0).unwrap();
+        assert_eq!(parts[1].to_vec(), vec![2., 3., 4.]);
+        assert_eq!(parts[2].size(), 0);
+        assert!(super::rust_split(&c, 2, 1).is_err());
+    }
+
+    #[test]
+    fn tile_repeat_test() {
+        let a = arange(vec![2]);
+        let t = super::rust_tile(&a, &[2, 2]).unwrap();
+        assert_eq!(t.shape(), &[2, 4]);
+        assert_eq!(t.to_vec(), vec![0., 1., 0., 1., 0., 1., 0., 1.]);
+
+        let m = arange(vec![2, 2]);
+        let r = super::rust_repeat(&m, &[2], Some(0)).unwrap();
+        assert_eq!(r.to_vec(), vec![0., 1., 0., 1., 2., 3., 2., 3.]);
+        let r = super::rust_repeat(&m, &[1, 2], Some(1)).unwrap();
+        assert_eq!(r.to_vec(), vec![0., 1., 1., 2., 3., 3.]);
+        let r = super::rust_repeat(&m, &[2], None).unwrap();
+        assert_eq!(r.to_vec(), vec![0., 0., 1., 1., 2., 2., 3., 3.]);
+    }
+
+    #[test]
+    fn pad_test() {
+        use super::PadMode;
+        let a = arange(vec![3]);
+        let c = super::rust_pad(&a, &[(1, 2)], PadMode::Constant(9.)).unwrap();
+        assert_eq!(c.to_vec(), vec![9., 0., 1., 2., 9., 9.]);
+        let e = super::rust_pad(&a, &[(2, 1)], PadMode::Edge).unwrap();
+        assert_eq!(e.to_vec(), vec![0., 0., 0., 1., 2., 2.]);
+        let r = super::rust_pad(&a, &[(2, 2)], PadMode::Reflect).unwrap();
+        assert_eq!(r.to_vec(), vec![2., 1., 0., 1., 2., 1., 0.]);
+
+        let m = arange(vec![2, 2]);
+        let p = super::rust_pad(&m, &[(0, 1), (1, 0)], PadMode::Constant(0.)).unwrap();
+        assert_eq!(p.shape(), &[3, 3]);
+        assert_eq!(p.to_vec(), vec![0., 0., 1., 0., 2., 3., 0., 0., 0.]);
+    }
+}
@@ -0,0 +1,445 @@
+use crate::array::{extract_axes, normalize_axis, to_array, Array, Lanes};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
+use std::cmp::Ordering;
+
+/// Total order on floats that puts NaN after everything else, as numpy's
+/// sorts do.
+pub fn nan_last(a: &f64, b: &f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        _ => a.partial_cmp(b).unwrap(),
+    }
+}
+
+fn sort_lane(lane: &mut [f64], stable: bool, parallel: bool) {
+    match (stable, parallel) {
+        (true, true) => lane.par_sort_by(nan_last),
+        (true, false) => lane.sort_by(nan_last),
+        (false, true) => lane.par_sort_unstable_by(nan_last),
+        (false, false) => lane.sort_unstable_by(nan_last),
+    }
+}
+
+fn argsort_lane(lane: &[f64], out: &mut [f64], stable: bool, parallel: bool) {
+    let mut idx = (0..lane.len()).collect::<Vec<_>>();
+    let cmp = |i: &usize, j: &usize| nan_last(&lane[*i], &lane[*j]);
+    match (stable, parallel) {
+        (true, true) => idx.par_sort_by(cmp),
+        (true, false) => idx.sort_by(cmp),
+        (false, true) => idx.par_sort_unstable_by(cmp),
+        (false, false) => idx.sort_unstable_by(cmp),
+    }
+    out.iter_mut().zip(idx).for_each(|(o, i)| *o = i as f64);
+}
+
+pub fn rust_sort(a: &Array, axis: Option<isize>, stable: bool) -> Result<Array, String> {
+    let lanes = Lanes::new(a, axis)?;
+    let n = lanes.lane_len();
+    let mut data = lanes.data.clone();
+    if lanes.single() {
+        sort_lane(&mut data, stable, true);
+    } else if n > 0 {
+        data.par_chunks_mut(n)
+            .for_each(|lane| sort_lane(lane, stable, false));
+    }
+    lanes.finish(data, n)
+}
+
+pub fn rust_argsort(a: &Array, axis: Option<isize>, stable: bool) -> Result<Array, String> {
+    let lanes = Lanes::new(a, axis)?;
+    let n = lanes.lane_len();
+    let mut out = vec![0.; lanes.data.len()];
+    if lanes.single() {
+        argsort_lane(&lanes.data, &mut out, stable, true);
+    } else if n > 0 {
+        out.par_chunks_mut(n)
+            .zip(lanes.data.par_chunks(n))
+            .for_each(|(o, lane)| argsort_lane(lane, o, stable, false));
+    }
+    lanes.finish(out, n)
+}
+
+/// Sorted, deduplicated `kth` values normalized against a lane of length `n`.
+fn normalize_kth(kth: &[isize], n: usize) -> Result<Vec<usize>, String> {
+    let mut ks = kth
+        .iter()
+        .map(|&k| {
+            let j = if k < 0 { k + n as isize } else { k };
+            if j < 0 || j >= n as isize {
+                return Err(format!("kth(={}) out of bounds ({})", k, n));
+            }
+            Ok(j as usize)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    ks.sort_unstable();
+    ks.dedup();
+    Ok(ks)
+}
+
+/// Places the `k`-th smallest elements for every `k` in `ks` (ascending) at
+/// their sorted position, with smaller elements before and larger after.
+fn partition_lane<T, F>(lane: &mut [T], ks: &[usize], cmp: F)
+where
+    F: Fn(&T, &T) -> Ordering + Copy,
+{
+    let mut lo = 0;
+    for &k in ks {
+        lane[lo..].select_nth_unstable_by(k - lo, cmp);
+        lo = k + 1;
+    }
+}
+
+pub fn rust_partition(a: &Array, kth: &[isize], axis: Option<isize>) -> Result<Array, String> {
+    let lanes = Lanes::new(a, axis)?;
+    let n = lanes.lane_len();
+    let ks = normalize_kth(kth, n)?;
+    let mut data = lanes.data.clone();
+    if n > 0 {
+        data.par_chunks_mut(n)
+            .for_each(|lane| partition_lane(lane, &ks, nan_last));
+    }
+    lanes.finish(data, n)
+}
+
+pub fn rust_argpartition(a: &Array, kth: &[isize], axis: Option<isize>) -> Result<Array, String> {
+    let lanes = Lanes::new(a, axis)?;
+    let n = lanes.lane_len();
+    let ks = normalize_kth(kth, n)?;
+    let mut out = vec![0.; lanes.data.len()];
+    if n > 0 {
+        out.par_chunks_mut(n)
+            .zip(lanes.data.par_chunks(n))
+            .for_each(|(o, lane)| {
+                let mut idx = (0..n).collect::<Vec<_>>();
+                partition_lane(&mut idx, &ks, |i, j| nan_last(&lane[*i], &lane[*j]));
+                o.iter_mut().zip(idx).for_each(|(o, i)| *o = i as f64);
+            });
+    }
+    lanes.finish(out, n)
+}
+
+/// The `k` largest (or smallest) elements along `axis` and their indices.
+/// NaN counts as larger than any number; ties go to the lower index.
+pub fn rust_topk(
+    a: &Array,
+    k: usize,
+    axis: isize,
+    largest: bool,
+    sorted: bool,
+) -> Result<(Array, Array), String> {
+    let lanes = Lanes::new(a, Some(axis))?;
+    let n = lanes.lane_len();
+    if k > n {
+        return Err(format!("k(={}) is larger than the axis length ({})", k, n));
+    }
+    let count = lanes.data.len().checked_div(n).unwrap_or(0);
+    let mut values = vec![0.; count * k];
+    let mut indices = vec![0.; count * k];
+    if k > 0 {
+        values
+            .par_chunks_mut(k)
+            .zip(indices.par_chunks_mut(k))
+            .zip(lanes.data.par_chunks(n))
+            .for_each(|((vs, is), lane)| {
+                let cmp = |i: &usize, j: &usize| {
+                    let ord = if largest {
+                        nan_last(&lane[*j], &lane[*i])
+                    } else {
+                        nan_last(&lane[*i], &lane[*j])
+                    };
+                    ord.then(i.cmp(j))
+                };
+                let mut idx = (0..n).collect::<Vec<_>>();
+                if k < n {
+                    idx.select_nth_unstable_by(k - 1, cmp);
+                    idx.truncate(k);
+                }
+                if sorted {
+                    idx.sort_unstable_by(cmp);
+                }
+                for (t, &i) in idx.iter().enumerate() {
+                    vs[t] = lane[i];
+                    is[t] = i as f64;
+                }
+            });
+    }
+    Ok((lanes.finish(values, k)?, lanes.finish(indices, k)?))
+}
+
+/// Insertion points of `v` into the sorted 1-D array `a` (sorted through
+/// `sorter` if given), searched in parallel.
+pub fn rust_searchsorted(
+    a: &Array,
+    v: &Array,
+    right: bool,
+    sorter: Option<&Array>,
+) -> Result<Array, String> {
+    if a.ndim() != 1 {
+        return Err("searchsorted requires a 1-D array".to_string());
+    }
+    let mut xs = a.to_vec();
+    if let Some(sorter) = sorter {
+        if sorter.shape() != a.shape() {
+            return Err("sorter must have the same shape as the array".to_string());
+        }
+        let order = sorter.to_vec();
+        if order
+            .iter()
+            .any(|&i| i.fract() != 0. || i < 0. || i >= xs.len() as f64)
+        {
+            return Err("sorter index out of range".to_string());
+        }
+        xs = order.iter().map(|&i| xs[i as usize]).collect();
+    }
+    let out = v
+        .to_vec()
+        .par_iter()
+        .map(|x| {
+            let pos = if right {
+                xs.partition_point(|y| nan_last(y, x) != Ordering::Greater)
+            } else {
+                xs.partition_point(|y| nan_last(y, x) == Ordering::Less)
+            };
+            pos as f64
+        })
+        .collect();
+    Ok(Array::from_vec(out, v.shape().to_vec()))
+}
+
+pub struct Unique {
+    pub values: Array,
+    pub index: Vec<usize>,
+    pub inverse: Vec<usize>,
+    pub counts: Vec<usize>,
+}
+
+/// Sorted unique elements (or sub-arrays along `axis`, compared
+/// lexicographically), with first-occurrence indices, the inverse mapping
+/// and counts. NaNs compare equal and collapse into one entry.
+pub fn rust_unique(a: &Array, axis: Option<isize>) -> Result<Unique, String> {
+    let (moved, ax) = match axis {
+        None => (Array::from_vec(a.to_vec(), vec![a.size()]), 0),
+        Some(axis) => {
+            let ax = normalize_axis(axis, a.ndim())?;
+            (a.moveaxis(&[ax as isize], &[0])?, ax)
+        }
+    };
+    let rows = moved.shape()[0];
+    let m = moved.shape()[1..].iter().product::<usize>();
+    let data = moved.to_vec();
+    let row = |i: usize| &data[i * m..(i + 1) * m];
+    let cmp = |i: &usize, j: &usize| {
+        row(*i)
+            .iter()
+            .zip(row(*j))
+            .map(|(x, y)| nan_last(x, y))
+            .find(|&o| o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    };
+    // Stable, so every group starts with its first occurrence.
+    let mut order = (0..rows).collect::<Vec<_>>();
+    order.par_sort_by(cmp);
+
+    let (mut index, mut counts) = (vec![], vec![]);
+    let mut inverse = vec![0; rows];
+    for (t, &i) in order.iter().enumerate() {
+        if t == 0 || cmp(&order[t - 1], &i) != Ordering::Equal {
+            index.push(i);
+            counts.push(0);
+        }
+        inverse[i] = index.len() - 1;
+        *counts.last_mut().unwrap() += 1;
+    }
+
+    let values = index.iter().flat_map(|&i| row(i)).copied().collect();
+    let mut shape = moved.shape().to_vec();
+    shape[0] = index.len();
+    let values = Array::from_vec(values, shape)
+        .moveaxis(&[0], &[ax as isize])?
+        .to_contiguous();
+    Ok(Unique {
+        values,
+        index,
+        inverse,
+        counts,
+    })
+}
+
+fn is_stable(kind: Option<&str>) -> PyResult<bool> {
+    match kind {
+        None | Some("quicksort") | Some("heapsort") => Ok(false),
+        Some("stable") | Some("mergesort") => Ok(true),
+        Some(kind) => Err(PyTypeError::new_err(format!(
+            "sort kind must be one of 'quicksort', 'mergesort', 'heapsort' or 'stable' (got '{}')",
+            kind
+        ))),
+    }
+}
+
+fn to_index_array(xs: &[usize]) -> Array {
+    Array::from_vec(xs.iter().map(|&i| i as f64).collect(), vec![xs.len()])
+}
+
+#[pyfunction(axis = "-1")]
+pub fn sort(a: &PyAny, axis: Option<isize>, kind: Option<&str>) -> PyResult<Array> {
+    rust_sort(&to_array(a)?, axis, is_stable(kind)?).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(axis = "-1")]
+pub fn argsort(a: &PyAny, axis: Option<isize>, kind: Option<&str>) -> PyResult<Array> {
+    rust_argsort(&to_array(a)?, axis, is_stable(kind)?).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(axis = "-1")]
+pub fn partition(a: &PyAny, kth: &PyAny, axis: Option<isize>) -> PyResult<Array> {
+    rust_partition(&to_array(a)?, &extract_axes(kth)?, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(axis = "-1")]
+pub fn argpartition(a: &PyAny, kth: &PyAny, axis: Option<isize>) -> PyResult<Array> {
+    rust_argpartition(&to_array(a)?, &extract_axes(kth)?, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(axis = "-1", largest = "true", sorted = "true")]
+pub fn topk(
+    a: &PyAny,
+    k: usize,
+    axis: isize,
+    largest: bool,
+    sorted: bool,
+) -> PyResult<(Array, Array)> {
+    rust_topk(&to_array(a)?, k, axis, largest, sorted).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(side = "\"left\"")]
+pub fn searchsorted(
+    a: &PyAny,
+    v: &PyAny,
+    side: &str,
+    sorter: Option<&PyAny>,
+) -> PyResult<PyObject> {
+    let right = match side {
+        "left" => false,
+        "right" => true,
+        _ => return Err(PyTypeError::new_err("side must be 'left' or 'right'")),
+    };
+    let sorter = sorter.map(to_array).transpose()?;
+    let res = rust_searchsorted(&to_array(a)?, &to_array(v)?, right, sorter.as_ref())
+        .map_err(PyTypeError::new_err)?;
+    let py = v.py();
+    if res.ndim() == 0 {
+        Ok((res.get(&[]) as usize).into_py(py))
+    } else {
+        Ok(res.into_py(py))
+    }
+}
+
+#[pyfunction(
+    return_index = "false",
+    return_inverse = "false",
+    return_counts = "false"
+)]
+pub fn unique(
+    py: Python,
+    ar: &PyAny,
+    return_index: bool,
+    return_inverse: bool,
+    return_counts: bool,
+    axis: Option<isize>,
+) -> PyResult<PyObject> {
+    let u = rust_unique(&to_array(ar)?, axis).map_err(PyTypeError::new_err)?;
+    if !(return_index || return_inverse || return_counts) {
+        return Ok(u.values.into_py(py));
+    }
+    let mut out = vec![u.values.into_py(py)];
+    if return_index {
+        out.push(to_index_array(&u.index).into_py(py));
+    }
+    if return_inverse {
+        out.push(to_index_array(&u.inverse).into_py(py));
+    }
+    if return_counts {
+        out.push(to_index_array(&u.counts).into_py(py));
+    }
+    Ok(PyTuple::new(py, out).into_py(py))
+}
+
+mod test {
+    #[test]
+    fn sort_test() {
+        use crate::array::Array;
+        let a = Array::from_vec(vec![3., f64::NAN, 1., 2., 2., 0.], vec![2, 3]);
+        let s = super::rust_sort(&a, Some(-1), false).unwrap().to_vec();
+        assert_eq!(&s[..2], &[1., 3.]);
+        assert!(s[2].is_nan());
+        assert_eq!(&s[3..], &[0., 2., 2.]);
+        let s = super::rust_sort(&a, Some(0), true).unwrap().to_vec();
+        assert_eq!(&s[..4], &[2., 2., 0., 3.]);
+        assert!(s[4].is_nan());
+        let s = super::rust_sort(&a, None, false).unwrap();
+        assert_eq!(s.shape(), &[6]);
+        assert!(s.to_vec()[5].is_nan());
+
+        let i = super::rust_argsort(&a, Some(-1), true).unwrap();
+        assert_eq!(i.to_vec(), vec![2., 0., 1., 2., 0., 1.]);
+        let i = super::rust_argsort(&a, Some(0), true).unwrap();
+        assert_eq!(i.to_vec(), vec![1., 1., 1., 0., 0., 0.]);
+    }
+
+    #[test]
+    fn partition_topk_test() {
+        use crate::array::Array;
+        let a = Array::from_vec(vec![5., 1., 4., 2., 3., 0.], vec![6]);
+        let p = super::rust_partition(&a, &[2, -1], None).unwrap().to_vec();
+        assert_eq!(p[2], 2.);
+        assert_eq!(p[5], 5.);
+        assert!(p[..2].iter().all(|&x| x < 2.));
+        let i = super::rust_argpartition(&a, &[0], Some(0))
+            .unwrap()
+            .to_vec();
+        assert_eq!(i[0], 5.);
+
+        let m = Array::from_vec(vec![1., 3., 3., f64::NAN, 0., 2.], vec![2, 3]);
+        let (v, i) = super::rust_topk(&m, 2, -1, true, true).unwrap();
+        assert_eq!(v.shape(), &[2, 2]);
+        assert_eq!(&v.to_vec()[..2], &[3., 3.]);
+        assert_eq!(i.to_vec(), vec![1., 2., 0., 2.]);
+        let (v, _) = super::rust_topk(&m, 1, 0, false, true).unwrap();
+        assert_eq!(v.to_vec(), vec![1., 0., 2.]);
+        assert!(super::rust_topk(&m, 4, -1, true, true).is_err());
+    }
+
+    #[test]
+    fn search_unique_test() {
+        use crate::array::Array;
+        let a = Array::from_vec(vec![1., 2., 2., 3., f64::NAN], vec![5]);
+        let v = Array::from_vec(vec![2., 0., 4., f64::NAN], vec![4]);
+        let l = super::rust_searchsorted(&a, &v, false, None).unwrap();
+        assert_eq!(l.to_vec(), vec![1., 0., 4., 4.]);
+        let r = super::rust_searchsorted(&a, &v, true, None).unwrap();
+        assert_eq!(r.to_vec(), vec![3., 0., 4., 5.]);
+        let b = Array::from_vec(vec![3., 1., 2.], vec![3]);
+        let sorter = Array::from_vec(vec![1., 2., 0.], vec![3]);
+        let s = super::rust_searchsorted(&b, &Array::scalar(2.5), false, Some(&sorter)).unwrap();
+        assert_eq!(s.get(&[]), 2.);
+
+        let x = Array::from_vec(vec![2., f64::NAN, 1., 2., f64::NAN], vec![5]);
+        let u = super::rust_unique(&x, None).unwrap();
+        assert_eq!(&u.values.to_vec()[..2], &[1., 2.]);
+        assert_eq!(u.values.size(), 3);
+        assert_eq!(u.index, vec![2, 0, 1]);
+        assert_eq!(u.inverse, vec![1, 2, 0, 1, 2]);
+        assert_eq!(u.counts, vec![1, 2, 2]);
+
+        let rows = Array::from_vec(vec![1., 0., 0., 1., 1., 0.], vec![3, 2]);
+        let u = super::rust_unique(&rows, Some(0)).unwrap();
+        assert_eq!(u.values.to_vec(), vec![0., 1., 1., 0.]);
+        assert_eq!(u.counts, vec![1, 2]);
+    }
+}
@@ -0,0 +1,1142 @@
+use crate::array::{to_array, Array};
+use crate::cwslice::UnsafeSlice;
+use crate::elementwise_ops::{BinOp, UnaryOp};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
+use std::borrow::Cow;
+
+/// Addition and multiplication of a semiring over `f64`, plus the additive
+/// identity that absent entries stand for.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Semiring {
+    pub add: BinOp,
+    pub mul: BinOp,
+    pub zero: f64,
+}
+
+impl Semiring {
+    pub const PLUS_TIMES: Semiring = Semiring {
+        add: BinOp::Add,
+        mul: BinOp::Mul,
+        zero: 0.,
+    };
+    /// Shortest paths, with entries as edge weights.
+    pub const MIN_PLUS: Semiring = Semiring {
+        add: BinOp::Min,
+        mul: BinOp::Add,
+        zero: f64::INFINITY,
+    };
+    /// Longest (critical) paths.
+    pub const MAX_PLUS: Semiring = Semiring {
+        add: BinOp::Max,
+        mul: BinOp::Add,
+        zero: f64::NEG_INFINITY,
+    };
+    /// Reachability, on 0/1 values.
+    pub const BOOLEAN: Semiring = Semiring {
+        add: BinOp::Max,
+        mul: BinOp::Min,
+        zero: 0.,
+    };
+
+    pub fn parse(name: &str) -> Result<Semiring, String> {
+        match name {
+            "plus_times" => Ok(Semiring::PLUS_TIMES),
+            "min_plus" => Ok(Semiring::MIN_PLUS),
+            "max_plus" => Ok(Semiring::MAX_PLUS),
+            "boolean" | "or_and" => Ok(Semiring::BOOLEAN),
+            _ => Err(format!(
+                "semiring must be one of 'plus_times', 'min_plus', 'max_plus', 'boolean', got '{}'",
+                name
+            )),
+        }
+    }
+
+    fn fma(&self, acc: f64, a: f64, x: f64) -> f64 {
+        self.add.apply(acc, self.mul.apply(a, x))
+    }
+}
+
+/// Compressed sparse rows: the entries of row `i` are at
+/// `indptr[i]..indptr[i + 1]` of `indices` (columns) and `data`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Csr {
+    pub shape: (usize, usize),
+    pub indptr: Vec<usize>,
+    pub indices: Vec<usize>,
+    pub data: Vec<f64>,
+}
+
+impl Csr {
+    pub fn new(
+        shape: (usize, usize),
+        indptr: Vec<usize>,
+        indices: Vec<usize>,
+        data: Vec<f64>,
+    ) -> Result<Csr, String> {
+        if indptr.len() != shape.0 + 1 || indptr[0] != 0 {
+            return Err(format!(
+                "indptr must start at 0 and have {} entries",
+                shape.0 + 1
+            ));
+        }
+        if indptr.windows(2).any(|w| w[0] > w[1]) {
+            return Err("indptr must be non-decreasing".to_string());
+        }
+        let nnz = indptr[shape.0];
+        if indices.len() != nnz || data.len() != nnz {
+            return Err(format!(
+                "indices and data must have {} entries, got {} and {}",
+                nnz,
+                indices.len(),
+                data.len()
+            ));
+        }
+        if indices.par_iter().any(|&j| j >= shape.1) {
+            return Err(format!(
+                "column index out of bounds for {} columns",
+                shape.1
+            ));
+        }
+        Ok(Csr {
+            shape,
+            indptr,
+            indices,
+            data,
+        })
+    }
+
+    pub fn nnz(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn row(&self, i: usize) -> (&[usize], &[f64]) {
+        let r = self.indptr[i]..self.indptr[i + 1];
+        (&self.indices[r.clone()], &self.data[r])
+    }
+
+    /// Assembles rows built independently, e.g. one per rayon task.
+    pub fn from_rows(shape: (usize, usize), rows: Vec<(Vec<usize>, Vec<f64>)>) -> Csr {
+        let mut indptr = Vec::with_capacity(rows.len() + 1);
+        indptr.push(0);
+        for (cols, _) in &rows {
+            indptr.push(indptr[indptr.len() - 1] + cols.len());
+        }
+        let nnz = indptr[rows.len()];
+        let (mut indices, mut data) = (Vec::with_capacity(nnz), Vec::with_capacity(nnz));
+        for (cols, vals) in rows {
+            indices.extend(cols);
+            data.extend(vals);
+        }
+        Csr {
+            shape,
+            indptr,
+            indices,
+            data,
+        }
+    }
+
+    /// The nonzeros of a dense matrix.
+    pub fn from_dense(a: &Array) -> Result<Csr, String> {
+        if a.ndim() != 2 {
+            return Err("sparse matrices must be 2-D".to_string());
+        }
+        let (m, n) = (a.shape()[0], a.shape()[1]);
+        let d = a.to_vec();
+        let rows = (0..m)
+            .into_par_iter()
+            .map(|i| {
+                let row = &d[i * n..(i + 1) * n];
+                (0..n)
+                    .filter(|&j| row[j] != 0.)
+                    .map(|j| (j, row[j]))
+                    .unzip()
+            })
+            .collect();
+        Ok(Csr::from_rows((m, n), rows))
+    }
+
+    pub fn to_dense(&self) -> Array {
+        let (m, n) = self.shape;
+        let mut d = vec![0.; m * n];
+        if n > 0 {
+            d.par_chunks_mut(n).enumerate().for_each(|(i, out)| {
+                let (cols, vals) = self.row(i);
+                cols.iter().zip(vals).for_each(|(&j, &v)| out[j] += v);
+            });
+        }
+        Array::from_vec(d, vec![m, n])
+    }
+
+    /// Structural transpose by counting sort; rows of the result come out
+    /// with sorted column indices.
+    pub fn transpose(&self) -> Csr {
+        let (m, n) = self.shape;
+        let mut indptr = vec![0; n + 1];
+        self.indices.iter().for_each(|&j| indptr[j + 1] += 1);
+        for j in 0..n {
+            indptr[j + 1] += indptr[j];
+        }
+        let mut next = indptr.clone();
+        let mut indices = vec![0; self.nnz()];
+        let mut data = vec![0.; self.nnz()];
+        for i in 0..m {
+            let (cols, vals) = self.row(i);
+            for (&j, &v) in cols.iter().zip(vals) {
+                indices[next[j]] = i;
+                data[next[j]] = v;
+                next[j] += 1;
+            }
+        }
+        Csr {
+            shape: (n, m),
+            indptr,
+            indices,
+            data,
+        }
+    }
+
+    /// Same matrix with every row sorted by column and duplicates summed.
+    pub fn canonical(&self) -> Csr {
+        let rows = (0..self.shape.0)
+            .into_par_iter()
+            .map(|i| {
+                let (cols, vals) = self.row(i);
+                let mut entries = cols
+                    .iter()
+                    .copied()
+                    .zip(vals.iter().copied())
+                    .collect::<Vec<_>>();
+                entries.sort_by_key(|e| e.0);
+                let mut out: (Vec<usize>, Vec<f64>) = (vec![], vec![]);
+                for (j, v) in entries {
+                    if out.0.last() == Some(&j) {
+                        *out.1.last_mut().unwrap() += v;
+                    } else {
+                        out.0.push(j);
+                        out.1.push(v);
+                    }
+                }
+                out
+            })
+            .collect();
+        Csr::from_rows(self.shape, rows)
+    }
+
+    /// `y = A x` over `sr`, one row per task.
+    pub fn matvec(&self, sr: Semiring, x: &[f64]) -> Vec<f64> {
+        (0..self.shape.0)
+            .into_par_iter()
+            .map(|i| {
+                let (cols, vals) = self.row(i);
+                cols.iter()
+                    .zip(vals)
+                    .fold(sr.zero, |acc, (&j, &v)| sr.fma(acc, v, x[j]))
+            })
+            .collect()
+    }
+
+    /// `y = A^T x` over `sr`: rows are scattered into per-task partial
+    /// results, which are then combined.
+    pub fn rmatvec(&self, sr: Semiring, x: &[f64]) -> Vec<f64> {
+        let n = self.shape.1;
+        (0..self.shape.0)
+            .into_par_iter()
+            .fold(
+                || vec![sr.zero; n],
+                |mut y, i| {
+                    let (cols, vals) = self.row(i);
+                    cols.iter()
+                        .zip(vals)
+                        .for_each(|(&j, &v)| y[j] = sr.fma(y[j], v, x[i]));
+                    y
+                },
+            )
+            .reduce(
+                || vec![sr.zero; n],
+                |mut a, b| {
+                    a.iter_mut()
+                        .zip(b)
+                        .for_each(|(x, y)| *x = sr.add.apply(*x, y));
+                    a
+                },
+            )
+    }
+
+    /// Sparse times dense `k x p` matrix, each output row accumulating the
+    /// rows of `b` selected by a row of `self`.
+    pub fn matmat(&self, b: &Array) -> Result<Array, String> {
+        if b.ndim() != 2 || b.shape()[0] != self.shape.1 {
+            return Err(format!(
+                "dimension mismatch: {:?} @ {:?}",
+                self.shape,
+                b.shape()
+            ));
+        }
+        let p = b.shape()[1];
+        let b = b.to_contiguous();
+        let bd = b.as_slice().unwrap();
+        let mut c = vec![0.; self.shape.0 * p];
+        if p > 0 {
+            c.par_chunks_mut(p).enumerate().for_each(|(i, out)| {
+                let (cols, vals) = self.row(i);
+                for (&k, &v) in cols.iter().zip(vals) {
+                    out.iter_mut()
+                        .zip(&bd[k * p..(k + 1) * p])
+                        .for_each(|(c, &x)| *c += v * x);
+                }
+            });
+        }
+        Ok(Array::from_vec(c, vec![self.shape.0, p]))
+    }
+
+    /// Dense `m x k` matrix times sparse, each output row a combination of
+    /// the rows of `self`.
+    pub fn left_matmat(&self, a: &Array) -> Result<Array, String> {
+        if a.ndim() != 2 || a.shape()[1] != self.shape.0 {
+            return Err(format!(
+                "dimension mismatch: {:?} @ {:?}",
+                a.shape(),
+                self.shape
+            ));
+        }
+        let (k, n) = self.shape;
+        let a = a.to_contiguous();
+        let ad = a.as_slice().unwrap();
+        let mut c = vec![0.; a.shape()[0] * n];
+        if n > 0 && k > 0 {
+            c.par_chunks_mut(n)
+                .zip(ad.par_chunks(k))
+                .for_each(|(out, a_row)| {
+                    for (r, &a_ir) in a_row.iter().enumerate().filter(|e| *e.1 != 0.) {
+                        let (cols, vals) = self.row(r);
+                        cols.iter()
+                            .zip(vals)
+                            .for_each(|(&j, &v)| out[j] += a_ir * v);
+                    }
+                });
+        }
+        Ok(Array::from_vec(c, vec![a.shape()[0], n]))
+    }
+
+    /// Applies `f` to the stored values; only valid if `f(0) == 0`.
+    pub fn map(&self, f: impl Fn(f64) -> f64 + Sync + Send) -> Csr {
+        Csr {
+            data: self.data.par_iter().map(|&v| f(v)).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Elementwise `op` of two canonical matrices of the same shape: the
+    /// union of their patterns for ops where `0 op 0 == 0`, the intersection
+    /// for `Mul`. Entries that come out as zero are dropped.
+    pub fn binary(&self, op: BinOp, other: &Csr) -> Result<Csr, String> {
+        if self.shape != other.shape {
+            return Err(format!(
+                "inconsistent shapes {:?} and {:?}",
+                self.shape, other.shape
+            ));
+        }
+        if matches!(op, BinOp::Div | BinOp::Pow) {
+            return Err(format!("{:?} of sparse matrices is not supported", op));
+        }
+        let rows = (0..self.shape.0)
+            .into_par_iter()
+            .map(|i| {
+                let (ac, av) = self.row(i);
+                let (bc, bv) = other.row(i);
+                let (mut p, mut q) = (0, 0);
+                let mut out: (Vec<usize>, Vec<f64>) = (vec![], vec![]);
+                while p < ac.len() || q < bc.len() {
+                    let (j, x, y) = match (ac.get(p), bc.get(q)) {
+                        (Some(&ja), Some(&jb)) if ja == jb => {
+                            p += 1;
+                            q += 1;
+                            (ja, av[p - 1], bv[q - 1])
+                        }
+                        (Some(&ja), Some(&jb)) if ja < jb => {
+                            p += 1;
+                            (ja, av[p - 1], 0.)
+                        }
+                        (Some(&ja), None) => {
+                            p += 1;
+                            (ja, av[p - 1], 0.)
+                        }
+                        (_, Some(&jb)) => {
+                            q += 1;
+                            (jb, 0., bv[q - 1])
+                        }
+                        (None, None) => unreachable!(),
+                    };
+                    let v = op.apply(x, y);
+                    if v != 0. {
+                        out.0.push(j);
+                        out.1.push(v);
+                    }
+                }
+                out
+            })
+            .collect();
+        Ok(Csr::from_rows(self.shape, rows))
+    }
+}
+
+/// Compressed sparse columns, stored as the CSR form of the transpose so
+/// that transposing either way is free.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Csc {
+    pub t: Csr,
+}
+
+impl Csc {
+    pub fn shape(&self) -> (usize, usize) {
+        (self.t.shape.1, self.t.shape.0)
+    }
+
+    pub fn from_csr(a: &Csr) -> Csc {
+        Csc { t: a.transpose() }
+    }
+
+    pub fn to_csr(&self) -> Csr {
+        self.t.transpose()
+    }
+}
+
+/// Coordinate (triplet) format; duplicates are allowed and add up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Coo {
+    pub shape: (usize, usize),
+    pub rows: Vec<usize>,
+    pub cols: Vec<usize>,
+    pub data: Vec<f64>,
+}
+
+impl Coo {
+    pub fn new(
+        shape: (usize, usize),
+        rows: Vec<usize>,
+        cols: Vec<usize>,
+        data: Vec<f64>,
+    ) -> Result<Coo, String> {
+        if rows.len() != data.len() || cols.len() != data.len() {
+            return Err("row, col and data must have the same length".to_string());
+        }
+        if rows.iter().any(|&i| i >= shape.0) || cols.iter().any(|&j| j >= shape.1) {
+            return Err(format!("index out of bounds for shape {:?}", shape));
+        }
+        Ok(Coo {
+            shape,
+            rows,
+            cols,
+            data,
+        })
+    }
+
+    pub fn from_csr(a: &Csr) -> Coo {
+        let rows = (0..a.shape.0)
+            .flat_map(|i| std::iter::repeat_n(i, a.indptr[i + 1] - a.indptr[i]))
+            .collect();
+        Coo {
+            shape: a.shape,
+            rows,
+            cols: a.indices.clone(),
+            data: a.data.clone(),
+        }
+    }
+
+    /// Bucket the entries by row, then sort and merge within rows.
+    pub fn to_csr(&self) -> Csr {
+        let m = self.shape.0;
+        let mut indptr = vec![0; m + 1];
+        self.rows.iter().for_each(|&i| indptr[i + 1] += 1);
+        for i in 0..m {
+            indptr[i + 1] += indptr[i];
+        }
+        let mut next = indptr.clone();
+        let mut indices = vec![0; self.data.len()];
+        let mut data = vec![0.; self.data.len()];
+        for ((&i, &j), &v) in self.rows.iter().zip(&self.cols).zip(&self.data) {
+            indices[next[i]] = j;
+            data[next[i]] = v;
+            next[i] += 1;
+        }
+        Csr {
+            shape: self.shape,
+            indptr,
+            indices,
+            data,
+        }
+        .canonical()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sparse {
+    Csr(Csr),
+    Csc(Csc),
+    Coo(Coo),
+}
+
+impl Sparse {
+    pub fn shape(&self) -> (usize, usize) {
+        match self {
+            Sparse::Csr(a) => a.shape,
+            Sparse::Csc(a) => a.shape(),
+            Sparse::Coo(a) => a.shape,
+        }
+    }
+
+    pub fn nnz(&self) -> usize {
+        match self {
+            Sparse::Csr(a) => a.nnz(),
+            Sparse::Csc(a) => a.t.nnz(),
+            Sparse::Coo(a) => a.data.len(),
+        }
+    }
+
+    pub fn format(&self) -> &'static str {
+        match self {
+            Sparse::Csr(_) => "csr",
+            Sparse::Csc(_) => "csc",
+            Sparse::Coo(_) => "coo",
+        }
+    }
+
+    /// The CSR form, borrowed when that is how it is stored.
+    pub fn csr(&self) -> Cow<'_, Csr> {
+        match self {
+            Sparse::Csr(a) => Cow::Borrowed(a),
+            Sparse::Csc(a) => Cow::Owned(a.to_csr()),
+            Sparse::Coo(a) => Cow::Owned(a.to_csr()),
+        }
+    }
+
+    pub fn to_format(&self, format: &str) -> Result<Sparse, String> {
+        Ok(match (self, format) {
+            (_, f) if f == self.format() => self.clone(),
+            (Sparse::Csr(a), "csc") => Sparse::Csc(Csc::from_csr(a)),
+            (_, "csr") => Sparse::Csr(self.csr().into_owned()),
+            (_, "csc") => Sparse::Csc(Csc::from_csr(&self.csr())),
+            (_, "coo") => Sparse::Coo(Coo::from_csr(&self.csr())),
+            _ => return Err(format!("unknown sparse format '{}'", format)),
+        })
+    }
+
+    pub fn transpose(&self) -> Sparse {
+        match self {
+            Sparse::Csr(a) => Sparse::Csc(Csc { t: a.clone() }),
+            Sparse::Csc(a) => Sparse::Csr(a.t.clone()),
+            Sparse::Coo(a) => Sparse::Coo(Coo {
+                shape: (a.shape.1, a.shape.0),
+                rows: a.cols.clone(),
+                cols: a.rows.clone(),
+                data: a.data.clone(),
+            }),
+        }
+    }
+
+    /// `A x` over `sr`; CSC reads its transpose's rows directly.
+    pub fn matvec(&self, sr: Semiring, x: &[f64]) -> Result<Vec<f64>, String> {
+        if x.len() != self.shape().1 {
+            return Err(format!(
+                "dimension mismatch: {:?} @ ({},)",
+                self.shape(),
+                x.len()
+            ));
+        }
+        Ok(match self {
+            Sparse::Csc(a) => a.t.rmatvec(sr, x),
+            _ => self.csr().matvec(sr, x),
+        })
+    }
+
+    /// `A^T x` over `sr`.
+    pub fn rmatvec(&self, sr: Semiring, x: &[f64]) -> Result<Vec<f64>, String> {
+        if x.len() != self.shape().0 {
+            return Err(format!(
+                "dimension mismatch: {:?}.T @ ({},)",
+                self.shape(),
+                x.len()
+            ));
+        }
+        Ok(match self {
+            Sparse::Csc(a) => a.t.matvec(sr, x),
+            _ => self.csr().rmatvec(sr, x),
+        })
+    }
+
+    /// Sparse times a dense vector or matrix.
+    pub fn dot_dense(&self, b: &Array) -> Result<Array, String> {
+        match b.ndim() {
+            1 => {
+                let y = self.matvec(Semiring::PLUS_TIMES, &b.to_vec())?;
+                Ok(Array::from_vec(y, vec![self.shape().0]))
+            }
+            2 => self.csr().matmat(b),
+            _ => Err("sparse products take 1-D or 2-D operands".to_string()),
+        }
+    }
+
+    /// A dense vector or matrix times sparse.
+    pub fn rdot_dense(&self, a: &Array) -> Result<Array, String> {
+        match a.ndim() {
+            1 => {
+                let y = self.rmatvec(Semiring::PLUS_TIMES, &a.to_vec())?;
+                Ok(Array::from_vec(y, vec![self.shape().1]))
+            }
+            2 => self.csr().left_matmat(a),
+            _ => Err("sparse products take 1-D or 2-D operands".to_string()),
+        }
+    }
+}
+
+/// Gustavson's row-by-row product `A B` over `sr`. A symbolic pass counts
+/// the distinct columns of every output row to size the result, then a
+/// numeric pass fills each row's disjoint slice of it, accumulating into a
+/// dense per-thread workspace. Output rows have sorted columns; entries
+/// that cancel to `sr.zero` are kept, as the pattern is fixed symbolically.
+pub fn rust_spgemm(a: &Csr, b: &Csr, sr: Semiring) -> Result<Csr, String> {
+    if a.shape.1 != b.shape.0 {
+        return Err(format!("dimension mismatch: {:?} @ {:?}", a.shape, b.shape));
+    }
+    let (m, n) = (a.shape.0, b.shape.1);
+
+    // Symbolic phase: `mark[j] == i + 1` once row `i` has touched column `j`.
+    let counts = (0..m)
+        .into_par_iter()
+        .map_init(
+            || vec![0; n],
+            |mark, i| {
+                let mut count = 0;
+                for &k in a.row(i).0 {
+                    for &j in b.row(k).0 {
+                        if mark[j] != i + 1 {
+                            mark[j] = i + 1;
+                            count += 1;
+                        }
+                    }
+                }
+                count
+            },
+        )
+        .collect::<Vec<usize>>();
+    let mut indptr = Vec::with_capacity(m + 1);
+    indptr.push(0);
+    for c in counts {
+        indptr.push(indptr[indptr.len() - 1] + c);
+    }
+
+    // Numeric phase.
+    let nnz = indptr[m];
+    let mut indices = vec![0; nnz];
+    let mut data = vec![0.; nnz];
+    let (out_indices, out_data) = (UnsafeSlice::new(&mut indices), UnsafeSlice::new(&mut data));
+    (0..m).into_par_iter().for_each_init(
+        || (vec![0; n], vec![0.; n], vec![]),
+        |(mark, acc, cols), i| {
+            cols.clear();
+            let (a_cols, a_vals) = a.row(i);
+            for (&k, &a_ik) in a_cols.iter().zip(a_vals) {
+                let (b_cols, b_vals) = b.row(k);
+                for (&j, &b_kj) in b_cols.iter().zip(b_vals) {
+                    let p = sr.mul.apply(a_ik, b_kj);
+                    if mark[j] != i + 1 {
+                        mark[j] = i + 1;
+                        acc[j] = p;
+                        cols.push(j);
+                    } else {
+                        acc[j] = sr.add.apply(acc[j], p);
+                    }
+                }
+            }
+            cols.sort_unstable();
+            for (p, &j) in (indptr[i]..).zip(cols.iter()) {
+                // SAFETY: row i owns indptr[i]..indptr[i + 1] of the output.
+                unsafe {
+                    out_indices.write(p, j);
+                    out_data.write(p, acc[j]);
+                }
+            }
+        },
+    );
+    Ok(Csr {
+        shape: (m, n),
+        indptr,
+        indices,
+        data,
+    })
+}
+
+#[pyclass(name = "SparseMatrix")]
+#[derive(Clone)]
+pub struct SparseMatrix {
+    pub inner: Sparse,
+}
+
+impl SparseMatrix {
+    fn wrap(inner: Sparse) -> Self {
+        SparseMatrix { inner }
+    }
+
+    /// Result of an op on the CSR form, converted back to this format.
+    fn same_format(&self, a: Csr) -> SparseMatrix {
+        let a = Sparse::Csr(a);
+        SparseMatrix::wrap(a.to_format(self.inner.format()).unwrap())
+    }
+
+    fn binary(&self, op: BinOp, other: &SparseMatrix) -> PyResult<SparseMatrix> {
+        let res = self.inner.csr().binary(op, &other.inner.csr());
+        Ok(self.same_format(res.map_err(PyTypeError::new_err)?))
+    }
+
+    /// `self @ other`: sparse for a sparse `other`, dense otherwise.
+    pub fn matmul(&self, py: Python, other: &PyAny) -> PyResult<PyObject> {
+        if let Some(other) = as_sparse(other) {
+            return Ok(self.spgemm(&other, Semiring::PLUS_TIMES)?.into_py(py));
+        }
+        let res = self.inner.dot_dense(&to_array(other)?);
+        Ok(res.map_err(PyTypeError::new_err)?.into_py(py))
+    }
+
+    fn spgemm(&self, other: &SparseMatrix, sr: Semiring) -> PyResult<SparseMatrix> {
+        let c = rust_spgemm(&self.inner.csr(), &other.inner.csr(), sr);
+        Ok(SparseMatrix::wrap(Sparse::Csr(
+            c.map_err(PyTypeError::new_err)?,
+        )))
+    }
+
+    fn unary(&self, op: UnaryOp) -> SparseMatrix {
+        self.same_format(self.inner.csr().map(|v| op.apply(v)))
+    }
+}
+
+/// The sparse matrix behind `obj`, if it is one.
+pub fn as_sparse(obj: &PyAny) -> Option<PyRef<'_, SparseMatrix>> {
+    obj.extract::<PyRef<SparseMatrix>>().ok()
+}
+
+fn extract_shape2(shape: Option<(usize, usize)>, name: &str) -> PyResult<(usize, usize)> {
+    shape.ok_or_else(|| PyTypeError::new_err(format!("{} needs shape=(rows, cols)", name)))
+}
+
+/// Builds the CSR form from a sparse matrix, a dense matrix, or
+/// `(data, indices, indptr)` of the given layout (`transposed` for CSC).
+fn build_compressed(arg: &PyAny, shape: Option<(usize, usize)>, transposed: bool) -> PyResult<Csr> {
+    if let Some(s) = as_sparse(arg) {
+        return Ok(s.inner.csr().into_owned());
+    }
+    if let Ok((data, indices, indptr)) = arg.extract::<(&PyAny, Vec<usize>, Vec<usize>)>() {
+        let shape = extract_shape2(
+            shape,
+            if transposed {
+                "csc_matrix"
+            } else {
+                "csr_matrix"
+            },
+        )?;
+        let data = to_array(data)?.to_vec();
+        let stored = if transposed {
+            (shape.1, shape.0)
+        } else {
+            shape
+        };
+        let a = Csr::new(stored, indptr, indices, data).map_err(PyTypeError::new_err)?;
+        return Ok(if transposed { a.transpose() } else { a }.canonical());
+    }
+    Csr::from_dense(&to_array(arg)?).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn csr_matrix(arg: &PyAny, shape: Option<(usize, usize)>) -> PyResult<SparseMatrix> {
+    Ok(SparseMatrix::wrap(Sparse::Csr(build_compressed(
+        arg, shape, false,
+    )?)))
+}
+
+#[pyfunction]
+pub fn csc_matrix(arg: &PyAny, shape: Option<(usize, usize)>) -> PyResult<SparseMatrix> {
+    let a = build_compressed(arg, shape, true)?;
+    Ok(SparseMatrix::wrap(Sparse::Csc(Csc::from_csr(&a))))
+}
+
+/// From a sparse or dense matrix, or `(data, (row, col))`; the shape
+/// defaults to the smallest that holds every entry.
+#[pyfunction]
+pub fn coo_matrix(arg: &PyAny, shape: Option<(usize, usize)>) -> PyResult<SparseMatrix> {
+    if let Ok((data, (rows, cols))) = arg.extract::<(&PyAny, (Vec<usize>, Vec<usize>))>() {
+        let extent = |v: &[usize]| v.iter().max().map_or(0, |&x| x + 1);
+        let shape = shape.unwrap_or((extent(&rows), extent(&cols)));
+        let data = to_array(data)?.to_vec();
+        let a = Coo::new(shape, rows, cols, data).map_err(PyTypeError::new_err)?;
+        return Ok(SparseMatrix::wrap(Sparse::Coo(a)));
+    }
+    let a = build_compressed(arg, shape, false)?;
+    Ok(SparseMatrix::wrap(Sparse::Coo(Coo::from_csr(&a))))
+}
+
+#[pyfunction]
+pub fn issparse(obj: &PyAny) -> bool {
+    as_sparse(obj).is_some()
+}
+
+/// `A x` over a semiring, for a sparse `A` and dense vector `x`.
+#[pyfunction(semiring = "\"plus_times\"")]
+pub fn spmv(a: PyRef<SparseMatrix>, x: &PyAny, semiring: &str) -> PyResult<Array> {
+    let sr = Semiring::parse(semiring).map_err(PyTypeError::new_err)?;
+    let y = a
+        .inner
+        .matvec(sr, &to_array(x)?.to_vec())
+        .map_err(PyTypeError::new_err)?;
+    Ok(Array::from_vec(y, vec![a.inner.shape().0]))
+}
+
+/// `A @ B` of two sparse matrices over a semiring, as CSR.
+#[pyfunction(semiring = "\"plus_times\"")]
+pub fn spgemm(
+    a: PyRef<SparseMatrix>,
+    b: PyRef<SparseMatrix>,
+    semiring: &str,
+) -> PyResult<SparseMatrix> {
+    let sr = Semiring::parse(semiring).map_err(PyTypeError::new_err)?;
+    a.spgemm(&b, sr)
+}
+
+#[pymethods]
+impl SparseMatrix {
+    #[getter]
+    fn shape(&self, py: Python) -> PyObject {
+        let (m, n) = self.inner.shape();
+        PyTuple::new(py, [m, n]).into_py(py)
+    }
+
+    #[getter]
+    fn nnz(&self) -> usize {
+        self.inner.nnz()
+    }
+
+    #[getter]
+    fn format(&self) -> &'static str {
+        self.inner.format()
+    }
+
+    #[getter]
+    fn data(&self) -> Array {
+        let data = match &self.inner {
+            Sparse::Csr(a) => a.data.clone(),
+            Sparse::Csc(a) => a.t.data.clone(),
+            Sparse::Coo(a) => a.data.clone(),
+        };
+        let n = data.len();
+        Array::from_vec(data, vec![n])
+    }
+
+    #[getter]
+    fn indices(&self) -> PyResult<Vec<usize>> {
+        match &self.inner {
+            Sparse::Csr(a) => Ok(a.indices.clone()),
+            Sparse::Csc(a) => Ok(a.t.indices.clone()),
+            Sparse::Coo(_) => Err(PyTypeError::new_err("coo matrices have row and col")),
+        }
+    }
+
+    #[getter]
+    fn indptr(&self) -> PyResult<Vec<usize>> {
+        match &self.inner {
+            Sparse::Csr(a) => Ok(a.indptr.clone()),
+            Sparse::Csc(a) => Ok(a.t.indptr.clone()),
+            Sparse::Coo(_) => Err(PyTypeError::new_err("coo matrices have row and col")),
+        }
+    }
+
+    #[getter]
+    fn row(&self) -> PyResult<Vec<usize>> {
+        match &self.inner {
+            Sparse::Coo(a) => Ok(a.rows.clone()),
+            _ => Err(PyTypeError::new_err("only coo matrices have row")),
+        }
+    }
+
+    #[getter]
+    fn col(&self) -> PyResult<Vec<usize>> {
+        match &self.inner {
+            Sparse::Coo(a) => Ok(a.cols.clone()),
+            _ => Err(PyTypeError::new_err("only coo matrices have col")),
+        }
+    }
+
+    #[getter(T)]
+    fn py_t(&self) -> SparseMatrix {
+        self.transpose()
+    }
+
+    fn transpose(&self) -> SparseMatrix {
+        SparseMatrix::wrap(self.inner.transpose())
+    }
+
+    fn toarray(&self) -> Array {
+        self.inner.csr().to_dense()
+    }
+
+    fn asformat(&self, format: &str) -> PyResult<SparseMatrix> {
+        let inner = self.inner.to_format(format).map_err(PyTypeError::new_err)?;
+        Ok(SparseMatrix::wrap(inner))
+    }
+
+    fn tocsr(&self) -> SparseMatrix {
+        SparseMatrix::wrap(self.inner.to_format("csr").unwrap())
+    }
+
+    fn tocsc(&self) -> SparseMatrix {
+        SparseMatrix::wrap(self.inner.to_format("csc").unwrap())
+    }
+
+    fn tocoo(&self) -> SparseMatrix {
+        SparseMatrix::wrap(self.inner.to_format("coo").unwrap())
+    }
+
+    fn dot(&self, py: Python, other: &PyAny) -> PyResult<PyObject> {
+        self.matmul(py, other)
+    }
+
+    fn __matmul__(&self, py: Python, other: &PyAny) -> PyResult<PyObject> {
+        self.matmul(py, other)
+    }
+
+    fn __rmatmul__(&self, other: &PyAny) -> PyResult<Array> {
+        self.inner
+            .rdot_dense(&to_array(other)?)
+            .map_err(PyTypeError::new_err)
+    }
+
+    fn __add__(&self, other: PyRef<SparseMatrix>) -> PyResult<SparseMatrix> {
+        self.binary(BinOp::Add, &other)
+    }
+
+    fn __sub__(&self, other: PyRef<SparseMatrix>) -> PyResult<SparseMatrix> {
+        self.binary(BinOp::Sub, &other)
+    }
+
+    fn __mul__(&self, k: f64) -> SparseMatrix {
+        self.same_format(self.inner.csr().map(|v| v * k))
+    }
+
+    fn __rmul__(&self, k: f64) -> SparseMatrix {
+        self.__mul__(k)
+    }
+
+    fn __truediv__(&self, k: f64) -> SparseMatrix {
+        self.same_format(self.inner.csr().map(|v| v / k))
+    }
+
+    fn __neg__(&self) -> SparseMatrix {
+        self.__mul__(-1.)
+    }
+
+    fn __abs__(&self) -> SparseMatrix {
+        self.unary(UnaryOp::Abs)
+    }
+
+    /// Elementwise product with another sparse matrix.
+    fn multiply(&self, other: PyRef<SparseMatrix>) -> PyResult<SparseMatrix> {
+        self.binary(BinOp::Mul, &other)
+    }
+
+    fn maximum(&self, other: PyRef<SparseMatrix>) -> PyResult<SparseMatrix> {
+        self.binary(BinOp::Max, &other)
+    }
+
+    fn minimum(&self, other: PyRef<SparseMatrix>) -> PyResult<SparseMatrix> {
+        self.binary(BinOp::Min, &other)
+    }
+
+    /// Elementwise power; `p` must be positive so that zeros stay zero.
+    fn power(&self, p: f64) -> PyResult<SparseMatrix> {
+        if p <= 0. {
+            return Err(PyTypeError::new_err("power must be positive"));
+        }
+        Ok(self.same_format(self.inner.csr().map(|v| v.powf(p))))
+    }
+
+    fn sqrt(&self) -> SparseMatrix {
+        self.unary(UnaryOp::Sqrt)
+    }
+
+    fn sin(&self) -> SparseMatrix {
+        self.unary(UnaryOp::Sin)
+    }
+
+    fn tan(&self) -> SparseMatrix {
+        self.unary(UnaryOp::Tan)
+    }
+
+    fn tanh(&self) -> SparseMatrix {
+        self.unary(UnaryOp::Tanh)
+    }
+
+    fn expm1(&self) -> SparseMatrix {
+        self.unary(UnaryOp::Expm1)
+    }
+
+    fn log1p(&self) -> SparseMatrix {
+        self.unary(UnaryOp::Log1p)
+    }
+
+    fn sign(&self) -> SparseMatrix {
+        self.unary(UnaryOp::Sign)
+    }
+
+    fn floor(&self) -> SparseMatrix {
+        self.unary(UnaryOp::Floor)
+    }
+
+    fn ceil(&self) -> SparseMatrix {
+        self.unary(UnaryOp::Ceil)
+    }
+
+    fn __repr__(&self) -> String {
+        let (m, n) = self.inner.shape();
+        let name = match self.inner.format() {
+            "csr" => "Compressed Sparse Row",
+            "csc" => "Compressed Sparse Column",
+            _ => "COOrdinate",
+        };
+        format!(
+            "<{}x{} sparse matrix of type 'float64' with {} stored elements in {} format>",
+            m,
+            n,
+            self.inner.nnz(),
+            name
+        )
+    }
+}
+
+pub(crate) mod test {
+    /// `tridiag(-1, d, -1)`; shared by the solver and preconditioner tests.
+    #[allow(dead_code)]
+    pub(crate) fn tridiagonal(n: usize, d: f64) -> super::Csr {
+        let rows = (0..n)
+            .map(|i| {
+                let cols = (i.saturating_sub(1)..(i + 2).min(n)).collect::<Vec<_>>();
+                let vals = cols.iter().map(|&j| if j == i { d } else { -1. }).collect();
+                (cols, vals)
+            })
+            .collect();
+        super::Csr::from_rows((n, n), rows)
+    }
+
+    #[allow(dead_code)]
+    fn example() -> super::Csr {
+        // [[1, 0, 2],
+        //  [0, 0, 3],
+        //  [4, 5, 0]]
+        super::Csr::new(
+            (3, 3),
+            vec![0, 2, 3, 5],
+            vec![0, 2, 2, 0, 1],
+            vec![1., 2., 3., 4., 5.],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn conversion_test() {
+        use super::{Coo, Csc, Csr};
+        use crate::array::Array;
+        let a = example();
+        let dense = a.to_dense();
+        assert_eq!(dense.to_vec(), vec![1., 0., 2., 0., 0., 3., 4., 5., 0.]);
+        assert_eq!(Csr::from_dense(&dense).unwrap(), a);
+        assert_eq!(a.transpose().to_dense().to_vec(), dense.t().to_vec());
+        assert_eq!(a.transpose().transpose(), a);
+        assert_eq!(Csc::from_csr(&a).to_csr(), a);
+        assert_eq!(Coo::from_csr(&a).to_csr(), a);
+
+        // Unsorted with a duplicate.
+        let coo = Coo::new((2, 2), vec![1, 0, 1], vec![1, 0, 1], vec![1., 2., 3.]).unwrap();
+        assert_eq!(coo.to_csr().to_dense().to_vec(), vec![2., 0., 0., 4.]);
+        assert!(Csr::new((2, 2), vec![0, 1, 1], vec![2], vec![1.]).is_err());
+        assert!(Csr::from_dense(&Array::from_vec(vec![1.], vec![1])).is_err());
+    }
+
+    #[test]
+    fn product_test() {
+        use super::{Csc, Semiring, Sparse};
+        use crate::array::Array;
+        let a = example();
+        let x = [1., 2., 3.];
+        assert_eq!(a.matvec(Semiring::PLUS_TIMES, &x), vec![7., 9., 14.]);
+        assert_eq!(a.rmatvec(Semiring::PLUS_TIMES, &x), vec![13., 15., 8.]);
+        let csc = Sparse::Csc(Csc::from_csr(&a));
+        assert_eq!(
+            csc.matvec(Semiring::PLUS_TIMES, &x).unwrap(),
+            vec![7., 9., 14.]
+        );
+        // min over the row of a_ij + x_j
+        assert_eq!(a.matvec(Semiring::MIN_PLUS, &x), vec![2., 6., 5.]);
+
+        let b = Array::from_vec(vec![1., 0., 0., 1., 1., 1.], vec![3, 2]);
+        assert_eq!(a.matmat(&b).unwrap().to_vec(), vec![3., 2., 3., 3., 4., 5.]);
+        let l = Array::from_vec(vec![1., 1., 0., 0., 0., 1.], vec![2, 3]);
+        assert_eq!(
+            a.left_matmat(&l).unwrap().to_vec(),
+            vec![1., 0., 5., 4., 5., 0.]
+        );
+        assert!(a.matmat(&l).is_err());
+    }
+
+    #[test]
+    fn elementwise_test() {
+        use crate::elementwise_ops::BinOp;
+        let a = example();
+        let b = a.transpose();
+        let sum = a.binary(BinOp::Add, &b).unwrap();
+        assert_eq!(
+            sum.to_dense().to_vec(),
+            vec![2., 0., 6., 0., 0., 8., 6., 8., 0.]
+        );
+        let diff = a.binary(BinOp::Sub, &a).unwrap();
+        assert_eq!(diff.nnz(), 0);
+        let prod = a.binary(BinOp::Mul, &b).unwrap();
+        assert_eq!(
+            prod.to_dense().to_vec(),
+            vec![1., 0., 8., 0., 0., 15., 8., 15., 0.]
+        );
+        assert!(a.binary(BinOp::Div, &b).is_err());
+        assert_eq!(a.map(|v| -v).to_dense().get(&[2, 1]), -5.);
+    }
+
+    #[test]
+    fn spgemm_test() {
+        use super::{rust_spgemm, Csr, Semiring};
+        use crate::array::Array;
+        use crate::matrix_mult::rust_array_matmul;
+        let a = example();
+        let b = Csr::new((3, 2), vec![0, 1, 1, 3], vec![1, 0, 1], vec![2., 1., -1.]).unwrap();
+        let c = rust_spgemm(&a, &b, Semiring::PLUS_TIMES).unwrap();
+        let expected = rust_array_matmul(&a.to_dense(), &b.to_dense()).unwrap();
+        assert_eq!(c.to_dense().to_vec(), expected.to_vec());
+        assert!(c.indptr.windows(2).all(|w| {
+            let cols = &c.indices[w[0]..w[1]];
+            cols.windows(2).all(|p| p[0] < p[1])
+        }));
+        // Row 0 is 1 * [0, 2] + 2 * [1, -1]: an explicit zero stays stored.
+        assert_eq!(c.row(0), (&[0, 1][..], &[2., 0.][..]));
+        assert!(rust_spgemm(&b, &b, Semiring::PLUS_TIMES).is_err());
+
+        // Two-hop shortest paths on a weighted graph 0 -> 1 -> 2, 0 -> 2.
+        let g = Csr::from_dense(&Array::from_vec(
+            vec![0., 1., 5., 0., 0., 1., 0., 0., 0.],
+            vec![3, 3],
+        ))
+        .unwrap();
+        let g2 = rust_spgemm(&g, &g, Semiring::MIN_PLUS).unwrap();
+        assert_eq!(g2.row(0), (&[2][..], &[2.][..]));
+        let reach = rust_spgemm(&g.map(|_| 1.), &g.map(|_| 1.), Semiring::BOOLEAN).unwrap();
+        assert_eq!(reach.nnz(), 1);
+    }
+}
@@ -0,0 +1,593 @@
+use crate::array::{normalize_axis, to_array, Array};
+use crate::elementwise_ops::{rust_binary, BinOp};
+use crate::matrix_mult::rust_array_matmul;
+use crate::reduction_ops::{rust_reduce, Reduction};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use std::collections::HashMap;
+
+/// `view` as a `rows * cols` matrix, copying only when it is neither
+/// contiguous nor a 2-D transpose that the GEMM kernel can read directly.
+fn as_matrix(view: &Array, rows: usize, cols: usize) -> Array {
+    if view.is_contiguous() {
+        view.reshaped(vec![rows, cols])
+    } else if view.shape() == [rows, cols] {
+        view.clone()
+    } else {
+        view.to_contiguous().reshaped(vec![rows, cols])
+    }
+}
+
+fn dims_of(a: &Array, axes: &[usize]) -> Vec<usize> {
+    axes.iter().map(|&ax| a.shape()[ax]).collect()
+}
+
+/// Sums the products over `axes_a` of `a` paired with `axes_b` of `b` by
+/// permuting both into matrices and making one GEMM call.
+pub fn rust_tensordot(
+    a: &Array,
+    b: &Array,
+    axes_a: &[usize],
+    axes_b: &[usize],
+) -> Result<Array, String> {
+    if axes_a.len() != axes_b.len() || dims_of(a, axes_a) != dims_of(b, axes_b) {
+        return Err("shape-mismatch for sum".to_string());
+    }
+    let free_a: Vec<usize> = (0..a.ndim()).filter(|ax| !axes_a.contains(ax)).collect();
+    let free_b: Vec<usize> = (0..b.ndim()).filter(|ax| !axes_b.contains(ax)).collect();
+    let m = dims_of(a, &free_a).iter().product::<usize>();
+    let k = dims_of(a, axes_a).iter().product::<usize>();
+    let n = dims_of(b, &free_b).iter().product::<usize>();
+
+    let a2 = as_matrix(
+        &a.permute_axes(&[free_a.as_slice(), axes_a].concat())?,
+        m,
+        k,
+    );
+    let b2 = as_matrix(
+        &b.permute_axes(&[axes_b, free_b.as_slice()].concat())?,
+        k,
+        n,
+    );
+    let c = rust_array_matmul(&a2, &b2)?;
+    Ok(c.reshaped([dims_of(a, &free_a), dims_of(b, &free_b)].concat()))
+}
+
+#[pyfunction]
+pub fn tensordot(a: &PyAny, b: &PyAny, axes: Option<&PyAny>) -> PyResult<Array> {
+    let (a, b) = (to_array(a)?, to_array(b)?);
+    let (axes_a, axes_b): (Vec<isize>, Vec<isize>) = match axes {
+        None => tensordot_last_first(&a, 2),
+        Some(axes) => match (
+            axes.extract::<usize>(),
+            axes.extract::<(Vec<isize>, Vec<isize>)>(),
+            axes.extract::<(isize, isize)>(),
+        ) {
+            (Ok(n), _, _) => tensordot_last_first(&a, n),
+            (_, Ok(pair), _) => pair,
+            (_, _, Ok((x, y))) => (vec![x], vec![y]),
+            _ => return Err(PyTypeError::new_err("Malformed axes parameter")),
+        },
+    };
+    let norm = |axes: Vec<isize>, ndim| {
+        axes.into_iter()
+            .map(|ax| normalize_axis(ax, ndim))
+            .collect::<Result<Vec<usize>, String>>()
+    };
+    let axes_a = norm(axes_a, a.ndim()).map_err(PyTypeError::new_err)?;
+    let axes_b = norm(axes_b, b.ndim()).map_err(PyTypeError::new_err)?;
+    rust_tensordot(&a, &b, &axes_a, &axes_b).map_err(PyTypeError::new_err)
+}
+
+// integer `axes`: the last n axes of `a` against the first n axes of `b`
+fn tensordot_last_first(a: &Array, n: usize) -> (Vec<isize>, Vec<isize>) {
+    let nd = a.ndim() as isize;
+    let n = n as isize;
+    ((nd - n..nd).collect(), (0..n).collect())
+}
+
+/// Input labels per operand and output labels of an einsum subscript string.
+pub fn parse_subscripts(
+    subscripts: &str,
+    nops: usize,
+) -> Result<(Vec<Vec<char>>, Vec<char>), String> {
+    let s: String = subscripts.chars().filter(|c| !c.is_whitespace()).collect();
+    if s.contains('.') {
+        return Err("ellipsis in einsum subscripts is not supported".to_string());
+    }
+    let (lhs, rhs) = match s.split_once("->") {
+        Some((lhs, rhs)) => (lhs, Some(rhs)),
+        None => (s.as_str(), None),
+    };
+    let inputs: Vec<Vec<char>> = lhs.split(',').map(|t| t.chars().collect()).collect();
+    if inputs.len() != nops {
+        return Err(format!(
+            "{} operands given but subscripts describe {}",
+            nops,
+            inputs.len()
+        ));
+    }
+    if let Some(c) = inputs.iter().flatten().find(|c| !c.is_ascii_alphabetic()) {
+        return Err(format!("invalid subscript `{}` in einsum string", c));
+    }
+
+    let count = |c: char| inputs.iter().flatten().filter(|&&x| x == c).count();
+    let output: Vec<char> = match rhs {
+        Some(rhs) => rhs.chars().collect(),
+        None => {
+            let mut once: Vec<char> = inputs
+                .iter()
+                .flatten()
+                .copied()
+                .filter(|&c| count(c) == 1)
+                .collect();
+            once.sort_unstable();
+            once
+        }
+    };
+    for (i, &c) in output.iter().enumerate() {
+        if count(c) == 0 {
+            return Err(format!(
+                "output subscript `{}` does not appear in the input",
+                c
+            ));
+        } else if output[..i].contains(&c) {
+            return Err(format!("output subscript `{}` appears more than once", c));
+        }
+    }
+    Ok((inputs, output))
+}
+
+#[derive(Clone)]
+struct Operand {
+    arr: Array,
+    labels: Vec<char>,
+}
+
+/// Takes diagonals for labels repeated within the operand and sums out
+/// labels that nothing in `keep` needs.
+fn prepare(mut op: Operand, keep: &[char]) -> Result<Operand, String> {
+    while let Some((i, j)) = (0..op.labels.len())
+        .flat_map(|i| (i + 1..op.labels.len()).map(move |j| (i, j)))
+        .find(|&(i, j)| op.labels[i] == op.labels[j])
+    {
+        op.arr = op.arr.diagonal_axes(i, j);
+        op.labels.remove(j);
+    }
+    let summed: Vec<usize> = (0..op.labels.len())
+        .filter(|&ax| !keep.contains(&op.labels[ax]))
+        .collect();
+    if !summed.is_empty() {
+        op.arr = rust_reduce(Reduction::Sum, &op.arr, Some(&summed), false)?;
+        op.labels.retain(|c| keep.contains(c));
+    }
+    Ok(op)
+}
+
+fn positions(labels: &[char], wanted: &[char]) -> Vec<usize> {
+    wanted
+        .iter()
+        .map(|c| labels.iter().position(|l| l == c).unwrap())
+        .collect()
+}
+
+/// Contracts two operands as one batched GEMM: shared labels still needed
+/// later are the batch, the other shared labels are summed over.
+fn contract(a: Operand, b: Operand, keep: &[char]) -> Result<Operand, String> {
+    let a = prepare(a, &[keep, b.labels.as_slice()].concat())?;
+    let b = prepare(b, &[keep, a.labels.as_slice()].concat())?;
+    let shared = |c: &char| b.labels.contains(c);
+    let batch: Vec<char> = a
+        .labels
+        .iter()
+        .copied()
+        .filter(|c| shared(c) && keep.contains(c))
+        .collect();
+    let inner: Vec<char> = a
+        .labels
+        .iter()
+        .copied()
+        .filter(|c| shared(c) && !keep.contains(c))
+        .collect();
+    let free_a: Vec<char> = a.labels.iter().copied().filter(|c| !shared(c)).collect();
+    let free_b: Vec<char> = b
+        .labels
+        .iter()
+        .copied()
+        .filter(|c| !a.labels.contains(c))
+        .collect();
+
+    let pa = a.arr.permute_axes(&positions(
+        &a.labels,
+        &[batch.as_slice(), &free_a, &inner].concat(),
+    ))?;
+    let pb = b.arr.permute_axes(&positions(
+        &b.labels,
+        &[batch.as_slice(), &inner, &free_b].concat(),
+    ))?;
+    let size = |arr: &Array, from: usize, len: usize| {
+        arr.shape()[from..from + len].iter().product::<usize>()
+    };
+    let nb = batch.len();
+    let (bs, m, k) = (
+        size(&pa, 0, nb),
+        size(&pa, nb, free_a.len()),
+        size(&pa, nb + free_a.len(), inner.len()),
+    );
+    let n = size(&pb, nb + inner.len(), free_b.len());
+
+    let c = if nb == 0 {
+        rust_array_matmul(&as_matrix(&pa, m, k), &as_matrix(&pb, k, n))?
+    } else {
+        let a3 = pa.to_contiguous().reshaped(vec![bs, m, k]);
+        let b3 = pb.to_contiguous().reshaped(vec![bs, k, n]);
+        rust_array_matmul(&a3, &b3)?
+    };
+    let shape = [
+        &pa.shape()[..nb + free_a.len()],
+        &pb.shape()[nb + inner.len()..],
+    ]
+    .concat();
+    Ok(Operand {
+        arr: c.reshaped(shape),
+        labels: [batch, free_a, free_b].concat(),
+    })
+}
+
+/// Order in which to contract the operands, as pairs of positions in the
+/// shrinking operand list (the result is appended at the end, as in numpy).
+/// The greedy path picks the pair whose result frees the most memory,
+/// breaking ties by the fewest multiplications.
+pub fn einsum_contraction_path(
+    inputs: &[Vec<char>],
+    output: &[char],
+    dims: &HashMap<char, usize>,
+    optimize: bool,
+) -> Vec<(usize, usize)> {
+    let mut ops: Vec<Vec<char>> = inputs.to_vec();
+    let mut path = vec![];
+    let size = |labels: &[char]| labels.iter().map(|c| dims[c]).product::<usize>() as f64;
+    while ops.len() > 1 {
+        let mut best = (0, 1);
+        if optimize {
+            let mut best_cost = (f64::INFINITY, f64::INFINITY);
+            for i in 0..ops.len() {
+                for j in i + 1..ops.len() {
+                    let (result, union) = pair_result(&ops, i, j, output);
+                    let cost = (size(&result) - size(&ops[i]) - size(&ops[j]), size(&union));
+                    if cost < best_cost {
+                        best_cost = cost;
+                        best = (i, j);
+                    }
+                }
+            }
+        }
+        let (result, _) = pair_result(&ops, best.0, best.1, output);
+        ops.remove(best.1);
+        ops.remove(best.0);
+        ops.push(result);
+        path.push(best);
+    }
+    path
+}
+
+// labels left after contracting ops i and j, and all labels involved
+fn pair_result(ops: &[Vec<char>], i: usize, j: usize, output: &[char]) -> (Vec<char>, Vec<char>) {
+    let mut union: Vec<char> = ops[i].clone();
+    union.extend(ops[j].iter().filter(|c| !ops[i].contains(c)));
+    union.dedup();
+    let needed = |c: &char| {
+        output.contains(c)
+            || ops
+                .iter()
+                .enumerate()
+                .any(|(o, labels)| o != i && o != j && labels.contains(c))
+    };
+    let mut result: Vec<char> = vec![];
+    for c in union.iter().filter(|c| needed(c)) {
+        if !result.contains(c) {
+            result.push(*c);
+        }
+    }
+    (result, union)
+}
+
+fn label_dims(inputs: &[Vec<char>], arrays: &[Array]) -> Result<HashMap<char, usize>, String> {
+    let mut dims = HashMap::new();
+    for (labels, a) in inputs.iter().zip(arrays) {
+        if labels.len() != a.ndim() {
+            return Err(format!(
+                "operand has {} dimensions but {} subscripts",
+                a.ndim(),
+                labels.len()
+            ));
+        }
+        for (&c, &n) in labels.iter().zip(a.shape()) {
+            match dims.insert(c, n) {
+                Some(m) if m != n => {
+                    return Err(format!(
+                        "size of label `{}` does not match ({} vs {})",
+                        c, m, n
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(dims)
+}
+
+pub fn rust_einsum(subscripts: &str, arrays: &[Array], optimize: bool) -> Result<Array, String> {
+    let (inputs, output) = parse_subscripts(subscripts, arrays.len())?;
+    let dims = label_dims(&inputs, arrays)?;
+    let path = einsum_contraction_path(&inputs, &output, &dims, optimize);
+
+    let mut ops: Vec<Operand> = inputs
+        .into_iter()
+        .zip(arrays)
+        .map(|(labels, arr)| Operand {
+            arr: arr.clone(),
+            labels,
+        })
+        .collect();
+    for (i, j) in path {
+        let b = ops.remove(j);
+        let a = ops.remove(i);
+        let mut keep: Vec<char> = output.clone();
+        ops.iter().for_each(|op| keep.extend(&op.labels));
+        ops.push(contract(a, b, &keep)?);
+    }
+    let last = prepare(ops.pop().unwrap(), &output)?;
+    last.arr.permute_axes(&positions(&last.labels, &output))
+}
+
+fn extract_operands(operands: &PyTuple) -> PyResult<Vec<Array>> {
+    operands.iter().map(to_array).collect()
+}
+
+#[pyfunction(operands = "*", optimize = "true")]
+pub fn einsum(
+    py: Python,
+    subscripts: &str,
+    operands: &PyTuple,
+    optimize: bool,
+) -> PyResult<PyObject> {
+    let arrays = extract_operands(operands)?;
+    let res = rust_einsum(subscripts, &arrays, optimize).map_err(PyTypeError::new_err)?;
+    if res.ndim() == 0 {
+        Ok(res.get(&[]).into_py(py))
+    } else {
+        Ok(res.into_py(py))
+    }
+}
+
+#[pyfunction(operands = "*")]
+pub fn einsum_path(subscripts: &str, operands: &PyTuple) -> PyResult<Vec<(usize, usize)>> {
+    let arrays = extract_operands(operands)?;
+    let (inputs, output) =
+        parse_subscripts(subscripts, arrays.len()).map_err(PyTypeError::new_err)?;
+    let dims = label_dims(&inputs, &arrays).map_err(PyTypeError::new_err)?;
+    Ok(einsum_contraction_path(&inputs, &output, &dims, true))
+}
+
+/// `np.kron`: the outer product of `a` and `b` is formed by broadcasting, then
+/// its axes are interleaved (a0, b0, a1, b1, ...) and merged pairwise.
+pub fn rust_kron(a: &Array, b: &Array) -> Result<Array, String> {
+    let nd = a.ndim().max(b.ndim());
+    let mut a = a.clone();
+    let mut b = b.clone();
+    while a.ndim() < nd {
+        a = a.expand_dims(0);
+    }
+    while b.ndim() < nd {
+        b = b.expand_dims(0);
+    }
+    let mut a_outer = a.clone();
+    for _ in 0..nd {
+        a_outer = a_outer.expand_dims(a_outer.ndim());
+    }
+    let outer = rust_binary(BinOp::Mul, &a_outer, &b)?;
+    let interleaved: Vec<usize> = (0..nd).flat_map(|ax| [ax, nd + ax]).collect();
+    let shape = (0..nd).map(|ax| a.shape()[ax] * b.shape()[ax]).collect();
+    Ok(outer
+        .permute_axes(&interleaved)?
+        .to_contiguous()
+        .reshaped(shape))
+}
+
+/// Elementwise product of two arrays of the same shape (no broadcasting).
+pub fn rust_hadamard(a: &Array, b: &Array) -> Result<Array, String> {
+    if a.shape() != b.shape() {
+        return Err(format!("shapes {:?} and {:?} differ", a.shape(), b.shape()));
+    }
+    rust_binary(BinOp::Mul, a, b)
+}
+
+/// Column-wise Kronecker product of an `i * k` and a `j * k` matrix, giving
+/// `(i * j) * k`.
+pub fn rust_khatri_rao(a: &Array, b: &Array) -> Result<Array, String> {
+    if a.ndim() != 2 || b.ndim() != 2 || a.shape()[1] != b.shape()[1] {
+        return Err("Parameters must be matrices with the same number of columns".to_string());
+    }
+    let (i, j, k) = (a.shape()[0], b.shape()[0], a.shape()[1]);
+    let prod = rust_binary(BinOp::Mul, &a.expand_dims(1), b)?;
+    Ok(prod.reshaped(vec![i * j, k]))
+}
+
+/// `(A1 ⊗ A2 ⊗ ... ⊗ Ad) x` without forming the Kronecker product: `x` is
+/// viewed as a tensor with one axis per factor and each factor is applied
+/// along its own axis, so the work is d small GEMMs instead of one product
+/// with the full `prod(m_i) * prod(n_i)` matrix.
+pub fn rust_kron_matvec(factors: &[Array], x: &Array) -> Result<Array, String> {
+    if factors.is_empty() || factors.iter().any(|f| f.ndim() != 2) {
+        return Err("Factors must be a non-empty list of matrices".to_string());
+    }
+    let cols: Vec<usize> = factors.iter().map(|f| f.shape()[1]).collect();
+    if x.ndim() != 1 || x.size() != cols.iter().product::<usize>() {
+        return Err("Vector length doesn't match the Kronecker product".to_string());
+    }
+    let mut t = x.to_contiguous().reshaped(cols);
+    for (i, f) in factors.iter().enumerate() {
+        t = rust_tensordot(f, &t, &[1], &[i])?.moveaxis(&[0], &[i as isize])?;
+    }
+    let len = t.size();
+    Ok(t.to_contiguous().reshaped(vec![len]))
+}
+
+#[pyfunction]
+pub fn kron(a: &PyAny, b: &PyAny) -> PyResult<Array> {
+    rust_kron(&to_array(a)?, &to_array(b)?).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn hadamard(a: &PyAny, b: &PyAny) -> PyResult<Array> {
+    rust_hadamard(&to_array(a)?, &to_array(b)?).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn khatri_rao(a: &PyAny, b: &PyAny) -> PyResult<Array> {
+    rust_khatri_rao(&to_array(a)?, &to_array(b)?).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction]
+pub fn kron_matvec(factors: Vec<&PyAny>, x: &PyAny) -> PyResult<Array> {
+    let factors = factors
+        .into_iter()
+        .map(to_array)
+        .collect::<PyResult<Vec<Array>>>()?;
+    rust_kron_matvec(&factors, &to_array(x)?).map_err(PyTypeError::new_err)
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::array::test::arange;
+
+    #[test]
+    fn tensordot_test() {
+        let a = arange(vec![3, 4, 5]);
+        let b = arange(vec![4, 3, 2]);
+        let c = super::rust_tensordot(&a, &b, &[1, 0], &[0, 1]).unwrap();
+        assert_eq!(c.shape(), &[5, 2]);
+        let mut want = 0.;
+        for i in 0..3 {
+            for j in 0..4 {
+                want += a.get(&[i, j, 2]) * b.get(&[j, i, 1]);
+            }
+        }
+        assert_eq!(c.get(&[2, 1]), want);
+        assert!(super::rust_tensordot(&a, &b, &[0], &[0]).is_err());
+    }
+
+    #[test]
+    fn einsum_basic_test() {
+        use super::rust_einsum;
+        let a = arange(vec![3, 3]);
+        let b = arange(vec![3, 4]);
+        assert_eq!(
+            rust_einsum("ii", std::slice::from_ref(&a), true)
+                .unwrap()
+                .get(&[]),
+            12.
+        );
+        assert_eq!(
+            rust_einsum("ii->i", std::slice::from_ref(&a), true)
+                .unwrap()
+                .to_vec(),
+            vec![0., 4., 8.]
+        );
+        assert_eq!(
+            rust_einsum("ij->ji", std::slice::from_ref(&b), true)
+                .unwrap()
+                .to_vec(),
+            b.t().to_vec()
+        );
+        let ab = crate::matrix_mult::rust_array_matmul(&a, &b).unwrap();
+        assert_eq!(
+            rust_einsum("ij,jk", &[a.clone(), b.clone()], true)
+                .unwrap()
+                .to_vec(),
+            ab.to_vec()
+        );
+        assert_eq!(
+            rust_einsum("ij,jk->ki", &[a.clone(), b.clone()], false)
+                .unwrap()
+                .to_vec(),
+            ab.t().to_vec()
+        );
+        assert!(rust_einsum("ij,jk", &[a.clone(), a.t().expand_dims(0)], true).is_err());
+    }
+
+    #[test]
+    fn einsum_batched_test() {
+        use super::rust_einsum;
+        let x = arange(vec![2, 3, 4]);
+        let y = arange(vec![2, 4, 5]);
+        let out = rust_einsum("bij,bjk->bik", &[x.clone(), y.clone()], true).unwrap();
+        let want = crate::matrix_mult::rust_array_matmul(&x, &y).unwrap();
+        assert_eq!(out.to_vec(), want.to_vec());
+    }
+
+    #[test]
+    fn einsum_path_test() {
+        use std::collections::HashMap;
+        // a(10x1000) b(1000x10) c(10x1000): contracting b with c first makes a
+        // 1000x1000 intermediate, so the greedy path starts with a and b
+        let dims: HashMap<char, usize> = [('i', 10), ('j', 1000), ('k', 10), ('l', 1000)]
+            .into_iter()
+            .collect();
+        let inputs = vec![vec!['i', 'j'], vec!['j', 'k'], vec!['k', 'l']];
+        let path = super::einsum_contraction_path(&inputs, &['i', 'l'], &dims, true);
+        assert_eq!(path, vec![(0, 1), (0, 1)]);
+
+        let a = arange(vec![2, 3]);
+        let b = arange(vec![3, 4]);
+        let c = arange(vec![4, 2]);
+        let chain =
+            super::rust_einsum("ij,jk,kl->il", &[a.clone(), b.clone(), c.clone()], true).unwrap();
+        let ab = crate::matrix_mult::rust_array_matmul(&a, &b).unwrap();
+        let want = crate::matrix_mult::rust_array_matmul(&ab, &c).unwrap();
+        assert_eq!(chain.to_vec(), want.to_vec());
+    }
+
+    #[test]
+    fn kron_test() {
+        use crate::array::Array;
+        let a = Array::from_vec(vec![1., 2., 3., 4.], vec![2, 2]);
+        let b = Array::from_vec(vec![0., 5., 6., 7.], vec![2, 2]);
+        let k = super::rust_kron(&a, &b).unwrap();
+        assert_eq!(k.shape(), &[4, 4]);
+        assert_eq!(
+            k.to_vec(),
+            vec![0., 5., 0., 10., 6., 7., 12., 14., 0., 15., 0., 20., 18., 21., 24., 28.]
+        );
+        let v = Array::from_vec(vec![1., 10.], vec![2]);
+        assert_eq!(super::rust_kron(&v, &a).unwrap().shape(), &[2, 4]);
+    }
+
+    #[test]
+    fn khatri_rao_test() {
+        use crate::array::Array;
+        let a = Array::from_vec(vec![1., 2., 3., 4.], vec![2, 2]);
+        let b = Array::from_vec(vec![1., 10., 100., 1000., 5., 6.], vec![3, 2]);
+        let kr = super::rust_khatri_rao(&a, &b).unwrap();
+        assert_eq!(kr.shape(), &[6, 2]);
+        assert_eq!(kr.get(&[4, 1]), 4000.);
+        assert_eq!(kr.get(&[2, 0]), 5.);
+        assert!(super::rust_hadamard(&a, &b).is_err());
+    }
+
+    #[test]
+    fn kron_matvec_test() {
+        use crate::array::Array;
+        let a = arange(vec![2, 3]);
+        let b = arange(vec![4, 2]);
+        let c = Array::from_vec(vec![1., -1., 2., 0.5], vec![2, 2]);
+        let x = arange(vec![12]);
+        let full = super::rust_kron(&super::rust_kron(&a, &b).unwrap(), &c).unwrap();
+        let want = crate::matrix_mult::rust_array_matmul(&full, &x).unwrap();
+        let got = super::rust_kron_matvec(&[a, b, c], &x).unwrap();
+        assert_eq!(got.shape(), &[16]);
+        assert_eq!(got.to_vec(), want.to_vec());
+    }
+}
@@ -0,0 +1,660 @@
+use crate::array::{to_array, Array};
+use crate::npy_io::extract_path;
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rayon::slice::ParallelSlice;
+use std::io::Write;
+
+/// Bytes of text handed to each parsing task, at least.
+const MIN_CHUNK: usize = 1 << 20;
+
+/// How `loadtxt`/`genfromtxt` split and convert lines.
+#[derive(Clone, Debug)]
+pub struct TextOptions {
+    /// `None` splits on runs of whitespace.
+    pub delimiter: Option<String>,
+    /// Everything after any of these markers is ignored.
+    pub comments: Vec<String>,
+    /// Lines skipped at the start, comments and blank lines included.
+    pub skiprows: usize,
+    pub usecols: Option<Vec<isize>>,
+    /// Fields (besides empty ones) that count as missing.
+    pub missing: Vec<String>,
+    /// Replaces missing fields; without it they are an error.
+    pub fill: Option<f64>,
+    /// Also fill fields that are not numbers instead of failing.
+    pub lenient: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: None,
+            comments: vec!["#".to_string()],
+            skiprows: 0,
+            usecols: None,
+            missing: vec![],
+            fill: None,
+            lenient: false,
+        }
+    }
+}
+
+/// Values of one chunk of lines, row-major; `cols` is `None` if the chunk
+/// held no data lines.
+struct Parsed {
+    data: Vec<f64>,
+    rows: usize,
+    cols: Option<usize>,
+}
+
+fn parse_field(field: &str, o: &TextOptions) -> Result<f64, String> {
+    if field.is_empty() || o.missing.iter().any(|m| m == field) {
+        return o
+            .fill
+            .ok_or_else(|| "missing value and no filling value given".to_string());
+    }
+    match field.parse::<f64>() {
+        Ok(x) => Ok(x),
+        Err(_) if o.lenient => Ok(o.fill.unwrap_or(f64::NAN)),
+        Err(_) => Err(format!("could not convert string '{}' to float64", field)),
+    }
+}
+
+/// Appends the values of one line to `out` and returns how many there were;
+/// 0 for lines that are blank once comments are removed.
+fn parse_line(line: &str, o: &TextOptions, out: &mut Vec<f64>) -> Result<usize, String> {
+    let end = o
+        .comments
+        .iter()
+        .filter_map(|c| line.find(c.as_str()))
+        .min()
+        .unwrap_or(line.len());
+    let line = line[..end].trim_end_matches('\r');
+    if line.trim().is_empty() {
+        return Ok(0);
+    }
+    let fields: Vec<&str> = match &o.delimiter {
+        None => line.split_whitespace().collect(),
+        Some(d) => line.split(d.as_str()).map(str::trim).collect(),
+    };
+    match &o.usecols {
+        None => {
+            for f in &fields {
+                out.push(parse_field(f, o)?);
+            }
+            Ok(fields.len())
+        }
+        Some(cols) => {
+            let n = fields.len() as isize;
+            for &c in cols {
+                let i = if c < 0 { c + n } else { c };
+                if i < 0 || i >= n {
+                    return Err(format!(
+                        "usecols index {} is out of range for a row with {} columns",
+                        c, n
+                    ));
+                }
+                out.push(parse_field(fields[i as usize], o)?);
+            }
+            Ok(cols.len())
+        }
+    }
+}
+
+/// Parses a run of whole lines; errors carry the index of the failing line
+/// within the chunk.
+fn parse_chunk(chunk: &[u8], o: &TextOptions) -> Result<Parsed, (usize, String)> {
+    let text = std::str::from_utf8(chunk).map_err(|e| (0, e.to_string()))?;
+    let mut parsed = Parsed {
+        data: vec![],
+        rows: 0,
+        cols: None,
+    };
+    for (i, line) in text.split('\n').enumerate() {
+        let n = parse_line(line, o, &mut parsed.data).map_err(|e| (i, e))?;
+        if n == 0 {
+            continue;
+        }
+        match parsed.cols {
+            Some(c) if c != n => {
+                return Err((
+                    i,
+                    format!("the number of columns changed from {} to {}", c, n),
+                ))
+            }
+            _ => parsed.cols = Some(n),
+        }
+        parsed.rows += 1;
+    }
+    Ok(parsed)
+}
+
+/// Splits `bytes[start..]` into ranges of roughly `target` bytes that end
+/// just after a newline, so that no line straddles two ranges.
+fn line_ranges(bytes: &[u8], start: usize, target: usize) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    let mut s = start;
+    while s < bytes.len() {
+        let e = (s + target).min(bytes.len());
+        let e = bytes[e - 1..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |i| e + i);
+        ranges.push((s, e));
+        s = e;
+    }
+    ranges
+}
+
+/// Parses delimited text into row-major values and the number of rows and
+/// columns, one rayon task per byte range. Without data lines there are no
+/// columns.
+pub fn parse_table(bytes: &[u8], o: &TextOptions) -> Result<(Vec<f64>, usize, usize), String> {
+    let bytes = bytes.strip_prefix("\u{feff}".as_bytes()).unwrap_or(bytes);
+    let mut start = 0;
+    for _ in 0..o.skiprows {
+        start = bytes[start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |i| start + i + 1);
+    }
+    let target = (bytes.len() / (4 * rayon::current_num_threads())).max(MIN_CHUNK);
+    let ranges = line_ranges(bytes, start, target);
+    let results = ranges
+        .par_iter()
+        .map(|&(s, e)| parse_chunk(&bytes[s..e], o))
+        .collect::<Vec<_>>();
+
+    let line_no = |chunk: usize, line: usize| {
+        let before = bytes[start..ranges[chunk].0]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count();
+        o.skiprows + before + line + 1
+    };
+    let mut cols = None;
+    let mut data = vec![];
+    let mut rows = 0;
+    for (k, r) in results.into_iter().enumerate() {
+        let p = r.map_err(|(line, e)| format!("line {}: {}", line_no(k, line), e))?;
+        match (cols, p.cols) {
+            (Some(c), Some(n)) if c != n => {
+                return Err(format!(
+                    "line {}: the number of columns changed from {} to {}",
+                    line_no(k, 0),
+                    c,
+                    n
+                ))
+            }
+            (None, Some(n)) => cols = Some(n),
+            _ => {}
+        }
+        rows += p.rows;
+        data.extend(p.data);
+    }
+    Ok((data, rows, cols.unwrap_or(0)))
+}
+
+/// `parse_table` as an Array; like numpy, dimensions of length one are
+/// squeezed out.
+pub fn rust_parse_text(bytes: &[u8], o: &TextOptions) -> Result<Array, String> {
+    let (data, rows, cols) = parse_table(bytes, o)?;
+    let shape = match cols {
+        0 => vec![0],
+        c => [rows, c].into_iter().filter(|&d| d != 1).collect(),
+    };
+    Ok(Array::from_vec(data, shape))
+}
+
+pub fn rust_loadtxt(path: &str, o: &TextOptions) -> Result<Array, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    rust_parse_text(&bytes, o)
+}
+
+/// One `%` conversion of a printf-style format.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    zero: bool,
+    alt: bool,
+    width: usize,
+    precision: Option<usize>,
+    conv: char,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece {
+    Text(String),
+    Value(Spec),
+}
+
+/// Parses a printf-style format with `e`, `f`, `g` (and upper-case) and
+/// `d`/`i` conversions into literal text and conversions.
+pub fn parse_format(fmt: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            text.push('%');
+            continue;
+        }
+        let mut spec = Spec::default();
+        while let Some(&f) = chars.peek() {
+            match f {
+                '-' => spec.left = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '0' => spec.zero = true,
+                '#' => spec.alt = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        let digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+            let mut n = String::new();
+            while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                n.push(*d);
+                chars.next();
+            }
+            n.parse::<usize>().ok()
+        };
+        spec.width = digits(&mut chars).unwrap_or(0);
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            spec.precision = Some(digits(&mut chars).unwrap_or(0));
+        }
+        spec.conv = match chars.next() {
+            Some(c @ ('e' | 'E' | 'f' | 'F' | 'g' | 'G' | 'd' | 'i')) => c,
+            Some(c) => return Err(format!("unsupported format conversion '%{}'", c)),
+            None => return Err(format!("incomplete format '{}'", fmt)),
+        };
+        if !text.is_empty() {
+            pieces.push(Piece::Text(std::mem::take(&mut text)));
+        }
+        pieces.push(Piece::Value(spec));
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+/// C's `%.*e` of a non-negative finite `v`: at least two exponent digits.
+fn exp_notation(v: f64, precision: usize) -> String {
+    let s = format!("{:.*e}", precision, v);
+    let (mantissa, exp) = s.split_once('e').unwrap();
+    let exp = exp.parse::<i32>().unwrap();
+    let sign = if exp < 0 { '-' } else { '+' };
+    format!("{}e{}{:02}", mantissa, sign, exp.abs())
+}
+
+fn strip_zeros(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s
+    }
+}
+
+/// C's `%g`: fixed or exponent notation depending on the exponent, with
+/// trailing zeros removed unless `alt`.
+fn general_notation(v: f64, precision: usize, alt: bool) -> String {
+    let p = precision.max(1);
+    let exp = if v == 0. {
+        0
+    } else {
+        let s = format!("{:.*e}", p - 1, v);
+        s.split_once('e').unwrap().1.parse::<i32>().unwrap()
+    };
+    if exp < -4 || exp >= p as i32 {
+        let s = exp_notation(v, p - 1);
+        if alt {
+            return s;
+        }
+        let (mantissa, exp) = s.split_once('e').unwrap();
+        format!("{}e{}", strip_zeros(mantissa), exp)
+    } else {
+        let s = format!("{:.*}", (p as i32 - 1 - exp) as usize, v);
+        if alt {
+            s
+        } else {
+            strip_zeros(&s).to_string()
+        }
+    }
+}
+
+pub fn format_value(spec: &Spec, x: f64) -> String {
+    let (negative, body) = if x.is_nan() {
+        (false, "nan".to_string())
+    } else if x.is_infinite() {
+        (x < 0., "inf".to_string())
+    } else {
+        let v = x.abs();
+        match spec.conv {
+            'd' | 'i' => (x.trunc() < 0., format!("{:.0}", v.trunc())),
+            c => {
+                let p = spec.precision.unwrap_or(6);
+                let body = match c.to_ascii_lowercase() {
+                    'e' => exp_notation(v, p),
+                    'g' => general_notation(v, p, spec.alt),
+                    _ => format!("{:.*}", p, v),
+                };
+                (x.is_sign_negative(), body)
+            }
+        }
+    };
+    let body = if spec.conv.is_ascii_uppercase() {
+        body.to_ascii_uppercase()
+    } else {
+        body
+    };
+    let sign = match (negative, spec.plus, spec.space) {
+        (true, _, _) => "-",
+        (false, true, _) => "+",
+        (false, false, true) => " ",
+        _ => "",
+    };
+    let len = sign.len() + body.len();
+    if len >= spec.width {
+        format!("{}{}", sign, body)
+    } else if spec.left {
+        format!("{}{}{}", sign, body, " ".repeat(spec.width - len))
+    } else if spec.zero && x.is_finite() {
+        format!("{}{}{}", sign, "0".repeat(spec.width - len), body)
+    } else {
+        format!("{}{}{}", " ".repeat(spec.width - len), sign, body)
+    }
+}
+
+/// Format of a whole row: a single conversion is repeated for every column
+/// with `delimiter` in between, several are used as given.
+pub fn row_format(fmts: &[String], delimiter: &str, cols: usize) -> Result<Vec<Piece>, String> {
+    let mut pieces = vec![];
+    match fmts {
+        [fmt] if fmt.matches('%').count() - 2 * fmt.matches("%%").count() == 1 => {
+            let one = parse_format(fmt)?;
+            for c in 0..cols {
+                if c > 0 {
+                    pieces.push(Piece::Text(delimiter.to_string()));
+                }
+                pieces.extend(one.iter().cloned());
+            }
+        }
+        [fmt] => pieces = parse_format(fmt)?,
+        _ => {
+            for (c, fmt) in fmts.iter().enumerate() {
+                if c > 0 {
+                    pieces.push(Piece::Text(delimiter.to_string()));
+                }
+                pieces.extend(parse_format(fmt)?);
+            }
+        }
+    }
+    let n = pieces
+        .iter()
+        .filter(|p| matches!(p, Piece::Value(_)))
+        .count();
+    if n != cols {
+        return Err(format!(
+            "fmt has {} conversions but the array has {} columns",
+            n, cols
+        ));
+    }
+    Ok(pieces)
+}
+
+fn format_row(pieces: &[Piece], row: &[f64], newline: &str) -> String {
+    let mut values = row.iter();
+    let mut line = String::new();
+    for p in pieces {
+        match p {
+            Piece::Text(t) => line.push_str(t),
+            Piece::Value(spec) => line.push_str(&format_value(spec, *values.next().unwrap())),
+        }
+    }
+    line.push_str(newline);
+    line
+}
+
+/// Options of `savetxt` besides the data and the path.
+#[derive(Clone, Debug)]
+pub struct SaveTextOptions {
+    pub fmt: Vec<String>,
+    pub delimiter: String,
+    pub newline: String,
+    pub header: String,
+    pub footer: String,
+    pub comments: String,
+}
+
+/// Text of `a` (1-D as one column, or 2-D), rows formatted in parallel.
+pub fn rust_format_text(a: &Array, o: &SaveTextOptions) -> Result<String, String> {
+    let a = match a.ndim() {
+        1 => a.expand_dims(1),
+        2 => a.clone(),
+        _ => return Err("Expected 1D or 2D array".to_string()),
+    };
+    let cols = a.shape()[1];
+    let pieces = row_format(&o.fmt, &o.delimiter, cols)?;
+    let data = a.to_vec();
+    let commented = |text: &str| {
+        format!(
+            "{}{}{}",
+            o.comments,
+            text.replace('\n', &format!("\n{}", o.comments)),
+            o.newline
+        )
+    };
+    let mut text = String::new();
+    if !o.header.is_empty() {
+        text.push_str(&commented(&o.header));
+    }
+    if cols > 0 {
+        let rows = data
+            .par_chunks(cols)
+            .map(|row| format_row(&pieces, row, &o.newline))
+            .collect::<Vec<_>>();
+        rows.iter().for_each(|r| text.push_str(r));
+    }
+    if !o.footer.is_empty() {
+        text.push_str(&commented(&o.footer));
+    }
+    Ok(text)
+}
+
+pub fn rust_savetxt(path: &str, a: &Array, o: &SaveTextOptions) -> Result<(), String> {
+    let text = rust_format_text(a, o)?;
+    let file = std::fs::File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+    std::io::BufWriter::new(file)
+        .write_all(text.as_bytes())
+        .map_err(|e| e.to_string())
+}
+
+/// A string or a sequence of strings.
+#[derive(FromPyObject)]
+pub enum Strings {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Strings {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Strings::One(s) => vec![s],
+            Strings::Many(v) => v,
+        }
+    }
+}
+
+#[pyfunction(comments = "Strings::One(\"#\".to_string())", skiprows = "0")]
+pub fn loadtxt(
+    fname: &PyAny,
+    delimiter: Option<String>,
+    comments: Option<Strings>,
+    skiprows: usize,
+    usecols: Option<Vec<isize>>,
+    filling_values: Option<f64>,
+) -> PyResult<Array> {
+    let o = TextOptions {
+        delimiter,
+        comments: comments.map_or(vec![], Strings::into_vec),
+        skiprows,
+        usecols,
+        fill: filling_values,
+        ..TextOptions::default()
+    };
+    rust_loadtxt(&extract_path(fname)?, &o).map_err(PyIOError::new_err)
+}
+
+/// Like `loadtxt`, but missing and unparsable fields become
+/// `filling_values` (NaN by default) instead of errors.
+#[pyfunction(
+    comments = "Strings::One(\"#\".to_string())",
+    skip_header = "0",
+    filling_values = "f64::NAN"
+)]
+pub fn genfromtxt(
+    fname: &PyAny,
+    delimiter: Option<String>,
+    comments: Option<Strings>,
+    skip_header: usize,
+    usecols: Option<Vec<isize>>,
+    missing_values: Option<Strings>,
+    filling_values: f64,
+) -> PyResult<Array> {
+    let o = TextOptions {
+        delimiter,
+        comments: comments.map_or(vec![], Strings::into_vec),
+        skiprows: skip_header,
+        usecols,
+        missing: missing_values.map_or(vec![], Strings::into_vec),
+        fill: Some(filling_values),
+        lenient: true,
+    };
+    rust_loadtxt(&extract_path(fname)?, &o).map_err(PyIOError::new_err)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[pyfunction(
+    fmt = "Strings::One(\"%.18e\".to_string())",
+    delimiter = "\" \"",
+    newline = "\"\\n\"",
+    header = "\"\"",
+    footer = "\"\"",
+    comments = "\"# \""
+)]
+pub fn savetxt(
+    fname: &PyAny,
+    x: &PyAny,
+    fmt: Strings,
+    delimiter: &str,
+    newline: &str,
+    header: &str,
+    footer: &str,
+    comments: &str,
+) -> PyResult<()> {
+    let o = SaveTextOptions {
+        fmt: fmt.into_vec(),
+        delimiter: delimiter.to_string(),
+        newline: newline.to_string(),
+        header: header.to_string(),
+        footer: footer.to_string(),
+        comments: comments.to_string(),
+    };
+    rust_savetxt(&extract_path(fname)?, &to_array(x)?, &o).map_err(PyIOError::new_err)
+}
+
+mod test {
+    #[test]
+    fn parse_text_test() {
+        use super::TextOptions;
+        let text = b"# x y z\n1 2 3\n\n4 5 6 # trailing\r\n7 8 9\n";
+        let a = super::rust_parse_text(text, &TextOptions::default()).unwrap();
+        assert_eq!(a.shape(), &[3, 3]);
+        assert_eq!(a.to_vec(), vec![1., 2., 3., 4., 5., 6., 7., 8., 9.]);
+
+        let o = TextOptions {
+            delimiter: Some(",".to_string()),
+            skiprows: 1,
+            usecols: Some(vec![0, -1]),
+            missing: vec!["NA".to_string()],
+            fill: Some(-1.),
+            ..TextOptions::default()
+        };
+        let csv = b"a,b,c\n1,,3\nNA,5,6e1\n";
+        let a = super::rust_parse_text(csv, &o).unwrap();
+        assert_eq!(a.to_vec(), vec![1., 3., -1., 60.]);
+
+        let col = super::rust_parse_text(b"1\n2\n", &TextOptions::default()).unwrap();
+        assert_eq!(col.shape(), &[2]);
+        let err = super::rust_parse_text(b"1 2\n3\n", &TextOptions::default())
+            .err()
+            .unwrap();
+        assert!(err.starts_with("line 2"));
+        let err = super::rust_parse_text(b"1 x\n", &TextOptions::default())
+            .err()
+            .unwrap();
+        assert!(err.contains("'x'"));
+    }
+
+    #[test]
+    fn chunked_parse_test() {
+        use super::TextOptions;
+        let n = 3 * super::MIN_CHUNK / 10;
+        let text = (0..n)
+            .map(|i| format!("{},{}\n", i, i * 2))
+            .collect::<String>();
+        let o = TextOptions {
+            delimiter: Some(",".to_string()),
+            ..TextOptions::default()
+        };
+        let ranges = super::line_ranges(text.as_bytes(), 0, super::MIN_CHUNK);
+        assert!(ranges.len() > 1);
+        assert!(ranges.iter().all(|&(_, e)| text.as_bytes()[e - 1] == b'\n'));
+        let a = super::rust_parse_text(text.as_bytes(), &o).unwrap();
+        assert_eq!(a.shape(), &[n, 2]);
+        assert_eq!(a.get(&[n - 1, 1]), (2 * (n - 1)) as f64);
+    }
+
+    #[test]
+    fn format_test() {
+        use super::{format_value, parse_format, Piece};
+        let spec = |fmt: &str| match &parse_format(fmt).unwrap()[0] {
+            Piece::Value(s) => s.clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!(format_value(&spec("%.18e"), 1.), "1.000000000000000000e+00");
+        assert_eq!(format_value(&spec("%.3e"), -0.00012345), "-1.234e-04");
+        assert_eq!(format_value(&spec("%g"), 0.0001), "0.0001");
+        assert_eq!(format_value(&spec("%g"), 1234567.), "1.23457e+06");
+        assert_eq!(format_value(&spec("%g"), 100.), "100");
+        assert_eq!(format_value(&spec("%08.3f"), -1.23456), "-001.235");
+        assert_eq!(format_value(&spec("%-6d|"), 42.9), "42    ");
+        assert_eq!(format_value(&spec("%+.1E"), f64::INFINITY), "+INF");
+        assert!(parse_format("%s").is_err());
+
+        let o = super::SaveTextOptions {
+            fmt: vec!["%d".to_string(), "%.2f".to_string()],
+            delimiter: ",".to_string(),
+            newline: "\n".to_string(),
+            header: "a,b\nunits".to_string(),
+            footer: String::new(),
+            comments: "# ".to_string(),
+        };
+        let a = crate::array::Array::from_vec(vec![1., 2.5, 3., -0.125], vec![2, 2]);
+        assert_eq!(
+            super::rust_format_text(&a, &o).unwrap(),
+            "# a,b\n# units\n1,2.50\n3,-0.12\n"
+        );
+    }
+}
@@ -1,24 +1,22 @@
+use crate::array::{to_array, Array};
+use crate::elementwise_ops::broadcast_shape;
 use pyo3::exceptions::PyTypeError;
-use pyo3::prelude::{pyfunction, PyResult};
-use pyo3::types::PyList;
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use pyo3::prelude::{pyfunction, PyAny, PyResult};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
+use rayon::slice::ParallelSliceMut;
 
 #[pyfunction]
-pub fn norm(xs: &PyList) -> PyResult<f64> {
-    match xs.extract::<Vec<f64>>() {
-        Ok(xs_vec) => Ok(xs_vec.par_iter().map(|x| x * x).sum::<f64>().sqrt()),
-        _ => Err(PyTypeError::new_err(
-            "Parameter cannot be converted to list of floats.",
-        )),
-    }
+pub fn norm(xs: &PyAny) -> PyResult<f64> {
+    let xs = to_array(xs)?.to_contiguous();
+    let xs = xs.as_slice().unwrap();
+    Ok(xs.par_iter().map(|x| x * x).sum::<f64>().sqrt())
 }
 
 #[pyfunction]
-pub fn outer(xs: &PyList, ys: &PyList) -> PyResult<Vec<Vec<f64>>> {
-    match (xs.extract::<Vec<f64>>(), ys.extract::<Vec<f64>>()) {
-        (Ok(xs), Ok(ys)) => Ok(rust_outer(&xs, &ys)),
-        _ => Err(PyTypeError::new_err("Malformed parameter(s)")),
-    }
+pub fn outer(xs: &PyAny, ys: &PyAny) -> PyResult<Vec<Vec<f64>>> {
+    Ok(rust_outer(&to_array(xs)?.to_vec(), &to_array(ys)?.to_vec()))
 }
 
 pub fn rust_outer(xs: &Vec<f64>, ys: &Vec<f64>) -> Vec<Vec<f64>> {
@@ -27,26 +25,167 @@ pub fn rust_outer(xs: &Vec<f64>, ys: &Vec<f64>) -> Vec<Vec<f64>> {
         .collect()
 }
 
+/// Reads both vectors in place, so memory-mapped operands are never copied.
 #[pyfunction]
-pub fn dot(xs: &PyList, ys: &PyList) -> PyResult<f64> {
-    match (xs.extract::<Vec<f64>>(), ys.extract::<Vec<f64>>()) {
-        (Ok(xs_vec), Ok(ys_vec)) => {
-            if xs_vec.len() == ys.len() {
-                Ok(rust_dot(&xs_vec, &ys_vec))
-            } else {
-                Err(PyTypeError::new_err("Parameters have different lengths"))
-            }
-        }
-        _ => Err(PyTypeError::new_err(
-            "Parameter(s) cannot be converted to list of floats.",
-        )),
+pub fn dot(xs: &PyAny, ys: &PyAny) -> PyResult<f64> {
+    let (xs, ys) = (to_array(xs)?.to_contiguous(), to_array(ys)?.to_contiguous());
+    if xs.ndim() != 1 || ys.ndim() != 1 {
+        return Err(PyTypeError::new_err("Parameters must be vectors"));
+    }
+    if xs.size() != ys.size() {
+        return Err(PyTypeError::new_err("Parameters have different lengths"));
     }
+    Ok(rust_dot(xs.as_slice().unwrap(), ys.as_slice().unwrap()))
 }
 
-pub fn rust_dot(xs: &Vec<f64>, ys: &Vec<f64>) -> f64 {
+pub fn rust_dot(xs: &[f64], ys: &[f64]) -> f64 {
     xs.par_iter().zip(ys.par_iter()).map(|(&x, &y)| x * y).sum()
 }
 
+type Batched = (Vec<f64>, Vec<f64>, Vec<usize>, usize, usize);
+
+/// Broadcasts the leading (batch) axes of `a` and `b` against each other and
+/// lays both out contiguously, one vector per row, with the vectors taken
+/// along `axis`.
+fn batched_vectors(a: &Array, b: &Array, axis: isize) -> Result<Batched, String> {
+    if a.ndim() == 0 || b.ndim() == 0 {
+        return Err("Parameters must be arrays of vectors".to_string());
+    }
+    let a = a.moveaxis(&[axis], &[-1])?;
+    let b = b.moveaxis(&[axis], &[-1])?;
+    let (na, nb) = (a.shape()[a.ndim() - 1], b.shape()[b.ndim() - 1]);
+    let batch = broadcast_shape(&[&a.shape()[..a.ndim() - 1], &b.shape()[..b.ndim() - 1]])?;
+    let xs = a
+        .broadcast_to(&[batch.as_slice(), &[na]].concat())?
+        .to_vec();
+    let ys = b
+        .broadcast_to(&[batch.as_slice(), &[nb]].concat())?
+        .to_vec();
+    Ok((xs, ys, batch, na, nb))
+}
+
+fn from_vectors(
+    data: Vec<f64>,
+    mut batch: Vec<usize>,
+    n: usize,
+    axis: isize,
+) -> Result<Array, String> {
+    batch.push(n);
+    Array::from_vec(data, batch).moveaxis(&[-1], &[axis])
+}
+
+/// `np.cross` over arrays of 2- and 3-vectors. 2-vectors are treated as
+/// having a zero z component; if both are 2-vectors only the z component
+/// of the result is returned.
+pub fn rust_cross(a: &Array, b: &Array, axis: isize) -> Result<Array, String> {
+    let (xs, ys, batch, na, nb) = batched_vectors(a, b, axis)?;
+    if !(2..=3).contains(&na) || !(2..=3).contains(&nb) {
+        return Err(
+            "incompatible dimensions for cross product (dimension must be 2 or 3)".to_string(),
+        );
+    }
+    let count = batch.iter().product::<usize>();
+    let n = if na == 2 && nb == 2 { 1 } else { 3 };
+    let mut out = vec![0.; count * n];
+    let comp = |v: &[f64], i: usize| v.get(i).copied().unwrap_or(0.);
+    out.par_chunks_mut(n).enumerate().for_each(|(i, c)| {
+        let u = &xs[i * na..(i + 1) * na];
+        let v = &ys[i * nb..(i + 1) * nb];
+        let z = u[0] * v[1] - u[1] * v[0];
+        if n == 1 {
+            c[0] = z;
+        } else {
+            c[0] = comp(u, 1) * comp(v, 2) - comp(u, 2) * comp(v, 1);
+            c[1] = comp(u, 2) * comp(v, 0) - comp(u, 0) * comp(v, 2);
+            c[2] = z;
+        }
+    });
+    if n == 1 {
+        Ok(Array::from_vec(out, batch))
+    } else {
+        from_vectors(out, batch, 3, axis)
+    }
+}
+
+/// Scales every vector along `axis` to unit length; zero vectors are left
+/// as zeros instead of turning into NaN.
+pub fn rust_normalize(a: &Array, axis: isize) -> Result<Array, String> {
+    if a.ndim() == 0 {
+        return Err("Parameter must be an array of vectors".to_string());
+    }
+    let moved = a.moveaxis(&[axis], &[-1])?;
+    let n = moved.shape()[moved.ndim() - 1];
+    let mut data = moved.to_vec();
+    if n > 0 {
+        data.par_chunks_mut(n).for_each(|v| {
+            let len = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if len > 0. {
+                v.iter_mut().for_each(|x| *x /= len);
+            }
+        });
+    }
+    Array::from_vec(data, moved.shape().to_vec()).moveaxis(&[-1], &[axis])
+}
+
+/// Angle in radians between paired vectors, in `[0, pi]`.
+pub fn rust_angle_between(a: &Array, b: &Array, axis: isize) -> Result<Array, String> {
+    let (xs, ys, batch, na, nb) = batched_vectors(a, b, axis)?;
+    if na != nb {
+        return Err("Vectors have different lengths".to_string());
+    }
+    let count = batch.iter().product::<usize>();
+    let out = (0..count)
+        .into_par_iter()
+        .map(|i| {
+            let (u, v) = (&xs[i * na..(i + 1) * na], &ys[i * na..(i + 1) * na]);
+            let dot = u.iter().zip(v).map(|(x, y)| x * y).sum::<f64>();
+            let nu = u.iter().map(|x| x * x).sum::<f64>().sqrt();
+            let nv = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+            (dot / (nu * nv)).clamp(-1., 1.).acos()
+        })
+        .collect();
+    Ok(Array::from_vec(out, batch))
+}
+
+/// Projection of each vector of `a` onto the paired vector of `b`.
+pub fn rust_project(a: &Array, b: &Array, axis: isize) -> Result<Array, String> {
+    let (xs, ys, batch, na, nb) = batched_vectors(a, b, axis)?;
+    if na != nb {
+        return Err("Vectors have different lengths".to_string());
+    }
+    let count = batch.iter().product::<usize>();
+    let mut out = vec![0.; count * na];
+    if na > 0 {
+        out.par_chunks_mut(na).enumerate().for_each(|(i, p)| {
+            let (u, v) = (&xs[i * na..(i + 1) * na], &ys[i * na..(i + 1) * na]);
+            let scale = u.iter().zip(v).map(|(x, y)| x * y).sum::<f64>()
+                / v.iter().map(|y| y * y).sum::<f64>();
+            p.iter_mut().zip(v).for_each(|(p, &y)| *p = scale * y);
+        });
+    }
+    from_vectors(out, batch, na, axis)
+}
+
+#[pyfunction(axis = "-1")]
+pub fn cross(a: &PyAny, b: &PyAny, axis: isize) -> PyResult<Array> {
+    rust_cross(&to_array(a)?, &to_array(b)?, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(axis = "-1")]
+pub fn normalize(a: &PyAny, axis: isize) -> PyResult<Array> {
+    rust_normalize(&to_array(a)?, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(axis = "-1")]
+pub fn angle_between(a: &PyAny, b: &PyAny, axis: isize) -> PyResult<Array> {
+    rust_angle_between(&to_array(a)?, &to_array(b)?, axis).map_err(PyTypeError::new_err)
+}
+
+#[pyfunction(axis = "-1")]
+pub fn project(a: &PyAny, b: &PyAny, axis: isize) -> PyResult<Array> {
+    rust_project(&to_array(a)?, &to_array(b)?, axis).map_err(PyTypeError::new_err)
+}
+
 mod test {
     #[allow(dead_code)]
     fn generate_vectors() -> (Vec<f64>, Vec<f64>) {
@@ -100,4 +239,43 @@ mod test {
         dbg!(ds_stop / 3);
         dbg!(dr_stop / 3);
     }
+
+    #[test]
+    fn cross_test() {
+        use crate::array::Array;
+        let x = Array::from_vec(vec![1., 0., 0., 0., 1., 0.], vec![2, 3]);
+        let y = Array::from_vec(vec![0., 1., 0.], vec![3]);
+        let c = super::rust_cross(&x, &y, -1).unwrap();
+        assert_eq!(c.shape(), &[2, 3]);
+        assert_eq!(c.to_vec(), vec![0., 0., 1., 0., 0., 0.]);
+
+        let u = Array::from_vec(vec![1., 2.], vec![2]);
+        let v = Array::from_vec(vec![3., 4.], vec![2]);
+        let z = super::rust_cross(&u, &v, -1).unwrap();
+        assert_eq!(z.ndim(), 0);
+        assert_eq!(z.get(&[]), -2.);
+        assert_eq!(
+            super::rust_cross(&u, &y, -1).unwrap().to_vec(),
+            vec![0., 0., 1.]
+        );
+
+        let cols = super::rust_cross(&x.t(), &y.expand_dims(1), 0).unwrap();
+        assert_eq!(cols.shape(), &[3, 2]);
+        assert_eq!(cols.to_vec(), c.t().to_vec());
+    }
+
+    #[test]
+    fn geometry_test() {
+        use crate::array::Array;
+        let a = Array::from_vec(vec![3., 4., 0., 0.], vec![2, 2]);
+        assert_eq!(
+            super::rust_normalize(&a, -1).unwrap().to_vec(),
+            vec![0.6, 0.8, 0., 0.]
+        );
+        let x = Array::from_vec(vec![1., 0.], vec![2]);
+        let angles = super::rust_angle_between(&a, &x, -1).unwrap().to_vec();
+        assert!((angles[0] - (0.8f64).atan2(0.6)).abs() < 1e-12);
+        let p = super::rust_project(&a, &x, -1).unwrap();
+        assert_eq!(p.to_vec(), vec![3., 0., 0., 0.]);
+    }
 }
@@ -50,6 +50,21 @@ test_function("outer",
               lambda: nw.outer(xs, ys),
               lambda o1, o2: np.linalg.norm(o1-o2))
 
+# cross product
+N = 1_000_000
+P = [[rd.random() for _ in range(3)] for _ in range(N)]
+Q = [[rd.random() for _ in range(3)] for _ in range(N)]
+test_function("cross",
+              lambda: np.cross(P, Q),
+              lambda: nw.cross(P, Q),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# batched normalize
+test_function("normalize",
+              lambda: P / np.linalg.norm(P, axis=-1, keepdims=True),
+              lambda: nw.normalize(P),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
 # trace
 N = 500
 A = [[rd.random() for _ in range(N)] for _ in range(N)]
@@ -71,19 +86,185 @@ test_function("det",
 test_function("transpose",
               lambda: np.transpose(A),
               lambda: nw.transpose(A),
-              lambda o1, o2: np.linalg.norm(o1-o2))
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# n-d transpose
+T = nw.Array([[[rd.random() for _ in range(50)] for _ in range(40)] for _ in range(30)])
+T_np = np.array(T.tolist())
+test_function("transpose (axes)",
+              lambda: np.transpose(T_np, (1, 2, 0)),
+              lambda: nw.transpose(T, [1, 2, 0]),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+test_function("moveaxis",
+              lambda: np.moveaxis(T_np, 0, -1),
+              lambda: nw.moveaxis(T, 0, -1),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
 
 # matmul
 B = [[rd.random() for _ in range(N)] for _ in range(N)]
 test_function("matmul",
               lambda: np.matmul(A, B),
               lambda: nw.matmul(A, B),
-              lambda o1, o2: np.linalg.norm(o1-o2))
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
 # test_function("matmul2",
 #               lambda: np.matmul(A, B),
 #               lambda: nw.matmul2(A, B),
 #               lambda o1, o2: np.linalg.norm(o1-o2))
 
+# elementwise arithmetic with broadcasting
+A_nw, B_nw = nw.Array(A), nw.Array(B)
+A_np, B_np = np.array(A), np.array(B)
+test_function("add (broadcast)",
+              lambda: A_np + B_np[0],
+              lambda: A_nw + B_nw[0],
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+test_function("power",
+              lambda: np.power(A_np, 3),
+              lambda: nw.power(A_nw, 3),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+test_function("@ operator",
+              lambda: A_np @ B_np,
+              lambda: A_nw @ B_nw,
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# unary ufuncs
+test_function("exp",
+              lambda: np.exp(A_np),
+              lambda: nw.exp(A_nw),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+test_function("sin (strided)",
+              lambda: np.sin(A_np.T),
+              lambda: nw.sin(A_nw.T),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# reductions
+test_function("sum (axis=0)",
+              lambda: np.sum(A_np, axis=0),
+              lambda: nw.sum(A_nw, axis=0),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+test_function("std",
+              lambda: np.std(A_np, ddof=1),
+              lambda: nw.std(A_nw, ddof=1),
+              lambda o1, o2: abs(o2-o1))
+test_function("argmax (axis=1)",
+              lambda: np.argmax(A_np, axis=1),
+              lambda: nw.argmax(A_nw, axis=1),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# array creation
+test_function("linspace",
+              lambda: np.linspace(0, 1, 10_000_000),
+              lambda: nw.linspace(0, 1, 10_000_000),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+test_function("eye",
+              lambda: np.eye(3000, k=1),
+              lambda: nw.eye(3000, k=1),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# shape manipulation
+test_function("concatenate",
+              lambda: np.concatenate([A_np, B_np], axis=1),
+              lambda: nw.concatenate([A_nw, B_nw], axis=1),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+test_function("pad",
+              lambda: np.pad(A_np, 3, mode="edge"),
+              lambda: nw.pad(A_nw, 3, mode="edge"),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# fancy indexing
+idx = [rd.randrange(len(A)) for _ in range(len(A))]
+test_function("fancy indexing",
+              lambda: A_np[idx],
+              lambda: A_nw[idx],
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+for key in ["x", b"x", ["x"]]:
+    try:
+        A_nw[key]
+        raise AssertionError(f"indexing with {key!r} should fail")
+    except (IndexError, TypeError):
+        pass
+
+test_function("where",
+              lambda: np.where(A_np, A_np, B_np),
+              lambda: nw.where(A_nw, A_nw, B_nw),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# sorting
+test_function("sort",
+              lambda: np.sort(A_np, axis=1),
+              lambda: nw.sort(A_nw, axis=1),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+test_function("unique",
+              lambda: np.unique(np.round(A_np, 3)),
+              lambda: nw.unique(nw.Array(np.round(A_np, 3).tolist())),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# scans
+xs_long = [rd.random() for _ in range(10_000_000)]
+test_function("cumsum",
+              lambda: np.cumsum(xs_long),
+              lambda: nw.cumsum(xs_long),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+test_function("diff",
+              lambda: np.diff(A_np, axis=0),
+              lambda: nw.diff(A_nw, axis=0),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# npy round trip
+np.save("/tmp/numpar_test.npy", A_np)
+test_function("load",
+              lambda: np.load("/tmp/numpar_test.npy"),
+              lambda: nw.load("/tmp/numpar_test.npy"),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# text round trip
+np.savetxt("/tmp/numpar_test.csv", A_np, delimiter=",")
+test_function("loadtxt",
+              lambda: np.loadtxt("/tmp/numpar_test.csv", delimiter=","),
+              lambda: nw.loadtxt("/tmp/numpar_test.csv", delimiter=","),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+A_np.tofile("/tmp/numpar_test.bin")
+test_function("fromfile",
+              lambda: np.fromfile("/tmp/numpar_test.bin"),
+              lambda: nw.fromfile("/tmp/numpar_test.bin"),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+test_function("matmul_blocked",
+              lambda: A_np @ B_np,
+              lambda: nw.matmul_blocked(A_nw, B_nw, memory_budget=1 << 20),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+test_function("memmap matmul",
+              lambda: np.load("/tmp/numpar_test.npy", mmap_mode="r") @ B_np,
+              lambda: nw.matmul(nw.load("/tmp/numpar_test.npy", mmap_mode="r"), B_nw),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# fused expression evaluation
+test_function("evaluate",
+              lambda: A_np*B_np + A_np*np.sin(B_np),
+              lambda: nw.evaluate("a*b + a*sin(b)", {"a": A_nw, "b": B_nw}),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# multi_dot
+C = [[rd.random() for _ in range(20)] for _ in range(N)]
+v = [rd.random() for _ in range(N)]
+test_function("multi_dot",
+              lambda: np.linalg.multi_dot([A, B, C, np.array(C).T, v]),
+              lambda: nw.multi_dot([A, B, C, nw.Array(C).T, v]),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# einsum
+test_function("einsum",
+              lambda: np.einsum("ij,jk,kl->il", A_np, B_np, A_np, optimize=True),
+              lambda: nw.einsum("ij,jk,kl->il", A_nw, B_nw, A_nw),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
 # mat_pow
 E = 50
 test_function("matrix_power",
@@ -105,9 +286,66 @@ test_function("solve",
               lambda: np.linalg.solve(A, b),
               lambda: nw.solve(A, b),
               lambda o1, o2: np.linalg.norm(o1-o2))
+test_function("solve_blocked",
+              lambda: np.linalg.solve(A, b),
+              lambda: nw.solve_blocked(A, b, memory_budget=1 << 20),
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+
+# krylov solvers on a sparse SPD system, against the dense numpy solve
+K_N = 2_000
+K_rows = list(range(K_N)) + list(range(1, K_N)) + list(range(K_N - 1))
+K_cols = list(range(K_N)) + list(range(K_N - 1)) + list(range(1, K_N))
+K_vals = [4.0] * K_N + [-1.0] * (2 * K_N - 2)
+K_nw = nw.coo_matrix((K_vals, (K_rows, K_cols)), shape=(K_N, K_N)).tocsr()
+K_np = np.array(K_nw.toarray().tolist())
+k_b = [rd.random() for _ in range(K_N)]
+for solver in ["cg", "gmres", "bicgstab", "minres"]:
+    test_function(solver,
+                  lambda: np.linalg.solve(K_np, k_b),
+                  lambda: getattr(nw, solver)(K_nw, k_b, tol=1e-10)[0],
+                  lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+K_op = nw.LinearOperator((K_N, K_N), lambda x: K_nw @ x, rmatvec=lambda x: x @ K_nw)
+test_function("cg on a LinearOperator",
+              lambda: np.linalg.solve(K_np, k_b),
+              lambda: nw.cg(K_op, k_b, tol=1e-10)[0],
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+for name, precond in [("jacobi", nw.jacobi), ("ssor", nw.ssor), ("ilu0", nw.ilu0), ("ic0", nw.ic0)]:
+    K_M = precond(K_nw)
+    test_function("gmres with " + name,
+                  lambda: np.linalg.solve(K_np, k_b),
+                  lambda: nw.gmres(K_nw, k_b, tol=1e-10, M=K_M)[0],
+                  lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
 
 # matrix_rank
 test_function("matrix_rank",
               lambda: np.linalg.matrix_rank(A),
               lambda: nw.matrix_rank(A),
               lambda o1, o2: np.linalg.norm(o1-o2))
+
+# sparse matrix-vector and matrix-dense products, against the dense numpy product
+S_N = 20_000
+S_nnz = 200_000
+S_nw = nw.coo_matrix(([rd.random() for _ in range(S_nnz)],
+                      ([rd.randrange(S_N) for _ in range(S_nnz)], [rd.randrange(S_N) for _ in range(S_nnz)])),
+                     shape=(S_N, S_N)).tocsr()
+S_rows = np.repeat(np.arange(S_N), np.diff(S_nw.indptr))
+S_np = np.zeros((S_N, S_N))
+np.add.at(S_np, (S_rows, S_nw.indices), np.array(S_nw.data.tolist()))
+s_x = [rd.random() for _ in range(S_N)]
+test_function("sparse matvec",
+              lambda: S_np @ np.array(s_x),
+              lambda: S_nw @ s_x,
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+S_B = [[rd.random() for _ in range(16)] for _ in range(S_N)]
+test_function("sparse matmat",
+              lambda: S_np @ np.array(S_B),
+              lambda: S_nw @ S_B,
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.tolist())))
+S_small = nw.coo_matrix(([rd.random() for _ in range(S_nnz // 10)],
+                         ([rd.randrange(2_000) for _ in range(S_nnz // 10)],
+                          [rd.randrange(2_000) for _ in range(S_nnz // 10)])), shape=(2_000, 2_000)).tocsr()
+S_small_np = np.array(S_small.toarray().tolist())
+test_function("sparse spgemm",
+              lambda: S_small_np @ S_small_np,
+              lambda: S_small @ S_small,
+              lambda o1, o2: np.linalg.norm(o1-np.array(o2.toarray().tolist())))